@@ -1,3 +1,60 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 fn main() {
+  // Exposed to the app through `build_info::get_build_info`
+  let git_hash = Command::new("git")
+    .args(["rev-parse", "--short", "HEAD"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .and_then(|output| String::from_utf8(output.stdout).ok())
+    .map(|hash| hash.trim().to_string())
+    .filter(|hash| !hash.is_empty())
+    .unwrap_or_else(|| "unknown".to_string());
+  let build_timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or(0);
+
+  // tauri_build prints its own rerun-if-changed lines, which stop Cargo from re-running
+  // this script on every change, so ask for a re-run whenever HEAD moves
+  for path in git_head_files() {
+    println!("cargo:rerun-if-changed={}", path.display());
+  }
+
+  println!("cargo:rustc-env=BUILD_GIT_HASH={}", git_hash);
+  println!("cargo:rustc-env=BUILD_TIMESTAMP={}", build_timestamp);
+  println!("cargo:rustc-env=BUILD_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+
   tauri_build::build()
 }
+
+/// HEAD, the ref it points to and packed-refs, whichever exist. Missing paths would
+/// make Cargo re-run the script on every build.
+fn git_head_files() -> Vec<PathBuf> {
+  let dirs: Vec<PathBuf> = Command::new("git")
+    .args(["rev-parse", "--git-dir", "--git-common-dir"])
+    .output()
+    .ok()
+    .filter(|output| output.status.success())
+    .and_then(|output| String::from_utf8(output.stdout).ok())
+    .map(|stdout| stdout.lines().map(|line| PathBuf::from(line.trim())).collect())
+    .unwrap_or_default();
+  let (git_dir, common_dir) = match dirs.as_slice() {
+    [git_dir, common_dir] => (git_dir, common_dir),
+    _ => return Vec::new(),
+  };
+
+  let head = git_dir.join("HEAD");
+  let mut files = vec![head.clone(), common_dir.join("packed-refs")];
+  if let Some(reference) = std::fs::read_to_string(&head)
+    .ok()
+    .and_then(|contents| contents.strip_prefix("ref:").map(|r| r.trim().to_string()))
+  {
+    files.push(common_dir.join(reference));
+  }
+  files.retain(|path| path.exists());
+  files
+}
@@ -0,0 +1,106 @@
+use crate::ledger::redact_url;
+use log::Level;
+
+/// Log target for security-relevant events, so they can be filtered or routed separately
+pub const AUDIT_TARGET: &str = "audit";
+
+/// Field names containing any of these are never logged verbatim
+const SECRET_FIELD_MARKERS: &[&str] = &[
+    "password",
+    "secret",
+    "key",
+    "token",
+    "credential",
+    "signature",
+    "plaintext",
+];
+
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_FIELD_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Render an audit line as `event=<event> name=value ...`. Secret-looking fields are
+/// replaced by `[REDACTED]` and URLs lose their presigned credentials.
+pub fn format_audit_event(event: &str, fields: &[(&str, &str)]) -> String {
+    let mut line = format!("event={}", event);
+    for (name, value) in fields {
+        let value = if is_secret_field(name) {
+            "[REDACTED]".to_string()
+        } else if value.starts_with("http://") || value.starts_with("https://") {
+            redact_url(value)
+        } else {
+            value.to_string()
+        };
+        line.push_str(&format!(" {}={:?}", name, value));
+    }
+    line
+}
+
+/// Log a security-sensitive operation under the `audit` target without secrets
+pub fn audit_log(level: Level, event: &str, fields: &[(&str, &str)]) {
+    log::log!(target: AUDIT_TARGET, level, "{}", format_audit_event(event, fields));
+}
+
+#[cfg(test)]
+pub(crate) mod test_capture {
+    use std::sync::{Mutex, Once};
+
+    struct CaptureLogger;
+
+    static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static INIT: Once = Once::new();
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            if let Ok(mut lines) = LINES.lock() {
+                lines.push(format!("{} {} {}", record.level(), record.target(), record.args()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Install the capturing logger for this test binary (once)
+    pub fn install() {
+        INIT.call_once(|| {
+            let _ = log::set_logger(&CaptureLogger);
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    /// Captured lines containing `needle`
+    pub fn lines_containing(needle: &str) -> Vec<String> {
+        LINES
+            .lock()
+            .map(|lines| lines.iter().filter(|line| line.contains(needle)).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_fields_are_redacted() {
+        let line = format_audit_event(
+            "profile_import",
+            &[
+                ("profile", "work"),
+                ("secretAccessKey", "wJalrXUtnFEMI"),
+                ("password", "hunter2"),
+                ("source", "https://host/obj?X-Amz-Signature=abc&v=1"),
+            ],
+        );
+
+        assert_eq!(
+            line,
+            r#"event=profile_import profile="work" secretAccessKey="[REDACTED]" password="[REDACTED]" source="https://host/obj?v=1""#
+        );
+    }
+}
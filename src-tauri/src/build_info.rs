@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Version and build metadata of the backend, for bug reports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Short commit hash, or "unknown" when built outside a git checkout
+    pub git_hash: String,
+    /// Unix seconds when the build script ran
+    pub build_timestamp: u64,
+    pub target_triple: String,
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("BUILD_GIT_HASH").to_string(),
+        build_timestamp: env!("BUILD_TIMESTAMP").parse().unwrap_or(0),
+        target_triple: env!("BUILD_TARGET").to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_build_info() -> BuildInfo {
+    build_info()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_is_populated() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_hash.is_empty());
+        assert!(info.build_timestamp > 0);
+        assert!(!info.target_triple.is_empty());
+    }
+}
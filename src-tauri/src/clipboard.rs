@@ -0,0 +1,194 @@
+use crate::config::SecureString;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Minimal clipboard access so the clearing logic can run against a mock
+pub trait ClipboardBackend: Send + Sync {
+    /// Current text content, `None` when the clipboard holds no text
+    fn read_text(&self) -> Result<Option<String>, String>;
+    fn clear(&self) -> Result<(), String>;
+}
+
+/// System clipboard backed by `arboard`
+pub struct SystemClipboard;
+
+impl ClipboardBackend for SystemClipboard {
+    fn read_text(&self) -> Result<Option<String>, String> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        match clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+        clipboard.clear().map_err(|e| e.to_string())
+    }
+}
+
+/// Wakes pending clears early, see `on_app_suspend`
+fn clear_now_signal() -> &'static Notify {
+    static SIGNAL: OnceLock<Notify> = OnceLock::new();
+    SIGNAL.get_or_init(Notify::new)
+}
+
+/// Snapshot the clipboard now and clear it after `delay` if it still holds the same text.
+/// Returns whether the clipboard was cleared.
+pub async fn clear_clipboard_if_unchanged(
+    backend: Arc<dyn ClipboardBackend>,
+    delay: Duration,
+) -> Result<bool, String> {
+    clear_clipboard_if_unchanged_with(backend, delay, clear_now_signal()).await
+}
+
+/// `clear_clipboard_if_unchanged` that stops waiting as soon as `clear_now` is notified
+async fn clear_clipboard_if_unchanged_with(
+    backend: Arc<dyn ClipboardBackend>,
+    delay: Duration,
+    clear_now: &Notify,
+) -> Result<bool, String> {
+    // Listen before taking the snapshot so a suspend right after it isn't missed
+    let woken = clear_now.notified();
+    tokio::pin!(woken);
+    woken.as_mut().enable();
+
+    let snapshot = match backend.read_text()? {
+        Some(text) => SecureString::new(text),
+        None => return Ok(false),
+    };
+
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => {}
+        _ = woken => {}
+    }
+
+    let current = match backend.read_text()? {
+        Some(text) => SecureString::new(text),
+        None => return Ok(false),
+    };
+
+    // Something else was copied in the meantime, leave it alone
+    if current.as_bytes() != snapshot.as_bytes() {
+        return Ok(false);
+    }
+
+    backend.clear()?;
+    Ok(true)
+}
+
+/// Schedule clearing the clipboard, intended to be called right after a secret was pasted
+#[tauri::command]
+pub async fn clear_clipboard_after(secs: u64) -> Result<(), String> {
+    tauri::async_runtime::spawn(async move {
+        let backend: Arc<dyn ClipboardBackend> = Arc::new(SystemClipboard);
+        if let Err(e) = clear_clipboard_if_unchanged(backend, Duration::from_secs(secs)).await {
+            log::warn!("Failed to clear clipboard: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Clear any secret still waiting on `clear_clipboard_after` right away, so it isn't
+/// left on the clipboard while the machine sleeps. Anything copied since is kept.
+#[tauri::command]
+pub fn on_app_suspend() {
+    clear_now_signal().notify_waiters();
+}
+
+/// Same as `on_app_suspend`, for when the suspend went unnoticed: a clear still pending
+/// after waking is for a secret copied before the sleep, so it happens now
+#[tauri::command]
+pub fn on_app_resume() {
+    clear_now_signal().notify_waiters();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct MockClipboard {
+        contents: Mutex<Option<String>>,
+    }
+
+    impl MockClipboard {
+        fn with_text(text: &str) -> Arc<Self> {
+            Arc::new(MockClipboard {
+                contents: Mutex::new(Some(text.to_string())),
+            })
+        }
+
+        fn set_text(&self, text: &str) {
+            *self.contents.lock().unwrap() = Some(text.to_string());
+        }
+
+        fn text(&self) -> Option<String> {
+            self.contents.lock().unwrap().clone()
+        }
+    }
+
+    impl ClipboardBackend for MockClipboard {
+        fn read_text(&self) -> Result<Option<String>, String> {
+            Ok(self.text())
+        }
+
+        fn clear(&self) -> Result<(), String> {
+            *self.contents.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clears_when_value_unchanged() {
+        let clipboard = MockClipboard::with_text("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY");
+
+        let cleared = clear_clipboard_if_unchanged(clipboard.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        assert!(cleared);
+        assert_eq!(clipboard.text(), None);
+    }
+
+    #[tokio::test]
+    async fn test_keeps_clipboard_when_value_changed() {
+        let clipboard = MockClipboard::with_text("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY");
+
+        let task = tokio::spawn(clear_clipboard_if_unchanged(
+            clipboard.clone(),
+            Duration::from_millis(200),
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        clipboard.set_text("something unrelated");
+
+        let cleared = task.await.unwrap().unwrap();
+
+        assert!(!cleared);
+        assert_eq!(clipboard.text().as_deref(), Some("something unrelated"));
+    }
+
+    #[tokio::test]
+    async fn test_suspend_clears_pending_secret_immediately() {
+        let clipboard = MockClipboard::with_text("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY");
+        let clear_now = Arc::new(Notify::new());
+
+        let pending = Arc::clone(&clear_now);
+        let backend = clipboard.clone();
+        let task = tokio::spawn(async move {
+            clear_clipboard_if_unchanged_with(backend, Duration::from_secs(3600), &pending).await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        clear_now.notify_waiters();
+
+        let cleared = tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("suspend should cut the wait short")
+            .unwrap()
+            .unwrap();
+        assert!(cleared);
+        assert_eq!(clipboard.text(), None);
+    }
+}
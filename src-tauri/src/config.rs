@@ -1,22 +1,28 @@
+use crate::audit::audit_log;
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     Aes256Gcm, Nonce,
 };
 use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::ChaCha20Poly1305;
 use pbkdf2::{
     password_hash::{PasswordHasher, SaltString},
     Pbkdf2,
 };
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager};
 use zeroize::ZeroizeOnDrop;
 use hmac::Hmac;
+use log::Level;
 use sha2::Sha256;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EncryptedConfig {
     pub data: String,
     pub salt: String,
@@ -27,6 +33,16 @@ pub struct EncryptedConfig {
     pub algorithm: String,
     #[serde(default = "default_iterations")]
     pub iterations: u32,
+    /// Key derivation function; absent for PBKDF2, which every envelope used before
+    /// others were supported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<String>,
+    /// Argon2id memory cost in KiB; `iterations` holds its time cost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_kib: Option<u32>,
+    /// Argon2id lanes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallelism: Option<u32>,
 }
 
 fn default_version() -> String {
@@ -81,6 +97,32 @@ impl std::fmt::Debug for SecureString {
     }
 }
 
+/// Parsed JSON whose strings, object keys included, are zeroed on drop
+struct SecureJson(serde_json::Value);
+
+impl Drop for SecureJson {
+    fn drop(&mut self) {
+        zeroize_json(&mut self.0);
+    }
+}
+
+fn zeroize_json(value: &mut serde_json::Value) {
+    use zeroize::Zeroize;
+
+    match value {
+        serde_json::Value::String(text) => text.zeroize(),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(zeroize_json),
+        serde_json::Value::Object(map) => {
+            // Keys can't be borrowed mutably in place, so take the entries out
+            for (mut key, mut item) in std::mem::take(map) {
+                key.zeroize();
+                zeroize_json(&mut item);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Secure key that automatically zeros memory on drop
 #[derive(ZeroizeOnDrop)]
 pub struct SecureKey {
@@ -111,318 +153,2848 @@ pub enum ConfigError {
     InvalidPassword,
     #[error("Config file not found")]
     ConfigNotFound,
+    #[error("Verification failed: {0}")]
+    VerificationFailed(String),
+    #[error("Export file already exists: {0}")]
+    ExportExists(String),
+    #[error("Profile already exists: {0}")]
+    ProfileExists(String),
+    #[error("Invalid config: {}", .0.join("; "))]
+    InvalidConfig(Vec<String>),
+    #[error("Invalid password hint: {0}")]
+    InvalidHint(String),
+    #[error("A password is required for {0} exports")]
+    PasswordRequired(String),
+    #[error("Unknown export format: {0}")]
+    UnknownExportFormat(String),
+    #[error("Config was written by a newer app version (format {found}, this app reads up to {max_supported})")]
+    UnsupportedVersion { found: String, max_supported: String },
+    #[error("Password does not meet the policy: {}", .0.join(", "))]
+    WeakPassword(Vec<String>),
+    #[error("No config entry with id {0}")]
+    ConfigEntryNotFound(String),
+    #[error("Field cannot be cleared: {0}")]
+    FieldNotClearable(String),
+    #[error("Secure random numbers unavailable: {0}")]
+    RandomnessUnavailable(String),
+    #[error("Checking file permissions is not supported on this platform")]
+    PermissionsUnsupported,
+    #[error("No migration is awaiting verification")]
+    NoPendingMigration,
 }
 
-pub struct ConfigManager {
-    config_dir: PathBuf,
+/// Size of each overwrite write during secure deletion
+const SECURE_DELETE_CHUNK_SIZE: usize = 1024 * 1024;
+const SECURE_DELETE_PASSES: u32 = 2;
+
+/// Progress of a secure delete, reported after every chunk written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecureDeleteProgress {
+    pub path: String,
+    pub pass: u32,
+    pub total_passes: u32,
+    pub bytes_written: u64,
+    pub total_bytes: u64,
 }
 
-const ENCRYPTION_VERSION: &str = "1.0";
-const ENCRYPTION_ALGORITHM: &str = "AES-256-GCM";
-const PBKDF2_ITERATIONS: u32 = 100_000; // Increased iterations for better security
+/// Overwrite a file with random data, then zeros, before removing it.
+pub fn secure_remove_file(path: &Path) -> std::io::Result<()> {
+    secure_remove_file_with_progress(path, |_| {})
+}
 
-impl ConfigManager {
-    pub fn new(app_handle: &AppHandle) -> Result<Self, ConfigError> {
-        let config_dir = app_handle
-            .path()
-            .app_config_dir()
-            .map_err(|e| ConfigError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+/// Streaming variant of `secure_remove_file` that overwrites in fixed-size chunks
+/// (so huge files never need a full-size buffer) and reports progress per chunk.
+pub fn secure_remove_file_with_progress<F>(path: &Path, mut on_progress: F) -> std::io::Result<()>
+where
+    F: FnMut(SecureDeleteProgress),
+{
+    use std::io::{Seek, SeekFrom, Write};
 
-        // Create config directory if it doesn't exist
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)?;
-        }
+    let total_bytes = fs::metadata(path)?.len();
+    if total_bytes > 0 {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        let mut buffer = vec![0u8; SECURE_DELETE_CHUNK_SIZE];
+
+        for pass in 1..=SECURE_DELETE_PASSES {
+            file.seek(SeekFrom::Start(0))?;
+            let mut bytes_written = 0u64;
+
+            while bytes_written < total_bytes {
+                let chunk_len = (total_bytes - bytes_written).min(buffer.len() as u64) as usize;
+                let chunk = &mut buffer[..chunk_len];
+                if pass == 1 {
+                    // First pass: random data
+                    OsRng.fill_bytes(chunk);
+                } else {
+                    // Final pass: zeros
+                    chunk.fill(0);
+                }
+                file.write_all(chunk)?;
+                bytes_written += chunk_len as u64;
+
+                on_progress(SecureDeleteProgress {
+                    path: path.to_string_lossy().to_string(),
+                    pass,
+                    total_passes: SECURE_DELETE_PASSES,
+                    bytes_written,
+                    total_bytes,
+                });
+            }
 
-        Ok(ConfigManager { config_dir })
+            file.sync_data()?;
+        }
     }
 
-    fn get_config_path(&self) -> PathBuf {
-        self.config_dir.join("config.encrypted")
+    fs::remove_file(path)
+}
+
+/// Restrict a file to owner read/write only
+fn restrict_permissions(path: &Path) -> Result<(), ConfigError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o600); // Owner read/write only
+        fs::set_permissions(path, perms)?;
     }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
 
-    fn derive_key(&self, password: &SecureString, salt: &[u8]) -> Result<SecureKey, ConfigError> {
-        let salt_string = SaltString::encode_b64(salt)
-            .map_err(|e| ConfigError::Encryption(format!("Salt encoding error: {}", e)))?;
+/// When this process last wrote each file, so watchers can ignore our own saves
+fn self_writes() -> &'static Mutex<HashMap<PathBuf, Instant>> {
+    static SELF_WRITES: OnceLock<Mutex<HashMap<PathBuf, Instant>>> = OnceLock::new();
+    SELF_WRITES.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-        // Use custom PBKDF2 with higher iteration count
-        let mut key = [0u8; 32];
-        pbkdf2::pbkdf2::<Hmac<Sha256>>(
-            password.as_bytes(),
-            salt,
-            PBKDF2_ITERATIONS,
-            &mut key,
-        );
-        // PBKDF2 doesn't return an error in this implementation
+/// Take the per-file lock serialising the operations that rewrite a config or vault
+/// file. Every command builds its own `ConfigManager`, so this has to be process-wide.
+/// Reads don't take it; the rename in `write_atomically` already keeps them from seeing
+/// a partial file. It blocks, so async commands reach it through `run_config_task`, and
+/// callers check the password first so a wrong one never holds it.
+fn lock_for_write(path: &Path) -> MutexGuard<'static, ()> {
+    // One lock per config or vault path, kept for the life of the process
+    static LOCKS: OnceLock<Mutex<HashMap<PathBuf, &'static Mutex<()>>>> = OnceLock::new();
+    let lock = *LOCKS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(path.to_path_buf())
+        .or_insert_with(|| Box::leak(Box::default()));
+    lock.lock().unwrap_or_else(|e| e.into_inner())
+}
 
-        Ok(SecureKey::new(key))
+fn mark_self_write(path: &Path) {
+    if let Ok(mut writes) = self_writes().lock() {
+        writes.insert(path.to_path_buf(), Instant::now());
     }
+}
 
-    fn generate_secure_salt(&self) -> [u8; 32] {
-        let mut salt = [0u8; 32]; // Increased salt size for better security
-        OsRng.fill_bytes(&mut salt);
-        salt
+/// Whether this process wrote `path` within the last `window`
+pub(crate) fn is_recent_self_write(path: &Path, window: Duration) -> bool {
+    self_writes()
+        .lock()
+        .ok()
+        .and_then(|writes| writes.get(path).map(|written| written.elapsed() < window))
+        .unwrap_or(false)
+}
+
+/// fsync the directory holding `path` so a rename into it is durable. Directories
+/// can't be opened for syncing outside Unix, where this is a no-op.
+fn sync_parent_dir(path: &Path) -> Result<(), ConfigError> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent)?.sync_all()?;
     }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
 
-    fn generate_secure_nonce(&self) -> [u8; 12] {
-        let mut nonce = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce);
-        nonce
+/// Drop a leading UTF-8 byte order mark, which editors on Windows like to add
+/// and `serde_json` refuses to parse
+fn strip_bom(contents: &str) -> &str {
+    contents.strip_prefix('\u{feff}').unwrap_or(contents)
+}
+
+/// Whether `restrict_permissions` would leave the file unchanged. Outside Unix there are
+/// no mode bits to check and the ACLs aren't inspected, so this fails with
+/// `PermissionsUnsupported` rather than report the file as restricted.
+fn has_restricted_permissions(path: &Path) -> Result<bool, ConfigError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(fs::metadata(path)?.permissions().mode() & 0o777 == 0o600)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Err(ConfigError::PermissionsUnsupported)
     }
+}
 
-    fn secure_delete_file(&self, path: &PathBuf) -> Result<(), ConfigError> {
-        if path.exists() {
-            // Overwrite file with random data before deletion for secure deletion
-            let file_size = fs::metadata(path)?.len() as usize;
-            if file_size > 0 {
-                let mut random_data = vec![0u8; file_size];
-                OsRng.fill_bytes(&mut random_data);
-                fs::write(path, &random_data)?;
-                
-                // Overwrite with zeros
-                let zero_data = vec![0u8; file_size];
-                fs::write(path, &zero_data)?;
-            }
-            
-            fs::remove_file(path)?;
-        }
-        Ok(())
+/// Whether users other than the owner and group may read the file. Always false
+/// outside Unix, where there are no mode bits to inspect.
+fn is_world_readable(path: &Path) -> Result<bool, ConfigError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(fs::metadata(path)?.permissions().mode() & 0o004 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(false)
     }
+}
 
-    pub fn save_config(&self, config_json: &str, password: &str) -> Result<(), ConfigError> {
-        let secure_password = SecureString::new(password.to_string());
-        let secure_config = SecureString::new(config_json.to_string());
-        
-        // Generate secure random salt and nonce
-        let salt = self.generate_secure_salt();
-        let nonce_bytes = self.generate_secure_nonce();
+/// Options controlling how `save_config_with_options` writes the config
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    /// Re-read and decrypt the saved file, rolling back to the backup on mismatch
+    pub verify: bool,
+    /// Cipher used for the new envelope
+    pub algorithm: CipherAlgorithm,
+    /// fsync the new file and its directory so the save survives a crash or power
+    /// loss right after it returns, at the cost of slower saves
+    pub durable: bool,
+    /// Reject the save with `WeakPassword` unless the password satisfies this policy
+    pub password_policy: Option<PasswordPolicy>,
+}
 
-        // Derive encryption key
-        let secure_key = self.derive_key(&secure_password, &salt)?;
+/// Password requirements an organization can impose. Every rule is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    /// Any character that isn't an ASCII letter or digit
+    pub require_symbol: bool,
+    /// Longest run of the same character allowed, e.g. 2 rejects "aaa"
+    pub max_repeated: Option<usize>,
+}
 
-        // Create cipher
-        let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
-            .map_err(|e| ConfigError::Encryption(format!("Cipher creation error: {}", e)))?;
+/// Outcome of one enabled `PasswordPolicy` rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasswordRuleResult {
+    pub rule: String,
+    pub passed: bool,
+}
 
-        let nonce = Nonce::from_slice(&nonce_bytes);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasswordPolicyReport {
+    pub passed: bool,
+    pub rules: Vec<PasswordRuleResult>,
+}
 
-        // Encrypt the config
-        let encrypted_data = cipher
-            .encrypt(nonce, secure_config.as_bytes())
-            .map_err(|e| ConfigError::Encryption(format!("Encryption failed: {}", e)))?;
+impl PasswordPolicyReport {
+    /// Names of the rules the password broke
+    pub fn failed_rules(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|result| !result.passed)
+            .map(|result| result.rule.clone())
+            .collect()
+    }
+}
 
-        // Create encrypted config structure with metadata
-        let encrypted_config = EncryptedConfig {
-            data: general_purpose::STANDARD.encode(&encrypted_data),
-            salt: general_purpose::STANDARD.encode(&salt),
-            nonce: general_purpose::STANDARD.encode(&nonce_bytes),
-            version: ENCRYPTION_VERSION.to_string(),
-            algorithm: ENCRYPTION_ALGORITHM.to_string(),
-            iterations: PBKDF2_ITERATIONS,
+fn longest_repeat(password: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None;
+    for c in password.chars() {
+        run = if previous == Some(c) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        previous = Some(c);
+    }
+    longest
+}
+
+impl PasswordPolicy {
+    /// Check `password` against each enabled rule. Length counts characters, not bytes.
+    pub fn check(&self, password: &str) -> PasswordPolicyReport {
+        let mut rules = Vec::new();
+        let mut rule = |name: &str, passed: bool| {
+            rules.push(PasswordRuleResult {
+                rule: name.to_string(),
+                passed,
+            })
         };
 
-        // Save to file with secure permissions
-        let config_path = self.get_config_path();
-        let json_data = serde_json::to_string_pretty(&encrypted_config)?;
-        
-        // Write with restricted permissions (owner read/write only)
-        fs::write(&config_path, json_data)?;
-        
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&config_path)?.permissions();
-            perms.set_mode(0o600); // Owner read/write only
-            fs::set_permissions(&config_path, perms)?;
+        if self.min_length > 0 {
+            rule("min_length", password.chars().count() >= self.min_length);
+        }
+        if self.require_lowercase {
+            rule("lowercase", password.chars().any(|c| c.is_lowercase()));
+        }
+        if self.require_uppercase {
+            rule("uppercase", password.chars().any(|c| c.is_uppercase()));
+        }
+        if self.require_digit {
+            rule("digit", password.chars().any(|c| c.is_ascii_digit()));
+        }
+        if self.require_symbol {
+            rule("symbol", password.chars().any(|c| !c.is_ascii_alphanumeric()));
+        }
+        if let Some(max_repeated) = self.max_repeated {
+            rule("max_repeated", longest_repeat(password) <= max_repeated);
         }
 
-        Ok(())
-    }
-
-    pub fn load_config(&self, password: &str) -> Result<String, ConfigError> {
-        let secure_password = SecureString::new(password.to_string());
-        let config_path = self.get_config_path();
-
-        if !config_path.exists() {
-            return Err(ConfigError::ConfigNotFound);
+        PasswordPolicyReport {
+            passed: rules.iter().all(|result| result.passed),
+            rules,
         }
+    }
+}
 
-        // Read encrypted config from file
-        let file_content = fs::read_to_string(config_path)?;
-        let encrypted_config: EncryptedConfig = serde_json::from_str(&file_content)?;
+/// Backoff applied to `load_config` after consecutive wrong passwords: the n-th retry
+/// waits `base_delay * 2^(n-1)`, capped at `max_delay`
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordThrottle {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
 
-        // Validate encryption metadata
-        if encrypted_config.version != ENCRYPTION_VERSION {
-            return Err(ConfigError::Decryption(format!(
-                "Unsupported encryption version: {}",
-                encrypted_config.version
-            )));
+impl Default for PasswordThrottle {
+    fn default() -> Self {
+        PasswordThrottle {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
         }
+    }
+}
 
-        if encrypted_config.algorithm != ENCRYPTION_ALGORITHM {
-            return Err(ConfigError::Decryption(format!(
-                "Unsupported encryption algorithm: {}",
-                encrypted_config.algorithm
-            )));
+impl PasswordThrottle {
+    /// Delay owed before the next attempt after `failures` consecutive failures
+    pub fn delay_for(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
         }
+        let factor = 1u32.checked_shl(failures - 1).unwrap_or(u32::MAX);
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
 
-        // Decode base64 data
-        let encrypted_data = general_purpose::STANDARD
-            .decode(&encrypted_config.data)
-            .map_err(|e| ConfigError::Decryption(format!("Base64 decode error: {}", e)))?;
-
-        let salt = general_purpose::STANDARD
-            .decode(&encrypted_config.salt)
-            .map_err(|e| ConfigError::Decryption(format!("Salt decode error: {}", e)))?;
+/// Consecutive failed unlocks of one config file, persisted so a restart doesn't reset them
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct FailedAttempts {
+    count: u32,
+    /// Unix milliseconds of the most recent failure
+    last_failure_ms: u64,
+}
 
-        let nonce_bytes = general_purpose::STANDARD
-            .decode(&encrypted_config.nonce)
-            .map_err(|e| ConfigError::Decryption(format!("Nonce decode error: {}", e)))?;
+/// Fill `bytes` from the OS CSPRNG. Test builds can swap in a seeded RNG per thread
+/// (see `test_rng`) to produce reproducible envelopes.
+fn fill_random(bytes: &mut [u8]) {
+    #[cfg(test)]
+    if test_rng::fill(bytes) {
+        return;
+    }
+    OsRng.fill_bytes(bytes);
+}
 
-        // Derive decryption key
-        let secure_key = self.derive_key(&secure_password, &salt)?;
+/// Like `fill_random`, but reports an OS RNG failure instead of panicking
+fn try_fill_random(bytes: &mut [u8]) -> Result<(), rand::Error> {
+    #[cfg(test)]
+    if test_rng::fill(bytes) {
+        return Ok(());
+    }
+    OsRng.try_fill_bytes(bytes)
+}
 
-        // Create cipher
-        let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
-            .map_err(|e| ConfigError::Decryption(format!("Cipher creation error: {}", e)))?;
+#[cfg(test)]
+pub(crate) mod test_rng {
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+    use std::cell::{Cell, RefCell};
 
-        let nonce = Nonce::from_slice(&nonce_bytes);
+    thread_local! {
+        static SEEDED: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+        static BROKEN: Cell<bool> = const { Cell::new(false) };
+    }
 
-        // Decrypt the data
-        let decrypted_data = cipher
-            .decrypt(nonce, encrypted_data.as_ref())
-            .map_err(|_| ConfigError::InvalidPassword)?;
+    /// Use a deterministic RNG for salts and nonces generated on this thread
+    pub fn seed(seed: u64) {
+        SEEDED.with(|rng| *rng.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+    }
 
-        // Convert to secure string and then to regular string
-        let secure_config = SecureString::from_bytes(decrypted_data);
-        let config_json = secure_config.as_str()
-            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?
-            .to_string();
+    /// Make the RNG on this thread return only zeros, like a source without entropy
+    pub fn break_rng() {
+        BROKEN.with(|broken| broken.set(true));
+    }
 
-        Ok(config_json)
+    pub fn clear() {
+        SEEDED.with(|rng| *rng.borrow_mut() = None);
+        BROKEN.with(|broken| broken.set(false));
     }
 
-    pub fn config_exists(&self) -> bool {
-        self.get_config_path().exists()
+    pub(super) fn fill(bytes: &mut [u8]) -> bool {
+        if BROKEN.with(Cell::get) {
+            bytes.fill(0);
+            return true;
+        }
+        SEEDED.with(|rng| match rng.borrow_mut().as_mut() {
+            Some(rng) => {
+                rng.fill_bytes(bytes);
+                true
+            }
+            None => false,
+        })
     }
+}
 
-    pub fn delete_config(&self) -> Result<(), ConfigError> {
-        let config_path = self.get_config_path();
-        self.secure_delete_file(&config_path)?;
-        Ok(())
+/// Bytes drawn by `rng_health`
+const RNG_HEALTH_SAMPLE_LEN: usize = 32;
+/// Fewest distinct byte values accepted in a 32-byte sample. A working CSPRNG
+/// falls below this with negligible probability.
+const RNG_MIN_DISTINCT_BYTES: usize = 8;
+
+/// Result of drawing a sample from the OS RNG before generating keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RngHealth {
+    pub healthy: bool,
+    pub sample_len: usize,
+    pub distinct_bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Why `sample` doesn't look random, if it doesn't: all zeros or too few distinct values
+fn random_sample_problem(sample: &[u8]) -> Option<String> {
+    if sample.iter().all(|&byte| byte == 0) {
+        return Some("RNG returned only zero bytes".to_string());
+    }
+    let distinct = distinct_byte_count(sample);
+    if distinct < RNG_MIN_DISTINCT_BYTES.min(sample.len()) {
+        return Some(format!(
+            "RNG returned only {} distinct values in {} bytes",
+            distinct,
+            sample.len()
+        ));
     }
+    None
+}
 
-    pub fn export_config(&self, export_path: &str, config_json: &str) -> Result<(), ConfigError> {
-        let export_path = PathBuf::from(export_path);
-        fs::write(export_path, config_json)?;
-        Ok(())
+fn distinct_byte_count(sample: &[u8]) -> usize {
+    let mut seen = [false; 256];
+    sample.iter().for_each(|&byte| seen[byte as usize] = true);
+    seen.iter().filter(|&&seen| seen).count()
+}
+
+/// Draw a sample from the RNG used for salts and nonces and sanity-check it
+pub fn rng_health() -> RngHealth {
+    let mut sample = [0u8; RNG_HEALTH_SAMPLE_LEN];
+    let error = match try_fill_random(&mut sample) {
+        Ok(()) => random_sample_problem(&sample),
+        Err(e) => Some(format!("RNG unavailable: {}", e)),
+    };
+    RngHealth {
+        healthy: error.is_none(),
+        sample_len: sample.len(),
+        distinct_bytes: distinct_byte_count(&sample),
+        error,
     }
+}
 
-    pub fn import_config(&self, import_path: &str) -> Result<String, ConfigError> {
-        let import_path = PathBuf::from(import_path);
-        if !import_path.exists() {
-            return Err(ConfigError::ConfigNotFound);
+/// Hex characters kept from the SHA-256 of the canonical config
+const FINGERPRINT_LEN: usize = 16;
+
+/// Serialize `value` with object keys sorted at every level, so equal configs give
+/// equal bytes regardless of key order
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(value, out);
+            }
+            out.push('}');
         }
-        let config_json = fs::read_to_string(import_path)?;
-        Ok(config_json)
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
     }
 }
 
-// Tauri commands
-#[tauri::command]
-pub async fn save_config(
-    app_handle: AppHandle,
-    config_json: String,
-    password: String,
-) -> Result<(), String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    config_manager
-        .save_config(&config_json, &password)
-        .map_err(|e| e.to_string())
+/// Short SHA-256 fingerprint of a config's canonical JSON, safe to show and compare
+pub fn fingerprint_config_json(config_json: &str) -> Result<String, ConfigError> {
+    use sha2::Digest;
+
+    let value: serde_json::Value = serde_json::from_str(config_json)?;
+    let mut canonical = String::new();
+    write_canonical_json(&value, &mut canonical);
+    let canonical = SecureString::new(canonical);
+
+    let digest = Sha256::digest(canonical.as_bytes());
+    let mut fingerprint = format!("{:x}", digest);
+    fingerprint.truncate(FINGERPRINT_LEN);
+    Ok(fingerprint)
 }
 
-#[tauri::command]
-pub async fn load_config(app_handle: AppHandle, password: String) -> Result<String, String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    config_manager
-        .load_config(&password)
-        .map_err(|e| e.to_string())
+/// Failed attempts per config file for this process
+fn failed_attempts() -> &'static Mutex<HashMap<PathBuf, FailedAttempts>> {
+    static FAILED_ATTEMPTS: OnceLock<Mutex<HashMap<PathBuf, FailedAttempts>>> = OnceLock::new();
+    FAILED_ATTEMPTS.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-#[tauri::command]
-pub async fn config_exists(app_handle: AppHandle) -> Result<bool, String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    Ok(config_manager.config_exists())
+fn unix_millis_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
 }
 
-#[tauri::command]
-pub async fn delete_config(app_handle: AppHandle) -> Result<(), String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    config_manager.delete_config().map_err(|e| e.to_string())
+/// How `export_config_as` writes the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// The config JSON as-is, readable by anyone with the file
+    PlaintextJson,
+    /// A pretty-printed encrypted envelope, the same shape as `config.encrypted`
+    Encrypted,
+    /// The encrypted envelope as a single base64 line, easy to paste elsewhere
+    Compact,
 }
 
-#[tauri::command]
-pub async fn export_config(
-    app_handle: AppHandle,
-    export_path: String,
-    config_json: String,
-) -> Result<(), String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    config_manager
-        .export_config(&export_path, &config_json)
-        .map_err(|e| e.to_string())
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::PlaintextJson => "plaintext-json",
+            ExportFormat::Encrypted => "encrypted",
+            ExportFormat::Compact => "compact",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, ConfigError> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "plaintext-json" => Ok(ExportFormat::PlaintextJson),
+            "encrypted" => Ok(ExportFormat::Encrypted),
+            "compact" => Ok(ExportFormat::Compact),
+            other => Err(ConfigError::UnknownExportFormat(other.to_string())),
+        }
+    }
+
+    fn needs_password(&self) -> bool {
+        !matches!(self, ExportFormat::PlaintextJson)
+    }
 }
 
-#[tauri::command]
-pub async fn import_config(app_handle: AppHandle, import_path: String) -> Result<String, String> {
-    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
-    config_manager
-        .import_config(&import_path)
-        .map_err(|e| e.to_string())
+/// Decrypted vault contents: profile name to that profile's config
+pub type VaultProfiles = BTreeMap<String, serde_json::Value>;
+
+/// Non-secret summary of a vault profile, readable without the password
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub label: Option<String>,
+    /// Unix seconds of the last save that changed this profile
+    pub last_modified: u64,
+    /// User-entered reminder for the vault password, stored in plain text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
 }
 
-#[tauri::command]
-pub async fn select_export_path(app_handle: AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
+/// A config read by `import_config_checked`, with problems that didn't stop the import
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedConfig {
+    pub config_json: String,
+    pub warnings: Vec<String>,
+}
 
-    use std::sync::{Arc, Mutex};
-    
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = Arc::clone(&result);
-    
-    app_handle
-        .dialog()
-        .file()
-        .add_filter("JSON files", &["json"])
-        .set_file_name("s3-config-export.json")
-        .save_file(move |path| {
-            *result_clone.lock().unwrap() = path;
-        });
-    
-    // In a real implementation, you'd want to use async/await properly
-    // For now, we'll return None as this is just for testing
-    Ok(None)
+/// Outcome for one profile of `upgrade_all_profiles`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileUpgradeResult {
+    pub name: String,
+    /// Whether the profile is now stored under the requested parameters
+    pub upgraded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
-#[tauri::command]
-pub async fn select_import_path(app_handle: AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
+/// Longest password hint accepted, in characters
+pub const MAX_PASSWORD_HINT_LEN: usize = 100;
 
-    use std::sync::{Arc, Mutex};
-    
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = Arc::clone(&result);
-    
-    app_handle
-        .dialog()
-        .file()
-        .add_filter("JSON files", &["json"])
-        .pick_file(move |path| {
-            *result_clone.lock().unwrap() = path;
-        });
-    
-    // In a real implementation, you'd want to use async/await properly
-    // For now, we'll return None as this is just for testing
-    Ok(None)
+/// Names `backup_config_to` tries within one second before giving up
+const MAX_BACKUP_NAME_ATTEMPTS: u32 = 1000;
+
+/// Display label for a profile: its `label` field, falling back to `name`.
+/// Only this string ever leaves the encrypted vault.
+fn profile_label(config: &serde_json::Value) -> Option<String> {
+    ["label", "name"]
+        .iter()
+        .filter_map(|field| config.get(field).and_then(|value| value.as_str()))
+        .map(|label| label.trim().to_string())
+        .find(|label| !label.is_empty())
+}
+
+pub struct ConfigManager {
+    config_dir: PathBuf,
+    throttle: PasswordThrottle,
+}
+
+/// Envelopes from this version on bind their metadata to the ciphertext as AAD
+const ENCRYPTION_VERSION: &str = "1.1";
+/// Original envelope format, encrypted without associated data
+const LEGACY_ENCRYPTION_VERSION: &str = "1.0";
+const ENCRYPTION_ALGORITHM: &str = "AES-256-GCM";
+const CHACHA_ALGORITHM: &str = "ChaCha20-Poly1305";
+const PBKDF2_ITERATIONS: u32 = 100_000; // Increased iterations for better security
+const KEY_DERIVATION: &str = "PBKDF2-HMAC-SHA256";
+const ARGON2_KDF: &str = "Argon2id";
+/// Argon2id costs for new envelopes. They equal the argon2 crate's defaults, which
+/// envelopes written before the costs were recorded were derived with.
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_TIME_COST: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+/// Envelopes asking for more than this multiple of any Argon2id cost are rejected
+/// before deriving, so a crafted file can't make a load exhaust memory or hang
+const ARGON2_MAX_COST_FACTOR: u32 = 4;
+
+const SUPPORTED_ALGORITHMS: &[&str] = &[ENCRYPTION_ALGORITHM, CHACHA_ALGORITHM];
+const SUPPORTED_KDFS: &[&str] = &[KEY_DERIVATION, ARGON2_KDF];
+
+/// Envelope metadata authenticated alongside the ciphertext. Fields are declared in
+/// alphabetical order so the serialized form is canonical.
+#[derive(Serialize)]
+struct EnvelopeAad<'a> {
+    algorithm: &'a str,
+    iterations: u32,
+    /// Left out for PBKDF2 so envelopes written before the KDF was recorded still verify
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kdf: Option<&'a str>,
+    /// Argon2id costs, left out when the envelope doesn't record them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_kib: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallelism: Option<u32>,
+    version: &'a str,
+}
+
+/// Associated data for an envelope: empty for legacy envelopes, otherwise the canonical
+/// JSON of its version, cipher and KDF parameters so none of them can be swapped
+fn envelope_aad(envelope: &EncryptedConfig) -> Result<Vec<u8>, ConfigError> {
+    if envelope.version == LEGACY_ENCRYPTION_VERSION {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::to_vec(&EnvelopeAad {
+        algorithm: &envelope.algorithm,
+        iterations: envelope.iterations,
+        kdf: envelope.kdf.as_deref(),
+        memory_kib: envelope.memory_kib,
+        parallelism: envelope.parallelism,
+        version: &envelope.version,
+    })?)
+}
+
+/// Argon2id costs used to derive a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Argon2Costs {
+    memory_kib: u32,
+    time_cost: u32,
+    parallelism: u32,
+}
+
+impl Default for Argon2Costs {
+    fn default() -> Self {
+        Argon2Costs {
+            memory_kib: ARGON2_MEMORY_KIB,
+            time_cost: ARGON2_TIME_COST,
+            parallelism: ARGON2_PARALLELISM,
+        }
+    }
+}
+
+impl Argon2Costs {
+    /// Costs recorded in `envelope`, defaulting those it predates. The envelope isn't
+    /// authenticated until after the key is derived, so costs above
+    /// `ARGON2_MAX_COST_FACTOR` times the pinned ones are refused here.
+    fn of_envelope(envelope: &EncryptedConfig) -> Result<Self, ConfigError> {
+        let costs = Argon2Costs {
+            memory_kib: envelope.memory_kib.unwrap_or(ARGON2_MEMORY_KIB),
+            time_cost: envelope.iterations,
+            parallelism: envelope.parallelism.unwrap_or(ARGON2_PARALLELISM),
+        };
+        let limits = [
+            ("memory", costs.memory_kib, ARGON2_MEMORY_KIB),
+            ("time", costs.time_cost, ARGON2_TIME_COST),
+            ("parallelism", costs.parallelism, ARGON2_PARALLELISM),
+        ];
+        for (name, value, pinned) in limits {
+            if value > pinned.saturating_mul(ARGON2_MAX_COST_FACTOR) {
+                return Err(ConfigError::Decryption(format!(
+                    "Argon2 {} cost {} exceeds the supported maximum",
+                    name, value
+                )));
+            }
+        }
+        Ok(costs)
+    }
+
+    fn hasher(&self) -> Result<argon2::Argon2<'static>, ConfigError> {
+        let params = argon2::Params::new(self.memory_kib, self.time_cost, self.parallelism, Some(32))
+            .map_err(|e| ConfigError::Decryption(format!("Invalid Argon2 parameters: {}", e)))?;
+        Ok(argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            params,
+        ))
+    }
+}
+
+/// Key derivation function turning the password into the cipher key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KdfAlgorithm {
+    #[default]
+    Pbkdf2Sha256,
+    Argon2id,
+}
+
+impl KdfAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KdfAlgorithm::Pbkdf2Sha256 => KEY_DERIVATION,
+            KdfAlgorithm::Argon2id => ARGON2_KDF,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            KEY_DERIVATION => Some(KdfAlgorithm::Pbkdf2Sha256),
+            ARGON2_KDF => Some(KdfAlgorithm::Argon2id),
+            _ => None,
+        }
+    }
+
+    /// Value of the envelope's `kdf` field, omitted for PBKDF2
+    fn envelope_name(&self) -> Option<&'static str> {
+        match self {
+            KdfAlgorithm::Pbkdf2Sha256 => None,
+            other => Some(other.as_str()),
+        }
+    }
+
+    /// Iteration (time cost) parameter recorded in the envelope
+    fn iterations(&self) -> u32 {
+        match self {
+            KdfAlgorithm::Pbkdf2Sha256 => PBKDF2_ITERATIONS,
+            KdfAlgorithm::Argon2id => ARGON2_TIME_COST,
+        }
+    }
+
+    /// Argon2id memory cost and lanes recorded in the envelope, omitted for PBKDF2
+    fn argon2_costs(&self) -> (Option<u32>, Option<u32>) {
+        match self {
+            KdfAlgorithm::Pbkdf2Sha256 => (None, None),
+            KdfAlgorithm::Argon2id => (Some(ARGON2_MEMORY_KIB), Some(ARGON2_PARALLELISM)),
+        }
+    }
+}
+
+/// `major.minor` of an envelope version string, `None` if it isn't in that form
+fn parse_envelope_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Whether `version` is a well-formed envelope version newer than this build writes
+fn is_future_envelope_version(version: &str) -> bool {
+    match (parse_envelope_version(version), parse_envelope_version(ENCRYPTION_VERSION)) {
+        (Some(found), Some(current)) => found > current,
+        _ => false,
+    }
+}
+
+/// AEAD cipher used to encrypt the config, recorded in the envelope's `algorithm` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CipherAlgorithm {
+    #[default]
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl CipherAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CipherAlgorithm::Aes256Gcm => ENCRYPTION_ALGORITHM,
+            CipherAlgorithm::ChaCha20Poly1305 => CHACHA_ALGORITHM,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            ENCRYPTION_ALGORITHM => Some(CipherAlgorithm::Aes256Gcm),
+            CHACHA_ALGORITHM => Some(CipherAlgorithm::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    fn encrypt(
+        &self,
+        key: &SecureKey,
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ConfigError> {
+        let nonce = Nonce::from_slice(nonce);
+        let payload = Payload { msg: plaintext, aad };
+        let result = match self {
+            CipherAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key.as_bytes())
+                .map_err(|e| ConfigError::Encryption(format!("Cipher creation error: {}", e)))?
+                .encrypt(nonce, payload),
+            CipherAlgorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key.as_bytes())
+                .map_err(|e| ConfigError::Encryption(format!("Cipher creation error: {}", e)))?
+                .encrypt(nonce, payload),
+        };
+        result.map_err(|e| ConfigError::Encryption(format!("Encryption failed: {}", e)))
+    }
+
+    fn decrypt(
+        &self,
+        key: &SecureKey,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, ConfigError> {
+        // Both ciphers use a 96-bit nonce; from_slice panics on any other length
+        if nonce.len() != 12 {
+            return Err(ConfigError::Decryption(format!(
+                "Invalid nonce length: {}",
+                nonce.len()
+            )));
+        }
+        let nonce = Nonce::from_slice(nonce);
+        let payload = Payload { msg: ciphertext, aad };
+        let result = match self {
+            CipherAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key.as_bytes())
+                .map_err(|e| ConfigError::Decryption(format!("Cipher creation error: {}", e)))?
+                .decrypt(nonce, payload),
+            CipherAlgorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key.as_bytes())
+                .map_err(|e| ConfigError::Decryption(format!("Cipher creation error: {}", e)))?
+                .decrypt(nonce, payload),
+        };
+        result.map_err(|_| ConfigError::InvalidPassword)
+    }
+}
+
+/// Cipher and KDF options the backend can read and write
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CryptoCapabilities {
+    pub algorithms: Vec<String>,
+    pub kdfs: Vec<String>,
+    pub default_algorithm: String,
+    pub default_kdf: String,
+    pub default_iterations: u32,
+    pub envelope_version: String,
+}
+
+/// Crypto parameters recorded in an envelope, read without decrypting it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CryptoInfo {
+    pub algorithm: String,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt_len: usize,
+    pub nonce_len: usize,
+    pub version: String,
+}
+
+impl CryptoInfo {
+    fn from_envelope(envelope: &EncryptedConfig) -> Result<Self, ConfigError> {
+        let decoded_len = |field: &str, value: &str| {
+            general_purpose::STANDARD
+                .decode(value)
+                .map(|bytes| bytes.len())
+                .map_err(|e| ConfigError::Decryption(format!("{} decode error: {}", field, e)))
+        };
+
+        Ok(CryptoInfo {
+            algorithm: envelope.algorithm.clone(),
+            // Envelopes only record the KDF when it isn't PBKDF2
+            kdf: envelope.kdf.clone().unwrap_or_else(|| KEY_DERIVATION.to_string()),
+            iterations: envelope.iterations,
+            salt_len: decoded_len("Salt", &envelope.salt)?,
+            nonce_len: decoded_len("Nonce", &envelope.nonce)?,
+            version: envelope.version.clone(),
+        })
+    }
+}
+
+/// Health of one backup of the encrypted config, checked without exposing its contents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupStatus {
+    /// 0 for `config.encrypted.bak`, `N` for `config.encrypted.bak.N`
+    pub index: u32,
+    pub parseable: bool,
+    pub decryptable: bool,
+    /// Envelope version, if the backup parsed
+    pub version: Option<String>,
+}
+
+pub fn crypto_capabilities() -> CryptoCapabilities {
+    CryptoCapabilities {
+        algorithms: SUPPORTED_ALGORITHMS.iter().map(|a| a.to_string()).collect(),
+        kdfs: SUPPORTED_KDFS.iter().map(|k| k.to_string()).collect(),
+        default_algorithm: ENCRYPTION_ALGORITHM.to_string(),
+        default_kdf: KEY_DERIVATION.to_string(),
+        default_iterations: PBKDF2_ITERATIONS,
+        envelope_version: ENCRYPTION_VERSION.to_string(),
+    }
+}
+
+/// Milliseconds one key derivation with `kdf` and its current parameters takes on this
+/// machine. Measured on first use of each KDF and cached for the rest of the session.
+pub fn encryption_time_estimate_ms(kdf: KdfAlgorithm) -> u64 {
+    static PBKDF2_ESTIMATE_MS: OnceLock<u64> = OnceLock::new();
+    static ARGON2_ESTIMATE_MS: OnceLock<u64> = OnceLock::new();
+    let estimate = match kdf {
+        KdfAlgorithm::Pbkdf2Sha256 => &PBKDF2_ESTIMATE_MS,
+        KdfAlgorithm::Argon2id => &ARGON2_ESTIMATE_MS,
+    };
+    *estimate.get_or_init(|| {
+        let mut salt = [0u8; 32];
+        fill_random(&mut salt);
+        let password = SecureString::new("benchmark".to_string());
+        let started = Instant::now();
+        if let Err(e) = derive_secure_key(&password, &salt, kdf, Argon2Costs::default()) {
+            log::warn!("Benchmarking {} failed: {}", kdf.as_str(), e);
+        }
+        // Never report zero so callers can treat the value as a real measurement
+        (started.elapsed().as_millis() as u64).max(1)
+    })
+}
+
+/// Derive the cipher key from `password`. `argon2_costs` only applies to Argon2id.
+fn derive_secure_key(
+    password: &SecureString,
+    salt: &[u8],
+    kdf: KdfAlgorithm,
+    argon2_costs: Argon2Costs,
+) -> Result<SecureKey, ConfigError> {
+    let salt_string = SaltString::encode_b64(salt)
+        .map_err(|e| ConfigError::Encryption(format!("Salt encoding error: {}", e)))?;
+
+    let mut key = [0u8; 32];
+    if kdf == KdfAlgorithm::Argon2id {
+        argon2_costs
+            .hasher()?
+            .hash_password_into(password.as_bytes(), salt, &mut key)
+            .map_err(|e| ConfigError::Encryption(format!("Key derivation error: {}", e)))?;
+        return Ok(SecureKey::new(key));
+    }
+
+    // Use custom PBKDF2 with higher iteration count
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(
+        password.as_bytes(),
+        salt,
+        PBKDF2_ITERATIONS,
+        &mut key,
+    );
+    // PBKDF2 doesn't return an error in this implementation
+
+    Ok(SecureKey::new(key))
+}
+
+/// Run one throwaway derivation with each KDF on fixed dummy inputs, so the first real
+/// save or load after launch doesn't also pay for cold code and caches. No password,
+/// key or file is involved.
+pub fn warm_up_crypto() -> Result<(), ConfigError> {
+    let password = SecureString::new("warmup".to_string());
+    let salt = [0u8; 32];
+    for kdf in [KdfAlgorithm::Pbkdf2Sha256, KdfAlgorithm::Argon2id] {
+        derive_secure_key(&password, &salt, kdf, Argon2Costs::default())?;
+    }
+    Ok(())
+}
+
+/// Inputs larger than this are validated without building the whole JSON tree
+const STREAMING_VALIDATION_THRESHOLD: usize = 1024 * 1024;
+
+/// Check that `config_json` has the shape the app stores and exports: a `configs` array of
+/// S3 configs with the required fields. Returns the problems found, empty when valid.
+/// Credentials may be blank since exports can strip them.
+pub fn validate_config_json(config_json: &str) -> Vec<String> {
+    if config_json.len() > STREAMING_VALIDATION_THRESHOLD {
+        return validate_config_reader(config_json.as_bytes());
+    }
+
+    let value: serde_json::Value = match serde_json::from_str(config_json) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("Not valid JSON: {}", e)],
+    };
+
+    let configs = match value.get("configs").and_then(|configs| configs.as_array()) {
+        Some(configs) => configs,
+        None => return vec!["Missing \"configs\" array".to_string()],
+    };
+
+    let mut problems = Vec::new();
+    for (index, config) in configs.iter().enumerate() {
+        validate_config_entry(index, config, &mut problems);
+    }
+
+    if let Some(active) = value.get("activeConfigId") {
+        validate_active_config_id(active, &mut problems);
+    }
+
+    problems
+}
+
+/// Same checks as `validate_config_json`, but reads the document as a stream and keeps
+/// at most one entry of `configs` in memory, stopping at the first syntax error
+pub fn validate_config_reader<R: std::io::Read>(reader: R) -> Vec<String> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut problems = Vec::new();
+    let result = serde::de::DeserializeSeed::deserialize(
+        ConfigRootValidator {
+            problems: &mut problems,
+        },
+        &mut deserializer,
+    )
+    .and_then(|()| deserializer.end());
+
+    match result {
+        Ok(()) => problems,
+        Err(e) => vec![format!("Not valid JSON: {}", e)],
+    }
+}
+
+/// Per-config fields holding credentials, dropped from redacted exports
+const CREDENTIAL_FIELDS: &[&str] = &["accessKeyId", "secretAccessKey"];
+
+/// Remove the credential fields from every entry in `configs`, keeping the rest of the
+/// document so buckets, regions and endpoints can be shared
+pub fn redact_credentials(config_json: &str) -> Result<String, ConfigError> {
+    let mut root: serde_json::Value = serde_json::from_str(strip_bom(config_json))?;
+    if let Some(configs) = root.get_mut("configs").and_then(|configs| configs.as_array_mut()) {
+        for config in configs.iter_mut().filter_map(|config| config.as_object_mut()) {
+            for field in CREDENTIAL_FIELDS {
+                config.remove(*field);
+            }
+        }
+    }
+    Ok(serde_json::to_string_pretty(&root)?)
+}
+
+fn validate_config_entry(index: usize, config: &serde_json::Value, problems: &mut Vec<String>) {
+    if !config.is_object() {
+        problems.push(format!("configs[{}] is not an object", index));
+        return;
+    }
+
+    for field in ["id", "name", "region", "bucketName"] {
+        let present = config
+            .get(field)
+            .and_then(|value| value.as_str())
+            .is_some_and(|value| !value.trim().is_empty());
+        if !present {
+            problems.push(format!("configs[{}] is missing \"{}\"", index, field));
+        }
+    }
+
+    for field in CREDENTIAL_FIELDS {
+        if config.get(*field).is_some_and(|value| !value.is_string()) {
+            problems.push(format!("configs[{}].{} must be a string", index, field));
+        }
+    }
+
+    if let Some(endpoint) = config.get("endpoint").filter(|value| !value.is_null()) {
+        let valid = endpoint
+            .as_str()
+            .is_some_and(|url| url.is_empty() || url.starts_with("http://") || url.starts_with("https://"));
+        if !valid {
+            problems.push(format!("configs[{}].endpoint must be an http(s) URL", index));
+        }
+    }
+}
+
+fn validate_active_config_id(active: &serde_json::Value, problems: &mut Vec<String>) {
+    if !active.is_null() && !active.is_string() {
+        problems.push("activeConfigId must be a string".to_string());
+    }
+}
+
+/// Streaming visitor for the top-level config object
+struct ConfigRootValidator<'a> {
+    problems: &'a mut Vec<String>,
+}
+
+impl<'de, 'a> serde::de::DeserializeSeed<'de> for ConfigRootValidator<'a> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> serde::de::Visitor<'de> for ConfigRootValidator<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a config object")
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut has_configs = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "configs" => {
+                    has_configs = map.next_value_seed(ConfigListValidator {
+                        problems: &mut *self.problems,
+                    })?;
+                }
+                "activeConfigId" => {
+                    let active: serde_json::Value = map.next_value()?;
+                    validate_active_config_id(&active, self.problems);
+                }
+                _ => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            }
+        }
+
+        if !has_configs {
+            *self.problems = vec!["Missing \"configs\" array".to_string()];
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+        *self.problems = vec!["Missing \"configs\" array".to_string()];
+        Ok(())
+    }
+}
+
+/// Streaming visitor for the `configs` value, yielding whether it was an array
+struct ConfigListValidator<'a> {
+    problems: &'a mut Vec<String>,
+}
+
+impl<'de, 'a> serde::de::DeserializeSeed<'de> for ConfigListValidator<'a> {
+    type Value = bool;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> serde::de::Visitor<'de> for ConfigListValidator<'a> {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of configs")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<bool, A::Error> {
+        let mut index = 0;
+        while let Some(config) = seq.next_element::<serde_json::Value>()? {
+            validate_config_entry(index, &config, self.problems);
+            index += 1;
+        }
+        Ok(true)
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        while map
+            .next_entry::<serde::de::IgnoredAny, serde::de::IgnoredAny>()?
+            .is_some()
+        {}
+        Ok(false)
+    }
+
+    fn visit_bool<E>(self, _value: bool) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_i64<E>(self, _value: i64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_u64<E>(self, _value: u64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_f64<E>(self, _value: f64) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_str<E>(self, _value: &str) -> Result<bool, E> {
+        Ok(false)
+    }
+
+    fn visit_unit<E>(self) -> Result<bool, E> {
+        Ok(false)
+    }
+}
+
+impl ConfigManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, ConfigError> {
+        let config_dir = app_handle
+            .path()
+            .app_config_dir()
+            .map_err(|e| ConfigError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+
+        Self::from_dir(config_dir)
+    }
+
+    /// Create a manager rooted at an explicit directory
+    pub fn from_dir(config_dir: PathBuf) -> Result<Self, ConfigError> {
+        // Create config directory if it doesn't exist
+        if !config_dir.exists() {
+            fs::create_dir_all(&config_dir)?;
+        }
+
+        Ok(ConfigManager {
+            config_dir,
+            throttle: PasswordThrottle::default(),
+        })
+    }
+
+    /// Use a different backoff for failed password attempts
+    pub fn with_password_throttle(mut self, throttle: PasswordThrottle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Location of the active encrypted config
+    pub fn config_path(&self) -> PathBuf {
+        self.get_config_path()
+    }
+
+    fn get_config_path(&self) -> PathBuf {
+        self.config_dir.join("config.encrypted")
+    }
+
+    fn get_backup_path(&self) -> PathBuf {
+        Self::backup_path_for(&self.get_config_path())
+    }
+
+    fn get_vault_path(&self) -> PathBuf {
+        self.config_dir.join("vault.encrypted")
+    }
+
+    fn get_vault_meta_path(&self) -> PathBuf {
+        self.config_dir.join("vault.meta.json")
+    }
+
+    fn get_preferences_path(&self) -> PathBuf {
+        self.config_dir.join("preferences.json")
+    }
+
+    fn get_password_attempts_path(&self) -> PathBuf {
+        self.config_dir.join("password-attempts.json")
+    }
+
+    /// Left by `migrate_config` while its `.bak` is the pre-migration file
+    fn get_migration_token_path(&self) -> PathBuf {
+        self.config_dir.join("migration-pending")
+    }
+
+    fn backup_path_for(target: &Path) -> PathBuf {
+        let mut name = target.as_os_str().to_os_string();
+        name.push(".bak");
+        PathBuf::from(name)
+    }
+
+    fn temp_path_for(target: &Path) -> PathBuf {
+        crate::download::temp_path_for(target)
+    }
+
+    /// Write `contents` to a temp file and rename it over `target`, keeping the
+    /// previous file as a `.bak` backup. Returns whether a previous file existed.
+    ///
+    /// The rename is atomic, but without `durable` it may not have reached the disk
+    /// yet when this returns. With `durable` the temp file is synced before the
+    /// rename and the directory entry after it.
+    fn write_atomically(&self, target: &Path, contents: &str, durable: bool) -> Result<bool, ConfigError> {
+        let temp_path = Self::temp_path_for(target);
+        mark_self_write(target);
+
+        if durable {
+            use std::io::Write;
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+        } else {
+            fs::write(&temp_path, contents)?;
+        }
+        restrict_permissions(&temp_path)?;
+
+        let had_previous = target.exists();
+        if had_previous {
+            let backup_path = Self::backup_path_for(target);
+            fs::copy(target, &backup_path)?;
+            restrict_permissions(&backup_path)?;
+        }
+
+        fs::rename(&temp_path, target)?;
+        mark_self_write(target);
+        if durable {
+            sync_parent_dir(target)?;
+        }
+        Ok(had_previous)
+    }
+
+    /// Write the envelope atomically over the active config, keeping a backup. The
+    /// backup is then the previous save rather than a pre-migration file, so any
+    /// pending migration can no longer be verified against it.
+    fn write_config_atomically(&self, contents: &str, durable: bool) -> Result<bool, ConfigError> {
+        let had_previous = self.write_atomically(&self.get_config_path(), contents, durable)?;
+        self.clear_migration_token()?;
+        Ok(had_previous)
+    }
+
+    fn clear_migration_token(&self) -> Result<(), ConfigError> {
+        match fs::remove_file(self.get_migration_token_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Decrypt the freshly written config and compare it with the expected plaintext,
+    /// restoring the backup (or removing the new file) if it doesn't round-trip.
+    fn verify_or_rollback(
+        &self,
+        expected: &str,
+        password: &str,
+        had_previous: bool,
+    ) -> Result<(), ConfigError> {
+        let loaded = self
+            .read_envelope(&self.get_config_path())
+            .and_then(|envelope| self.decrypt_envelope(&envelope, password));
+        let reason = match loaded {
+            Ok(loaded) if loaded == expected => return Ok(()),
+            Ok(_) => "decrypted config does not match the saved plaintext".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        let config_path = self.get_config_path();
+        if had_previous {
+            fs::rename(self.get_backup_path(), &config_path)?;
+        } else if config_path.exists() {
+            fs::remove_file(&config_path)?;
+        }
+
+        Err(ConfigError::VerificationFailed(reason))
+    }
+
+    /// Re-encrypt the active config with `algorithm` and the current envelope version.
+    /// The new file is verified against the plaintext read before migrating and the
+    /// pre-migration file is restored if they differ.
+    pub fn migrate_config(&self, password: &str, algorithm: CipherAlgorithm) -> Result<(), ConfigError> {
+        self.migrate_config_with(password, |plaintext| {
+            self.encrypt_envelope(plaintext, password, algorithm)
+        })
+    }
+
+    fn migrate_config_with<F>(&self, password: &str, encrypt: F) -> Result<(), ConfigError>
+    where
+        F: FnOnce(&str) -> Result<EncryptedConfig, ConfigError>,
+    {
+        let (plaintext, _guard) = self.load_config_for_update(password)?;
+        let plaintext = plaintext
+            .as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?;
+
+        let envelope = encrypt(plaintext)?;
+        let had_previous = self.write_config_atomically(&serde_json::to_string_pretty(&envelope)?, true)?;
+        self.verify_or_rollback(plaintext, password, had_previous)?;
+        if had_previous {
+            fs::write(self.get_migration_token_path(), &envelope.algorithm)?;
+        }
+
+        audit_log(Level::Info, "config_migrate", &[("algorithm", &envelope.algorithm)]);
+        Ok(())
+    }
+
+    /// Empty one credential field (see `CREDENTIAL_FIELDS`) of the config entry with id
+    /// `config_id`, keeping the rest of the profile. The config is re-encrypted with its
+    /// current cipher and KDF, written atomically and verified, and the backup holding the
+    /// old secret is securely deleted. Decrypted copies are zeroed before returning.
+    pub fn clear_config_field(&self, password: &str, config_id: &str, field: &str) -> Result<(), ConfigError> {
+        use zeroize::Zeroize;
+
+        if !CREDENTIAL_FIELDS.contains(&field) {
+            return Err(ConfigError::FieldNotClearable(field.to_string()));
+        }
+
+        let (plaintext, _guard) = self.load_config_for_update(password)?;
+        let plaintext = plaintext
+            .as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?;
+        let mut root = SecureJson(serde_json::from_str(strip_bom(plaintext))?);
+        let entry = root
+            .0
+            .get_mut("configs")
+            .and_then(|configs| configs.as_array_mut())
+            .and_then(|configs| {
+                configs
+                    .iter_mut()
+                    .find(|config| config.get("id").and_then(|id| id.as_str()) == Some(config_id))
+            })
+            .and_then(|config| config.as_object_mut())
+            .ok_or_else(|| ConfigError::ConfigEntryNotFound(config_id.to_string()))?;
+        match entry.get_mut(field) {
+            Some(serde_json::Value::String(secret)) => secret.zeroize(),
+            _ => {
+                entry.insert(field.to_string(), serde_json::Value::String(String::new()));
+            }
+        }
+
+        let updated = SecureString::new(serde_json::to_string_pretty(&root.0)?);
+        let updated = updated
+            .as_str()
+            .map_err(|e| ConfigError::Encryption(format!("UTF-8 conversion error: {}", e)))?;
+        let (algorithm, kdf) = self.crypto_params_of(&self.get_config_path());
+        let envelope = self.encrypt_envelope_with_kdf(updated, password, algorithm, kdf)?;
+        let had_previous = self.write_config_atomically(&serde_json::to_string_pretty(&envelope)?, true)?;
+        self.verify_or_rollback(updated, password, had_previous)?;
+        // The backup still holds the old secret under the same password
+        self.secure_delete_file(&self.get_backup_path())?;
+
+        audit_log(Level::Info, "config_field_cleared", &[("config_id", config_id), ("field", field)]);
+        Ok(())
+    }
+
+    /// Check that the active config still decrypts to the same plaintext as the
+    /// pre-migration file in its `.bak`, restoring the backup if not. Only valid until
+    /// the config is next saved, which replaces the backup; after that this fails with
+    /// `NoPendingMigration` instead of rolling the save back.
+    pub fn post_migration_verify(&self, password: &str) -> Result<(), ConfigError> {
+        let _guard = lock_for_write(&self.get_config_path());
+        if !self.get_migration_token_path().exists() {
+            return Err(ConfigError::NoPendingMigration);
+        }
+
+        let backup_path = self.get_backup_path();
+        let expected = SecureString::new(self.decrypt_envelope(&self.read_envelope(&backup_path)?, password)?);
+        let expected = expected
+            .as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?;
+        let result = self.verify_or_rollback(expected, password, true);
+        if result.is_err() {
+            // The pre-migration file is active again and no backup is left to compare with
+            self.clear_migration_token()?;
+        }
+        result
+    }
+
+    fn derive_key(
+        &self,
+        password: &SecureString,
+        salt: &[u8],
+        kdf: KdfAlgorithm,
+        argon2_costs: Argon2Costs,
+    ) -> Result<SecureKey, ConfigError> {
+        derive_secure_key(password, salt, kdf, argon2_costs)
+    }
+
+    fn generate_secure_salt(&self) -> Result<[u8; 32], ConfigError> {
+        let mut salt = [0u8; 32]; // Increased salt size for better security
+        try_fill_random(&mut salt).map_err(|e| ConfigError::RandomnessUnavailable(e.to_string()))?;
+        Ok(salt)
+    }
+
+    fn generate_secure_nonce(&self) -> Result<[u8; 12], ConfigError> {
+        let mut nonce = [0u8; 12];
+        try_fill_random(&mut nonce).map_err(|e| ConfigError::RandomnessUnavailable(e.to_string()))?;
+        Ok(nonce)
+    }
+
+    fn secure_delete_file(&self, path: &Path) -> Result<(), ConfigError> {
+        if path.exists() {
+            mark_self_write(path);
+            secure_remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_config(&self, config_json: &str, password: &str) -> Result<(), ConfigError> {
+        self.save_config_with_options(config_json, password, &SaveOptions::default())
+    }
+
+    pub fn save_config_with_options(
+        &self,
+        config_json: &str,
+        password: &str,
+        options: &SaveOptions,
+    ) -> Result<(), ConfigError> {
+        if let Some(policy) = &options.password_policy {
+            let report = policy.check(password);
+            if !report.passed {
+                return Err(ConfigError::WeakPassword(report.failed_rules()));
+            }
+        }
+
+        let encrypted_config = self.encrypt_envelope(config_json, password, options.algorithm)?;
+
+        // Save to file with secure permissions
+        let json_data = serde_json::to_string_pretty(&encrypted_config)?;
+        let _guard = lock_for_write(&self.get_config_path());
+        let had_previous = self.write_config_atomically(&json_data, options.durable)?;
+
+        if options.verify {
+            self.verify_or_rollback(config_json, password, had_previous)?;
+        }
+
+        audit_log(
+            Level::Info,
+            "config_save",
+            &[("algorithm", options.algorithm.as_str()), ("durable", &options.durable.to_string())],
+        );
+        Ok(())
+    }
+
+    pub fn load_config(&self, password: &str) -> Result<String, ConfigError> {
+        let encrypted_config = self.read_envelope(&self.get_config_path())?;
+        self.decrypt_active_config(&encrypted_config, password)
+    }
+
+    /// Check `password` against the active config, then take its write lock and return the
+    /// plaintext as of holding it. Checking first keeps a wrong password, and the retry
+    /// delay it earns, from stalling other writers. If another writer replaced the file
+    /// in between, the new one is decrypted under the lock.
+    fn load_config_for_update(
+        &self,
+        password: &str,
+    ) -> Result<(SecureString, MutexGuard<'static, ()>), ConfigError> {
+        let config_path = self.get_config_path();
+        let checked = self.read_envelope(&config_path)?;
+        let plaintext = SecureString::new(self.decrypt_active_config(&checked, password)?);
+
+        let guard = lock_for_write(&config_path);
+        let current = self.read_envelope(&config_path)?;
+        if current == checked {
+            return Ok((plaintext, guard));
+        }
+        Ok((SecureString::new(self.decrypt_envelope(&current, password)?), guard))
+    }
+
+    /// Decrypt an envelope of the active config after the retry delay earned by earlier
+    /// wrong passwords, counting this attempt towards the next delay
+    fn decrypt_active_config(
+        &self,
+        encrypted_config: &EncryptedConfig,
+        password: &str,
+    ) -> Result<String, ConfigError> {
+        std::thread::sleep(self.password_retry_delay());
+        let config_json = match self.decrypt_envelope(encrypted_config, password) {
+            Ok(config_json) => {
+                self.set_failed_attempts(FailedAttempts::default());
+                config_json
+            }
+            Err(ConfigError::InvalidPassword) => {
+                self.record_failed_attempt();
+                return Err(ConfigError::InvalidPassword);
+            }
+            Err(e) => return Err(e),
+        };
+        audit_log(Level::Info, "config_load", &[("algorithm", &encrypted_config.algorithm)]);
+        Ok(config_json)
+    }
+
+    fn record_failed_attempt(&self) {
+        let previous = self.failed_attempts();
+        self.set_failed_attempts(FailedAttempts {
+            count: previous.count.saturating_add(1),
+            last_failure_ms: unix_millis_now(),
+        });
+    }
+
+    /// How long the next `load_config` will wait because of earlier wrong passwords
+    pub fn password_retry_delay(&self) -> Duration {
+        let attempts = self.failed_attempts();
+        let elapsed = Duration::from_millis(unix_millis_now().saturating_sub(attempts.last_failure_ms));
+        self.throttle.delay_for(attempts.count).saturating_sub(elapsed)
+    }
+
+    /// Failed attempts against the active config, seeded from disk the first time
+    /// this process looks at it
+    fn failed_attempts(&self) -> FailedAttempts {
+        let Ok(mut attempts) = failed_attempts().lock() else {
+            return FailedAttempts::default();
+        };
+        *attempts.entry(self.get_config_path()).or_insert_with(|| {
+            fs::read_to_string(self.get_password_attempts_path())
+                .ok()
+                .and_then(|contents| serde_json::from_str(&contents).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn set_failed_attempts(&self, updated: FailedAttempts) {
+        if let Ok(mut attempts) = failed_attempts().lock() {
+            attempts.insert(self.get_config_path(), updated);
+        }
+
+        // Best effort: losing the persisted counter only weakens the backoff
+        let path = self.get_password_attempts_path();
+        let result = if updated.count == 0 {
+            // The copy `write_atomically` kept of the previous count goes too
+            let _ = fs::remove_file(Self::backup_path_for(&path));
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other.map_err(ConfigError::from),
+            }
+        } else {
+            // A torn write would read back as no failures at all
+            serde_json::to_string(&updated)
+                .map_err(ConfigError::from)
+                .and_then(|contents| self.write_atomically(&path, &contents, false))
+                .map(|_| ())
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to persist password attempts: {}", e);
+        }
+    }
+
+    /// Encrypt `plaintext` under `password` into a fresh envelope with random salt and nonce
+    fn encrypt_envelope(
+        &self,
+        plaintext: &str,
+        password: &str,
+        algorithm: CipherAlgorithm,
+    ) -> Result<EncryptedConfig, ConfigError> {
+        self.encrypt_envelope_with_kdf(plaintext, password, algorithm, KdfAlgorithm::default())
+    }
+
+    fn encrypt_envelope_with_kdf(
+        &self,
+        plaintext: &str,
+        password: &str,
+        algorithm: CipherAlgorithm,
+        kdf: KdfAlgorithm,
+    ) -> Result<EncryptedConfig, ConfigError> {
+        let secure_password = SecureString::new(password.to_string());
+        let secure_config = SecureString::new(plaintext.to_string());
+        
+        // Generate secure random salt and nonce
+        let salt = self.generate_secure_salt()?;
+        let nonce_bytes = self.generate_secure_nonce()?;
+        // A stuck RNG means a predictable salt and a reused nonce, which breaks the
+        // AEAD ciphers outright, so refuse to encrypt rather than write the envelope
+        if let Some(problem) = random_sample_problem(&salt) {
+            log::error!("Salt looks weak, the system may lack entropy: {}", problem);
+            audit_log(Level::Warn, "rng_unhealthy", &[("reason", &problem)]);
+            return Err(ConfigError::RandomnessUnavailable(problem));
+        }
+
+        // Derive encryption key
+        let secure_key = self.derive_key(&secure_password, &salt, kdf, Argon2Costs::default())?;
+
+        // Create encrypted config structure with metadata
+        let (memory_kib, parallelism) = kdf.argon2_costs();
+        let mut envelope = EncryptedConfig {
+            data: String::new(),
+            salt: general_purpose::STANDARD.encode(salt),
+            nonce: general_purpose::STANDARD.encode(nonce_bytes),
+            version: ENCRYPTION_VERSION.to_string(),
+            algorithm: algorithm.as_str().to_string(),
+            iterations: kdf.iterations(),
+            kdf: kdf.envelope_name().map(str::to_string),
+            memory_kib,
+            parallelism,
+        };
+
+        // Encrypt the config, authenticating the envelope metadata with it
+        let aad = envelope_aad(&envelope)?;
+        let encrypted_data =
+            algorithm.encrypt(&secure_key, &nonce_bytes, secure_config.as_bytes(), &aad)?;
+        envelope.data = general_purpose::STANDARD.encode(&encrypted_data);
+        Ok(envelope)
+    }
+
+    /// Read and parse an envelope file without decrypting it
+    fn read_envelope(&self, path: &Path) -> Result<EncryptedConfig, ConfigError> {
+        if !path.exists() {
+            return Err(ConfigError::ConfigNotFound);
+        }
+
+        // Read encrypted config from file
+        let file_content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(strip_bom(&file_content))?)
+    }
+
+    /// Decrypt an envelope, recording failed attempts in the audit log
+    fn decrypt_envelope(
+        &self,
+        encrypted_config: &EncryptedConfig,
+        password: &str,
+    ) -> Result<String, ConfigError> {
+        self.try_decrypt_envelope(encrypted_config, password)
+            .inspect_err(|e| {
+                let reason = match e {
+                    ConfigError::InvalidPassword => "invalid_password",
+                    ConfigError::UnsupportedVersion { .. } => "unsupported_version",
+                    _ => "malformed_envelope",
+                };
+                audit_log(
+                    Level::Warn,
+                    "decrypt_failed",
+                    &[("algorithm", &encrypted_config.algorithm), ("reason", reason)],
+                );
+            })
+    }
+
+    fn try_decrypt_envelope(
+        &self,
+        encrypted_config: &EncryptedConfig,
+        password: &str,
+    ) -> Result<String, ConfigError> {
+        let secure_password = SecureString::new(password.to_string());
+
+        // A downgraded app can't read newer envelopes; say so instead of blaming the password
+        if is_future_envelope_version(&encrypted_config.version) {
+            return Err(ConfigError::UnsupportedVersion {
+                found: encrypted_config.version.clone(),
+                max_supported: ENCRYPTION_VERSION.to_string(),
+            });
+        }
+
+        // Validate encryption metadata
+        if encrypted_config.version != ENCRYPTION_VERSION
+            && encrypted_config.version != LEGACY_ENCRYPTION_VERSION
+        {
+            return Err(ConfigError::Decryption(format!(
+                "Unsupported encryption version: {}",
+                encrypted_config.version
+            )));
+        }
+
+        let algorithm = CipherAlgorithm::from_name(&encrypted_config.algorithm).ok_or_else(|| {
+            ConfigError::Decryption(format!(
+                "Unsupported encryption algorithm: {}",
+                encrypted_config.algorithm
+            ))
+        })?;
+
+        let kdf = match encrypted_config.kdf.as_deref() {
+            None => KdfAlgorithm::Pbkdf2Sha256,
+            Some(name) => KdfAlgorithm::from_name(name).ok_or_else(|| {
+                ConfigError::Decryption(format!("Unsupported key derivation: {}", name))
+            })?,
+        };
+
+        // Decode base64 data
+        let encrypted_data = general_purpose::STANDARD
+            .decode(&encrypted_config.data)
+            .map_err(|e| ConfigError::Decryption(format!("Base64 decode error: {}", e)))?;
+
+        let salt = general_purpose::STANDARD
+            .decode(&encrypted_config.salt)
+            .map_err(|e| ConfigError::Decryption(format!("Salt decode error: {}", e)))?;
+
+        let nonce_bytes = general_purpose::STANDARD
+            .decode(&encrypted_config.nonce)
+            .map_err(|e| ConfigError::Decryption(format!("Nonce decode error: {}", e)))?;
+
+        // Derive decryption key. PBKDF2 always runs PBKDF2_ITERATIONS whatever the
+        // envelope says, so only Argon2 costs come from the file.
+        let argon2_costs = match kdf {
+            KdfAlgorithm::Argon2id => Argon2Costs::of_envelope(encrypted_config)?,
+            KdfAlgorithm::Pbkdf2Sha256 => Argon2Costs::default(),
+        };
+        let secure_key = self.derive_key(&secure_password, &salt, kdf, argon2_costs)?;
+
+        // Decrypt the data; tampered version, algorithm or KDF parameter fields fail here
+        let aad = envelope_aad(encrypted_config)?;
+        let decrypted_data = algorithm.decrypt(&secure_key, &nonce_bytes, &encrypted_data, &aad)?;
+
+        // Convert to secure string and then to regular string
+        let secure_config = SecureString::from_bytes(decrypted_data);
+        let config_json = secure_config.as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?
+            .to_string();
+
+        Ok(config_json)
+    }
+
+    /// Re-apply owner-only permissions to the encrypted config, the vault and their
+    /// backups, returning the files whose permissions had to change. Fails with
+    /// `PermissionsUnsupported` outside Unix.
+    pub fn harden_permissions(&self) -> Result<Vec<PathBuf>, ConfigError> {
+        let candidates = [
+            self.get_config_path(),
+            self.get_backup_path(),
+            self.get_vault_path(),
+            Self::backup_path_for(&self.get_vault_path()),
+        ];
+
+        let mut changed = Vec::new();
+        for path in candidates {
+            if path.exists() && !has_restricted_permissions(&path)? {
+                restrict_permissions(&path)?;
+                changed.push(path);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Fingerprint of the decrypted active config, equal on two machines exactly when
+    /// their configs match, whatever the password or envelope randomness
+    pub fn config_fingerprint(&self, password: &str) -> Result<String, ConfigError> {
+        let plaintext = SecureString::new(self.load_config(password)?);
+        let plaintext = plaintext
+            .as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?;
+        fingerprint_config_json(plaintext)
+    }
+
+    /// Full SHA-256 of the raw `config.encrypted` bytes, so a backup can be checked
+    /// against the file on disk without the password
+    pub fn config_file_hash(&self) -> Result<String, ConfigError> {
+        use sha2::Digest;
+
+        let config_path = self.get_config_path();
+        if !config_path.exists() {
+            return Err(ConfigError::ConfigNotFound);
+        }
+        Ok(format!("{:x}", Sha256::digest(fs::read(config_path)?)))
+    }
+
+    /// Parameters the active config was encrypted with, so weak legacy settings can be flagged
+    pub fn active_config_crypto_info(&self) -> Result<CryptoInfo, ConfigError> {
+        CryptoInfo::from_envelope(&self.read_envelope(&self.get_config_path())?)
+    }
+
+    pub fn config_exists(&self) -> bool {
+        self.get_config_path().exists()
+    }
+
+    pub fn vault_exists(&self) -> bool {
+        self.get_vault_path().exists()
+    }
+
+    /// Encrypt every profile into the consolidated vault under one password
+    pub fn save_vault(&self, profiles: &VaultProfiles, password: &str) -> Result<(), ConfigError> {
+        let touched: Vec<&str> = profiles.keys().map(String::as_str).collect();
+        let _guard = lock_for_write(&self.get_vault_path());
+        self.write_vault(profiles, password, &touched)
+    }
+
+    /// Write the vault and refresh its label sidecar. Profiles named in `touched`
+    /// get a new modification time, the rest keep the one already recorded.
+    fn write_vault(
+        &self,
+        profiles: &VaultProfiles,
+        password: &str,
+        touched: &[&str],
+    ) -> Result<(), ConfigError> {
+        let secure_vault = SecureString::new(serde_json::to_string(profiles)?);
+        let plaintext = secure_vault
+            .as_str()
+            .map_err(|e| ConfigError::Encryption(format!("UTF-8 conversion error: {}", e)))?;
+        let (algorithm, kdf) = self.crypto_params_of(&self.get_vault_path());
+        let envelope = self.encrypt_envelope_with_kdf(plaintext, password, algorithm, kdf)?;
+        self.write_atomically(&self.get_vault_path(), &serde_json::to_string_pretty(&envelope)?, false)?;
+        self.write_vault_meta(profiles, touched)
+    }
+
+    /// Cipher and KDF of the envelope at `path`, so routine edits keep an upgraded file
+    /// on its parameters. Defaults when there is no readable envelope yet.
+    fn crypto_params_of(&self, path: &Path) -> (CipherAlgorithm, KdfAlgorithm) {
+        match self.read_envelope(path) {
+            Ok(envelope) => (
+                CipherAlgorithm::from_name(&envelope.algorithm).unwrap_or_default(),
+                envelope
+                    .kdf
+                    .as_deref()
+                    .and_then(KdfAlgorithm::from_name)
+                    .unwrap_or_default(),
+            ),
+            Err(_) => Default::default(),
+        }
+    }
+
+    /// Re-encrypt every vault profile under `algorithm` and `kdf`. The new vault is
+    /// written atomically and each profile must read back unchanged; if any doesn't,
+    /// the previous vault is restored and no profile is reported as upgraded.
+    pub fn upgrade_all_profiles(
+        &self,
+        password: &str,
+        algorithm: CipherAlgorithm,
+        kdf: KdfAlgorithm,
+    ) -> Result<Vec<ProfileUpgradeResult>, ConfigError> {
+        self.upgrade_all_profiles_with(password, algorithm, kdf, |plaintext| {
+            self.encrypt_envelope_with_kdf(plaintext, password, algorithm, kdf)
+        })
+    }
+
+    fn upgrade_all_profiles_with<F>(
+        &self,
+        password: &str,
+        algorithm: CipherAlgorithm,
+        kdf: KdfAlgorithm,
+        encrypt: F,
+    ) -> Result<Vec<ProfileUpgradeResult>, ConfigError>
+    where
+        F: FnOnce(&str) -> Result<EncryptedConfig, ConfigError>,
+    {
+        let (profiles, _guard) = self.load_vault_for_update(password)?;
+        let profiles = profiles.ok_or(ConfigError::ConfigNotFound)?;
+        let secure_vault = SecureString::new(serde_json::to_string(&profiles)?);
+        let plaintext = secure_vault
+            .as_str()
+            .map_err(|e| ConfigError::Encryption(format!("UTF-8 conversion error: {}", e)))?;
+        let envelope = encrypt(plaintext)?;
+
+        let vault_path = self.get_vault_path();
+        self.write_atomically(&vault_path, &serde_json::to_string_pretty(&envelope)?, true)?;
+
+        let reloaded = self.load_vault(password);
+        let errors: Vec<(String, Option<String>)> = profiles
+            .iter()
+            .map(|(name, config)| {
+                let error = match &reloaded {
+                    Ok(reloaded) if reloaded.get(name) == Some(config) => None,
+                    Ok(_) => Some("profile did not round-trip through the upgraded vault".to_string()),
+                    Err(e) => Some(e.to_string()),
+                };
+                (name.clone(), error)
+            })
+            .collect();
+
+        let failed = errors.iter().any(|(_, error)| error.is_some());
+        if failed {
+            fs::rename(Self::backup_path_for(&vault_path), &vault_path)?;
+        }
+        audit_log(
+            if failed { Level::Warn } else { Level::Info },
+            "vault_upgrade",
+            &[
+                ("algorithm", algorithm.as_str()),
+                ("kdf", kdf.as_str()),
+                ("rolled_back", &failed.to_string()),
+            ],
+        );
+
+        Ok(errors
+            .into_iter()
+            .map(|(name, error)| ProfileUpgradeResult {
+                name,
+                upgraded: !failed,
+                error,
+            })
+            .collect())
+    }
+
+    fn write_vault_meta(&self, profiles: &VaultProfiles, touched: &[&str]) -> Result<(), ConfigError> {
+        let previous: BTreeMap<String, ProfileInfo> = self
+            .get_profile_labels()?
+            .into_iter()
+            .map(|info| (info.name.clone(), info))
+            .collect();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        let labels: Vec<ProfileInfo> = profiles
+            .iter()
+            .map(|(name, config)| {
+                let last_modified = match previous.get(name) {
+                    Some(info) if !touched.contains(&name.as_str()) => info.last_modified,
+                    _ => now,
+                };
+                ProfileInfo {
+                    name: name.clone(),
+                    label: profile_label(config),
+                    last_modified,
+                    hint: previous.get(name).and_then(|info| info.hint.clone()),
+                }
+            })
+            .collect();
+
+        self.write_profile_infos(&labels)
+    }
+
+    fn write_profile_infos(&self, infos: &[ProfileInfo]) -> Result<(), ConfigError> {
+        self.write_atomically(&self.get_vault_meta_path(), &serde_json::to_string_pretty(infos)?, false)?;
+        Ok(())
+    }
+
+    /// Set or clear the password hint of `profile`. The hint is whatever the user typed and
+    /// is stored unencrypted, so it is never derived from the password itself.
+    pub fn set_password_hint(&self, profile: &str, hint: Option<&str>) -> Result<(), ConfigError> {
+        let hint = hint.map(str::trim).filter(|hint| !hint.is_empty());
+        if hint.is_some_and(|hint| hint.chars().count() > MAX_PASSWORD_HINT_LEN) {
+            return Err(ConfigError::InvalidHint(format!(
+                "longer than {} characters",
+                MAX_PASSWORD_HINT_LEN
+            )));
+        }
+
+        let mut infos = self.get_profile_labels()?;
+        let info = infos
+            .iter_mut()
+            .find(|info| info.name == profile)
+            .ok_or(ConfigError::ConfigNotFound)?;
+        info.hint = hint.map(str::to_string);
+        self.write_profile_infos(&infos)
+    }
+
+    /// Password hint of `profile`, readable without decrypting the vault
+    pub fn get_password_hint(&self, profile: &str) -> Result<Option<String>, ConfigError> {
+        Ok(self
+            .get_profile_labels()?
+            .into_iter()
+            .find(|info| info.name == profile)
+            .and_then(|info| info.hint))
+    }
+
+    /// Profile names and labels from the unencrypted sidecar, without decrypting the vault
+    pub fn get_profile_labels(&self) -> Result<Vec<ProfileInfo>, ConfigError> {
+        let meta_path = self.get_vault_meta_path();
+        if !meta_path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(meta_path)?)?)
+    }
+
+    pub fn load_vault(&self, password: &str) -> Result<VaultProfiles, ConfigError> {
+        let envelope = self.read_envelope(&self.get_vault_path())?;
+        self.decrypt_vault(&envelope, password)
+    }
+
+    fn decrypt_vault(&self, envelope: &EncryptedConfig, password: &str) -> Result<VaultProfiles, ConfigError> {
+        let secure_vault = SecureString::new(self.decrypt_envelope(envelope, password)?);
+        let plaintext = secure_vault
+            .as_str()
+            .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?;
+        Ok(serde_json::from_str(plaintext)?)
+    }
+
+    /// Check `password` against the vault, then take its write lock and return the
+    /// profiles as of holding it, decrypting again if another writer replaced the vault
+    /// in between. `None` means there is no vault yet.
+    fn load_vault_for_update(
+        &self,
+        password: &str,
+    ) -> Result<(Option<VaultProfiles>, MutexGuard<'static, ()>), ConfigError> {
+        let vault_path = self.get_vault_path();
+        let read = |path: &Path| match self.read_envelope(path) {
+            Ok(envelope) => Ok(Some(envelope)),
+            Err(ConfigError::ConfigNotFound) => Ok(None),
+            Err(e) => Err(e),
+        };
+
+        let checked = read(&vault_path)?;
+        let mut profiles = checked
+            .as_ref()
+            .map(|envelope| self.decrypt_vault(envelope, password))
+            .transpose()?;
+
+        let guard = lock_for_write(&vault_path);
+        let current = read(&vault_path)?;
+        if current != checked {
+            profiles = current
+                .as_ref()
+                .map(|envelope| self.decrypt_vault(envelope, password))
+                .transpose()?;
+        }
+        Ok((profiles, guard))
+    }
+
+    pub fn list_vault_profiles(&self, password: &str) -> Result<Vec<String>, ConfigError> {
+        Ok(self.load_vault(password)?.into_keys().collect())
+    }
+
+    /// Add or replace a profile, creating the vault if it doesn't exist yet
+    pub fn upsert_vault_profile(
+        &self,
+        password: &str,
+        name: &str,
+        config: serde_json::Value,
+    ) -> Result<(), ConfigError> {
+        let (profiles, _guard) = self.load_vault_for_update(password)?;
+        let mut profiles = profiles.unwrap_or_default();
+        profiles.insert(name.to_string(), config);
+        self.write_vault(&profiles, password, &[name])
+    }
+
+    /// Remove a profile from the vault, returning whether it was present
+    pub fn remove_vault_profile(&self, password: &str, name: &str) -> Result<bool, ConfigError> {
+        let (profiles, _guard) = self.load_vault_for_update(password)?;
+        let mut profiles = profiles.ok_or(ConfigError::ConfigNotFound)?;
+        let removed = profiles.remove(name).is_some();
+        if removed {
+            self.write_vault(&profiles, password, &[])?;
+        }
+        Ok(removed)
+    }
+
+    /// Rename a profile in place, keeping its config, label and hint
+    pub fn rename_vault_profile(
+        &self,
+        password: &str,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(), ConfigError> {
+        let (profiles, _guard) = self.load_vault_for_update(password)?;
+        let mut profiles = profiles.ok_or(ConfigError::ConfigNotFound)?;
+        if profiles.contains_key(new_name) {
+            return Err(ConfigError::ProfileExists(new_name.to_string()));
+        }
+        let config = profiles
+            .remove(old_name)
+            .ok_or(ConfigError::ConfigNotFound)?;
+        profiles.insert(new_name.to_string(), config);
+        let hint = self.get_password_hint(old_name)?;
+        self.write_vault(&profiles, password, &[new_name])?;
+        if hint.is_some() {
+            self.set_password_hint(new_name, hint.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Move the standalone `config.encrypted` into the vault as `profile_name`.
+    /// The old file is only securely deleted once the vault reads back correctly.
+    pub fn migrate_config_to_vault(
+        &self,
+        password: &str,
+        profile_name: &str,
+    ) -> Result<(), ConfigError> {
+        let config: serde_json::Value = serde_json::from_str(&self.load_config(password)?)?;
+        self.upsert_vault_profile(password, profile_name, config.clone())?;
+
+        if self.load_vault(password)?.get(profile_name) != Some(&config) {
+            return Err(ConfigError::VerificationFailed(format!(
+                "Profile {} did not round-trip through the vault",
+                profile_name
+            )));
+        }
+
+        self.delete_config()
+    }
+
+    pub fn delete_config(&self) -> Result<(), ConfigError> {
+        let config_path = self.get_config_path();
+        let _guard = lock_for_write(&config_path);
+        self.secure_delete_file(&config_path)?;
+        self.secure_delete_file(&self.get_backup_path())?;
+        audit_log(Level::Info, "config_delete", &[]);
+        Ok(())
+    }
+
+    pub fn export_config(
+        &self,
+        export_path: &str,
+        config_json: &str,
+        overwrite: bool,
+    ) -> Result<(), ConfigError> {
+        use std::io::Write;
+
+        let export_path = PathBuf::from(export_path);
+        if overwrite {
+            fs::write(export_path, config_json)?;
+            return Ok(());
+        }
+
+        // create_new fails atomically if the file appeared since the user picked the path
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&export_path)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    ConfigError::ExportExists(export_path.display().to_string())
+                }
+                _ => ConfigError::Io(e),
+            })?;
+        file.write_all(config_json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Export `config_json` in `format`, encrypting it under `password` for the
+    /// encrypted formats. Returns the format written.
+    pub fn export_config_as(
+        &self,
+        format: ExportFormat,
+        export_path: &str,
+        config_json: &str,
+        password: Option<&str>,
+        overwrite: bool,
+    ) -> Result<ExportFormat, ConfigError> {
+        let password = password.filter(|password| !password.is_empty());
+        if format.needs_password() && password.is_none() {
+            return Err(ConfigError::PasswordRequired(format.as_str().to_string()));
+        }
+
+        let contents = match (format, password) {
+            (ExportFormat::Encrypted, Some(password)) => {
+                let envelope = self.encrypt_envelope(config_json, password, CipherAlgorithm::default())?;
+                serde_json::to_string_pretty(&envelope)?
+            }
+            (ExportFormat::Compact, Some(password)) => {
+                let envelope = self.encrypt_envelope(config_json, password, CipherAlgorithm::default())?;
+                general_purpose::STANDARD.encode(serde_json::to_string(&envelope)?)
+            }
+            _ => config_json.to_string(),
+        };
+
+        self.export_config(export_path, &contents, overwrite)?;
+        audit_log(Level::Info, "config_export", &[("format", format.as_str())]);
+        Ok(format)
+    }
+
+    /// Decrypt the stored config and export it as plaintext with every credential removed
+    pub fn export_config_redacted(
+        &self,
+        password: &str,
+        export_path: &str,
+        overwrite: bool,
+    ) -> Result<(), ConfigError> {
+        let redacted = redact_credentials(&self.load_config(password)?)?;
+        self.export_config(export_path, &redacted, overwrite)?;
+        audit_log(Level::Info, "config_export", &[("format", "redacted")]);
+        Ok(())
+    }
+
+    /// Read a plaintext config export, rejecting content that isn't a valid config
+    /// unless `skip_validation` is set
+    pub fn import_config(
+        &self,
+        import_path: &str,
+        skip_validation: bool,
+    ) -> Result<String, ConfigError> {
+        self.import_config_checked(import_path, skip_validation)
+            .map(|imported| imported.config_json)
+    }
+
+    /// `import_config` that also reports non-fatal warnings, such as an import file
+    /// other users could read and whose secrets may therefore already be exposed
+    pub fn import_config_checked(
+        &self,
+        import_path: &str,
+        skip_validation: bool,
+    ) -> Result<ImportedConfig, ConfigError> {
+        let import_path = PathBuf::from(import_path);
+        if !import_path.exists() {
+            return Err(ConfigError::ConfigNotFound);
+        }
+        let config_json = fs::read_to_string(&import_path)?;
+        let config_json = strip_bom(&config_json).to_string();
+
+        if !skip_validation {
+            let problems = validate_config_json(&config_json);
+            if !problems.is_empty() {
+                return Err(ConfigError::InvalidConfig(problems));
+            }
+        }
+
+        let mut warnings = Vec::new();
+        if is_world_readable(&import_path)? {
+            let source = import_path.display().to_string();
+            audit_log(Level::Warn, "import_world_readable", &[("path", &source)]);
+            warnings.push(format!(
+                "{} is readable by other users; the secrets it holds may have been exposed",
+                source
+            ));
+        }
+        Ok(ImportedConfig { config_json, warnings })
+    }
+
+    /// Copy the encrypted config into `directory` as `config-YYYYMMDD-HHMMSS.encrypted`,
+    /// returning the path of the new backup. Further backups within the same second get
+    /// a `-2`, `-3`, ... suffix rather than failing.
+    pub fn backup_config_to(&self, directory: &Path) -> Result<PathBuf, ConfigError> {
+        use std::io::Write;
+
+        let config_path = self.get_config_path();
+        if !config_path.exists() {
+            return Err(ConfigError::ConfigNotFound);
+        }
+        fs::create_dir_all(directory)?;
+
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let contents = fs::read(&config_path)?;
+
+        let mut attempt = 1;
+        let (mut file, backup_path) = loop {
+            let file_name = match attempt {
+                1 => format!("config-{}.encrypted", stamp),
+                n => format!("config-{}-{}.encrypted", stamp, n),
+            };
+            let backup_path = directory.join(file_name);
+            match fs::OpenOptions::new().write(true).create_new(true).open(&backup_path) {
+                Ok(file) => break (file, backup_path),
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(ConfigError::Io(e)),
+                Err(_) if attempt >= MAX_BACKUP_NAME_ATTEMPTS => {
+                    return Err(ConfigError::ExportExists(backup_path.display().to_string()));
+                }
+                Err(_) => attempt += 1,
+            }
+        };
+        restrict_permissions(&backup_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        Ok(backup_path)
+    }
+
+    /// Install a backup made by `backup_config_to` as the active config once it
+    /// decrypts with `password`
+    pub fn restore_config_from(&self, backup_path: &Path, password: &str) -> Result<(), ConfigError> {
+        let envelope = self.read_envelope(backup_path)?;
+        self.decrypt_envelope(&envelope, password)?;
+
+        let contents = serde_json::to_string_pretty(&envelope)?;
+        let _guard = lock_for_write(&self.get_config_path());
+        self.write_config_atomically(&contents, false)?;
+        audit_log(
+            Level::Info,
+            "config_restore",
+            &[("source", &backup_path.display().to_string())],
+        );
+        Ok(())
+    }
+
+    /// Backups of the active config in the config directory with their indices,
+    /// ordered by index
+    fn config_backups(&self) -> Result<Vec<(u32, PathBuf)>, ConfigError> {
+        let backup_name = Self::backup_path_for(&self.get_config_path())
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.config_dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let index = if name == backup_name {
+                Some(0)
+            } else {
+                name.strip_prefix(&backup_name)
+                    .and_then(|rest| rest.strip_prefix('.'))
+                    .and_then(|n| n.parse::<u32>().ok())
+            };
+            if let (Some(index), true) = (index, entry.file_type()?.is_file()) {
+                backups.push((index, entry.path()));
+            }
+        }
+        backups.sort();
+        Ok(backups)
+    }
+
+    /// Try to parse and decrypt every backup of the active config with `password`.
+    /// Only whether each step worked is reported; decrypted contents are dropped.
+    pub fn verify_all_backups(&self, password: &str) -> Result<Vec<BackupStatus>, ConfigError> {
+        let backups = self.config_backups()?;
+        // Each call is one password guess, so it shares the retry delay and failure
+        // count of `load_config` rather than offering an unthrottled oracle
+        std::thread::sleep(self.password_retry_delay());
+
+        let mut statuses = Vec::new();
+        let mut wrong_password = false;
+        for (index, path) in backups {
+            let (parseable, decryptable, version) = match self.read_envelope(&path) {
+                Ok(envelope) => {
+                    let decryptable = match self.try_decrypt_envelope(&envelope, password) {
+                        Ok(plaintext) => {
+                            drop(SecureString::new(plaintext));
+                            true
+                        }
+                        Err(ConfigError::InvalidPassword) => {
+                            wrong_password = true;
+                            false
+                        }
+                        Err(_) => false,
+                    };
+                    (true, decryptable, Some(envelope.version))
+                }
+                Err(_) => (false, false, None),
+            };
+            statuses.push(BackupStatus { index, parseable, decryptable, version });
+        }
+
+        if statuses.iter().any(|status| status.decryptable) {
+            self.set_failed_attempts(FailedAttempts::default());
+        } else if wrong_password {
+            self.record_failed_attempt();
+        }
+
+        let failed = statuses.iter().filter(|status| !status.decryptable).count();
+        audit_log(
+            Level::Info,
+            "backups_verified",
+            &[("count", &statuses.len().to_string()), ("failed", &failed.to_string())],
+        );
+        Ok(statuses)
+    }
+
+    /// Store non-secret UI preferences unencrypted so they are available before unlock
+    pub fn save_preferences(&self, preferences_json: &str) -> Result<(), ConfigError> {
+        serde_json::from_str::<serde_json::Value>(preferences_json)?;
+        self.write_atomically(&self.get_preferences_path(), preferences_json, false)?;
+        Ok(())
+    }
+
+    /// Stored preferences, or `None` if none were saved yet
+    pub fn load_preferences(&self) -> Result<Option<String>, ConfigError> {
+        let preferences_path = self.get_preferences_path();
+        if !preferences_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(preferences_path)?))
+    }
+}
+
+/// Run a `ConfigManager` operation on the blocking pool. Key derivation, the
+/// wrong-password retry delay in `load_config` and waiting for a file's write lock
+/// would otherwise stall a runtime worker.
+async fn run_config_task<T, F>(app_handle: &AppHandle, task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&ConfigManager) -> Result<T, ConfigError> + Send + 'static,
+{
+    let config_manager = ConfigManager::new(app_handle).map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || task(&config_manager))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+// Tauri commands
+#[tauri::command]
+pub async fn save_config(
+    app_handle: AppHandle,
+    config_json: String,
+    password: String,
+    verify: Option<bool>,
+    algorithm: Option<String>,
+    durable: Option<bool>,
+    password_policy: Option<PasswordPolicy>,
+) -> Result<(), String> {
+    let algorithm = match algorithm {
+        Some(name) => CipherAlgorithm::from_name(&name)
+            .ok_or_else(|| format!("Unsupported encryption algorithm: {}", name))?,
+        None => CipherAlgorithm::default(),
+    };
+    let options = SaveOptions {
+        verify: verify.unwrap_or(false),
+        algorithm,
+        durable: durable.unwrap_or(false),
+        password_policy,
+    };
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.save_config_with_options(&config_json, &password, &options)
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn check_password_policy(password: String, policy: PasswordPolicy) -> PasswordPolicyReport {
+    policy.check(&password)
+}
+
+#[tauri::command]
+pub async fn migrate_config(
+    app_handle: AppHandle,
+    password: String,
+    algorithm: Option<String>,
+) -> Result<(), String> {
+    let algorithm = match algorithm {
+        Some(name) => CipherAlgorithm::from_name(&name)
+            .ok_or_else(|| format!("Unsupported encryption algorithm: {}", name))?,
+        None => CipherAlgorithm::default(),
+    };
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.migrate_config(&password, algorithm)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn post_migration_verify(app_handle: AppHandle, password: String) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.post_migration_verify(&password)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn load_config(app_handle: AppHandle, password: String) -> Result<String, String> {
+    run_config_task(&app_handle, move |config_manager| config_manager.load_config(&password)).await
+}
+
+#[tauri::command]
+pub async fn config_exists(app_handle: AppHandle) -> Result<bool, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    Ok(config_manager.config_exists())
+}
+
+#[tauri::command]
+pub async fn harden_config_permissions(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let changed = config_manager
+        .harden_permissions()
+        .map_err(|e| e.to_string())?;
+    Ok(changed
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn delete_config(app_handle: AppHandle) -> Result<(), String> {
+    run_config_task(&app_handle, |config_manager| config_manager.delete_config()).await
+}
+
+#[tauri::command]
+pub async fn export_config(
+    app_handle: AppHandle,
+    export_path: String,
+    config_json: String,
+    overwrite: Option<bool>,
+) -> Result<(), String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .export_config(&export_path, &config_json, overwrite.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_config_as(
+    app_handle: AppHandle,
+    format: String,
+    export_path: String,
+    config_json: String,
+    password: Option<String>,
+    overwrite: Option<bool>,
+) -> Result<ExportFormat, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let format = ExportFormat::from_name(&format).map_err(|e| e.to_string())?;
+    config_manager
+        .export_config_as(
+            format,
+            &export_path,
+            &config_json,
+            password.as_deref(),
+            overwrite.unwrap_or(false),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_config_field(
+    app_handle: AppHandle,
+    password: String,
+    config_id: String,
+    field: String,
+) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.clear_config_field(&password, &config_id, &field)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn export_config_redacted(
+    app_handle: AppHandle,
+    password: String,
+    export_path: String,
+    overwrite: Option<bool>,
+) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.export_config_redacted(&password, &export_path, overwrite.unwrap_or(false))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn import_config(
+    app_handle: AppHandle,
+    import_path: String,
+    skip_validation: Option<bool>,
+) -> Result<String, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .import_config(&import_path, skip_validation.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_config_checked(
+    app_handle: AppHandle,
+    import_path: String,
+    skip_validation: Option<bool>,
+) -> Result<ImportedConfig, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .import_config_checked(&import_path, skip_validation.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn validate_config(config_json: String) -> Vec<String> {
+    validate_config_json(&config_json)
+}
+
+#[tauri::command]
+pub async fn backup_config_to(app_handle: AppHandle, directory: String) -> Result<String, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .backup_config_to(Path::new(&directory))
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn restore_config_from(
+    app_handle: AppHandle,
+    path: String,
+    password: String,
+) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.restore_config_from(Path::new(&path), &password)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn verify_all_backups(
+    app_handle: AppHandle,
+    password: String,
+) -> Result<Vec<BackupStatus>, String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.verify_all_backups(&password)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn save_preferences(app_handle: AppHandle, preferences_json: String) -> Result<(), String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .save_preferences(&preferences_json)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn load_preferences(app_handle: AppHandle) -> Result<Option<String>, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager.load_preferences().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_vault(
+    app_handle: AppHandle,
+    vault_json: String,
+    password: String,
+) -> Result<(), String> {
+    let profiles: VaultProfiles = serde_json::from_str(&vault_json).map_err(|e| e.to_string())?;
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.save_vault(&profiles, &password)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn load_vault(app_handle: AppHandle, password: String) -> Result<String, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let profiles = config_manager.load_vault(&password).map_err(|e| e.to_string())?;
+    serde_json::to_string(&profiles).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_vault_profiles(
+    app_handle: AppHandle,
+    password: String,
+) -> Result<Vec<String>, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .list_vault_profiles(&password)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_vault_profile(
+    app_handle: AppHandle,
+    password: String,
+    old_name: String,
+    new_name: String,
+) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.rename_vault_profile(&password, &old_name, &new_name)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn upgrade_all_profiles(
+    app_handle: AppHandle,
+    password: String,
+    target_algorithm: String,
+    target_kdf: String,
+) -> Result<Vec<ProfileUpgradeResult>, String> {
+    let algorithm = CipherAlgorithm::from_name(&target_algorithm)
+        .ok_or_else(|| format!("Unsupported encryption algorithm: {}", target_algorithm))?;
+    let kdf = KdfAlgorithm::from_name(&target_kdf)
+        .ok_or_else(|| format!("Unsupported key derivation: {}", target_kdf))?;
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.upgrade_all_profiles(&password, algorithm, kdf)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_password_hint(
+    app_handle: AppHandle,
+    profile: String,
+    hint: Option<String>,
+) -> Result<(), String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .set_password_hint(&profile, hint.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_password_hint(
+    app_handle: AppHandle,
+    profile: String,
+) -> Result<Option<String>, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .get_password_hint(&profile)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_profile_labels(app_handle: AppHandle) -> Result<Vec<ProfileInfo>, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager.get_profile_labels().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn migrate_config_to_vault(
+    app_handle: AppHandle,
+    password: String,
+    profile_name: String,
+) -> Result<(), String> {
+    run_config_task(&app_handle, move |config_manager| {
+        config_manager.migrate_config_to_vault(&password, &profile_name)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn config_fingerprint(app_handle: AppHandle, password: String) -> Result<String, String> {
+    run_config_task(&app_handle, move |config_manager| config_manager.config_fingerprint(&password)).await
+}
+
+#[tauri::command]
+pub async fn config_file_hash(app_handle: AppHandle) -> Result<String, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .config_file_hash()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_active_config_crypto_info(app_handle: AppHandle) -> Result<CryptoInfo, String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    config_manager
+        .active_config_crypto_info()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_crypto_capabilities() -> CryptoCapabilities {
+    crypto_capabilities()
+}
+
+/// Sanity-check the OS RNG before generating keys; drawing from it can block at boot
+#[tauri::command]
+pub async fn check_rng_health() -> Result<RngHealth, String> {
+    tokio::task::spawn_blocking(rng_health)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Expected cost of deriving the config key with `kdf` (the default KDF when omitted),
+/// so the UI can decide whether to show progress
+#[tauri::command]
+pub async fn estimate_encryption_time(kdf: Option<String>) -> Result<u64, String> {
+    let kdf = match kdf {
+        Some(name) => KdfAlgorithm::from_name(&name)
+            .ok_or_else(|| format!("Unsupported key derivation: {}", name))?,
+        None => KdfAlgorithm::default(),
+    };
+    tokio::task::spawn_blocking(move || encryption_time_estimate_ms(kdf))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pre-warm key derivation in the background; see `warm_up_crypto`
+#[tauri::command]
+pub async fn warmup_crypto() -> Result<(), String> {
+    tokio::task::spawn_blocking(warm_up_crypto)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn select_export_path(app_handle: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    use std::sync::{Arc, Mutex};
+    
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    
+    app_handle
+        .dialog()
+        .file()
+        .add_filter("JSON files", &["json"])
+        .set_file_name("s3-config-export.json")
+        .save_file(move |path| {
+            *result_clone.lock().unwrap() = path;
+        });
+    
+    // In a real implementation, you'd want to use async/await properly
+    // For now, we'll return None as this is just for testing
+    Ok(None)
+}
+
+#[tauri::command]
+pub async fn select_import_path(app_handle: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    use std::sync::{Arc, Mutex};
+    
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    
+    app_handle
+        .dialog()
+        .file()
+        .add_filter("JSON files", &["json"])
+        .pick_file(move |path| {
+            *result_clone.lock().unwrap() = path;
+        });
+    
+    // In a real implementation, you'd want to use async/await properly
+    // For now, we'll return None as this is just for testing
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -431,234 +3003,1548 @@ mod tests {
     use std::env;
     use std::fs;
 
-    // Mock AppHandle for testing
-    struct MockConfigManager {
-        config_dir: PathBuf,
+    // Mock AppHandle for testing
+    struct MockConfigManager {
+        config_dir: PathBuf,
+    }
+
+    impl MockConfigManager {
+        fn new() -> Result<Self, ConfigError> {
+            let config_dir = env::temp_dir().join("s3-upload-tool-test");
+            if config_dir.exists() {
+                fs::remove_dir_all(&config_dir)?;
+            }
+            fs::create_dir_all(&config_dir)?;
+            Ok(MockConfigManager { config_dir })
+        }
+
+        fn get_config_path(&self) -> PathBuf {
+            self.config_dir.join("config.encrypted")
+        }
+
+        fn derive_key(&self, password: &str, salt: &[u8]) -> Result<SecureKey, ConfigError> {
+            let secure_password = SecureString::new(password.to_string());
+            let mut key = [0u8; 32];
+            pbkdf2::pbkdf2::<Hmac<Sha256>>(
+                secure_password.as_bytes(),
+                salt,
+                PBKDF2_ITERATIONS,
+                &mut key,
+            );
+            Ok(SecureKey::new(key))
+        }
+
+        fn generate_secure_salt(&self) -> [u8; 32] {
+            let mut salt = [0u8; 32];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }
+
+        fn generate_secure_nonce(&self) -> [u8; 12] {
+            let mut nonce = [0u8; 12];
+            OsRng.fill_bytes(&mut nonce);
+            nonce
+        }
+
+        fn secure_delete_file(&self, path: &PathBuf) -> Result<(), ConfigError> {
+            if path.exists() {
+                let file_size = fs::metadata(path)?.len() as usize;
+                if file_size > 0 {
+                    let mut random_data = vec![0u8; file_size];
+                    OsRng.fill_bytes(&mut random_data);
+                    fs::write(path, &random_data)?;
+                    
+                    let zero_data = vec![0u8; file_size];
+                    fs::write(path, &zero_data)?;
+                }
+                
+                fs::remove_file(path)?;
+            }
+            Ok(())
+        }
+
+        fn save_config(&self, config_json: &str, password: &str) -> Result<(), ConfigError> {
+            let secure_password = SecureString::new(password.to_string());
+            let secure_config = SecureString::new(config_json.to_string());
+            
+            let salt = self.generate_secure_salt();
+            let nonce_bytes = self.generate_secure_nonce();
+
+            let secure_key = self.derive_key(password, &salt)?;
+
+            let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
+                .map_err(|e| ConfigError::Encryption(format!("Cipher creation error: {}", e)))?;
+
+            let nonce = Nonce::from_slice(&nonce_bytes);
+
+            let encrypted_data = cipher
+                .encrypt(nonce, secure_config.as_bytes())
+                .map_err(|e| ConfigError::Encryption(format!("Encryption failed: {}", e)))?;
+
+            let encrypted_config = EncryptedConfig {
+                data: general_purpose::STANDARD.encode(&encrypted_data),
+                salt: general_purpose::STANDARD.encode(&salt),
+                nonce: general_purpose::STANDARD.encode(&nonce_bytes),
+                version: ENCRYPTION_VERSION.to_string(),
+                algorithm: ENCRYPTION_ALGORITHM.to_string(),
+                iterations: PBKDF2_ITERATIONS,
+                kdf: None,
+                memory_kib: None,
+                parallelism: None,
+            };
+
+            let config_path = self.get_config_path();
+            let json_data = serde_json::to_string_pretty(&encrypted_config)?;
+            fs::write(&config_path, json_data)?;
+
+            Ok(())
+        }
+
+        fn load_config(&self, password: &str) -> Result<String, ConfigError> {
+            let config_path = self.get_config_path();
+
+            if !config_path.exists() {
+                return Err(ConfigError::ConfigNotFound);
+            }
+
+            let file_content = fs::read_to_string(config_path)?;
+            let encrypted_config: EncryptedConfig = serde_json::from_str(&file_content)?;
+
+            let encrypted_data = general_purpose::STANDARD
+                .decode(&encrypted_config.data)
+                .map_err(|e| ConfigError::Decryption(format!("Base64 decode error: {}", e)))?;
+
+            let salt = general_purpose::STANDARD
+                .decode(&encrypted_config.salt)
+                .map_err(|e| ConfigError::Decryption(format!("Salt decode error: {}", e)))?;
+
+            let nonce_bytes = general_purpose::STANDARD
+                .decode(&encrypted_config.nonce)
+                .map_err(|e| ConfigError::Decryption(format!("Nonce decode error: {}", e)))?;
+
+            let secure_key = self.derive_key(password, &salt)?;
+
+            let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
+                .map_err(|e| ConfigError::Decryption(format!("Cipher creation error: {}", e)))?;
+
+            let nonce = Nonce::from_slice(&nonce_bytes);
+
+            let decrypted_data = cipher
+                .decrypt(nonce, encrypted_data.as_ref())
+                .map_err(|_| ConfigError::InvalidPassword)?;
+
+            let secure_config = SecureString::from_bytes(decrypted_data);
+            let config_json = secure_config.as_str()
+                .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?
+                .to_string();
+
+            Ok(config_json)
+        }
+
+        fn config_exists(&self) -> bool {
+            self.get_config_path().exists()
+        }
+
+        fn delete_config(&self) -> Result<(), ConfigError> {
+            let config_path = self.get_config_path();
+            self.secure_delete_file(&config_path)?;
+            Ok(())
+        }
+    }
+
+    // Create a fresh directory per test so parallel tests don't clobber each other
+    fn unique_test_dir(label: &str) -> PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        env::temp_dir().join(format!("s3-upload-tool-{}-{}", label, timestamp))
+    }
+
+    #[test]
+    fn test_config_save_and_load() {
+        let manager = MockConfigManager::new().unwrap();
+        let test_config = r#"{"test": "data", "number": 42}"#;
+        let password = "test-password";
+
+        // Test saving config
+        assert!(manager.save_config(test_config, password).is_ok());
+        assert!(manager.config_exists());
+
+        // Test loading config
+        let loaded_config = manager.load_config(password).unwrap();
+        assert_eq!(loaded_config, test_config);
+    }
+
+    /// `save_config` output for `{"configs": []}` under "golden-password" with the
+    /// test RNG seeded to 7. Any change here means existing envelopes changed shape.
+    const GOLDEN_ENVELOPE: &str = r#"{
+  "data": "rfdAcR42r+k7ZNUeYhAOQEnG3dfz+wQjuWMur02kiA==",
+  "salt": "vvuoaungwgeGX34k6DSdTs28iw9GMoQkmaDfpgVo4oo=",
+  "nonce": "tiFinq1hwkXga7C8",
+  "version": "1.1",
+  "algorithm": "AES-256-GCM",
+  "iterations": 100000
+}"#;
+
+    /// The same config written by the pre-AAD format
+    const LEGACY_GOLDEN_ENVELOPE: &str = r#"{
+  "data": "rfdAcR42r+k7ZNUeYhAOxwOQtBtCnHTlEnlpd5WWRA==",
+  "salt": "vvuoaungwgeGX34k6DSdTs28iw9GMoQkmaDfpgVo4oo=",
+  "nonce": "tiFinq1hwkXga7C8",
+  "version": "1.0",
+  "algorithm": "AES-256-GCM",
+  "iterations": 100000
+}"#;
+
+    #[test]
+    fn test_seeded_envelope_matches_golden_file() {
+        let manager = ConfigManager::from_dir(unique_test_dir("golden-envelope")).unwrap();
+        test_rng::seed(7);
+        manager
+            .save_config(r#"{"configs": []}"#, "golden-password")
+            .unwrap();
+        test_rng::clear();
+
+        let saved = fs::read_to_string(manager.get_config_path()).unwrap();
+        assert_eq!(saved, GOLDEN_ENVELOPE);
+        assert_eq!(manager.load_config("golden-password").unwrap(), r#"{"configs": []}"#);
+    }
+
+    #[test]
+    fn test_legacy_envelope_without_aad_still_decrypts() {
+        let manager = ConfigManager::from_dir(unique_test_dir("legacy-envelope")).unwrap();
+        fs::write(manager.get_config_path(), LEGACY_GOLDEN_ENVELOPE).unwrap();
+        assert_eq!(manager.load_config("golden-password").unwrap(), r#"{"configs": []}"#);
+    }
+
+    #[test]
+    fn test_tampered_envelope_metadata_fails_decryption() {
+        let manager = ConfigManager::from_dir(unique_test_dir("aad-tamper")).unwrap();
+        manager.save_config(r#"{"configs": []}"#, "aad-password").unwrap();
+
+        let mut envelope = manager.read_envelope(&manager.get_config_path()).unwrap();
+        assert_eq!(envelope.version, ENCRYPTION_VERSION);
+        envelope.iterations = 1_000;
+        assert!(matches!(
+            manager.try_decrypt_envelope(&envelope, "aad-password"),
+            Err(ConfigError::InvalidPassword)
+        ));
+
+        envelope.iterations = PBKDF2_ITERATIONS;
+        envelope.version = LEGACY_ENCRYPTION_VERSION.to_string();
+        assert!(manager.try_decrypt_envelope(&envelope, "aad-password").is_err());
+    }
+
+    #[test]
+    fn test_excessive_argon2_costs_are_refused_before_deriving() {
+        let manager = ConfigManager::from_dir(unique_test_dir("argon2-ceiling")).unwrap();
+        let password = "argon2-password";
+        let envelope = manager
+            .encrypt_envelope_with_kdf(
+                r#"{"configs": []}"#,
+                password,
+                CipherAlgorithm::Aes256Gcm,
+                KdfAlgorithm::Argon2id,
+            )
+            .unwrap();
+
+        let tampered = [
+            EncryptedConfig { memory_kib: Some(4 * 1024 * 1024), ..envelope.clone() },
+            EncryptedConfig { iterations: u32::MAX, ..envelope.clone() },
+            EncryptedConfig { parallelism: Some(1024), ..envelope.clone() },
+        ];
+        for tampered in tampered {
+            let started = Instant::now();
+            assert!(matches!(
+                manager.try_decrypt_envelope(&tampered, password),
+                Err(ConfigError::Decryption(_))
+            ));
+            assert!(started.elapsed() < Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_argon2_envelope_records_and_authenticates_its_costs() {
+        let manager = ConfigManager::from_dir(unique_test_dir("argon2-costs")).unwrap();
+        let password = "argon2-password";
+        let mut envelope = manager
+            .encrypt_envelope_with_kdf(
+                r#"{"configs": []}"#,
+                password,
+                CipherAlgorithm::Aes256Gcm,
+                KdfAlgorithm::Argon2id,
+            )
+            .unwrap();
+        assert_eq!(envelope.iterations, ARGON2_TIME_COST);
+        assert_eq!(envelope.memory_kib, Some(ARGON2_MEMORY_KIB));
+        assert_eq!(envelope.parallelism, Some(ARGON2_PARALLELISM));
+        assert_eq!(manager.decrypt_envelope(&envelope, password).unwrap(), r#"{"configs": []}"#);
+
+        envelope.memory_kib = Some(ARGON2_MEMORY_KIB * 2);
+        assert!(matches!(
+            manager.try_decrypt_envelope(&envelope, password),
+            Err(ConfigError::InvalidPassword)
+        ));
+
+        // Argon2 envelopes written before the costs were recorded still decrypt
+        let salt = [7u8; 32];
+        let nonce = [9u8; 12];
+        let mut older = EncryptedConfig {
+            data: String::new(),
+            salt: general_purpose::STANDARD.encode(salt),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            version: ENCRYPTION_VERSION.to_string(),
+            algorithm: ENCRYPTION_ALGORITHM.to_string(),
+            iterations: ARGON2_TIME_COST,
+            kdf: Some(ARGON2_KDF.to_string()),
+            memory_kib: None,
+            parallelism: None,
+        };
+        let key = derive_secure_key(
+            &SecureString::new(password.to_string()),
+            &salt,
+            KdfAlgorithm::Argon2id,
+            Argon2Costs::default(),
+        )
+        .unwrap();
+        let ciphertext = CipherAlgorithm::Aes256Gcm
+            .encrypt(&key, &nonce, b"{}", &envelope_aad(&older).unwrap())
+            .unwrap();
+        older.data = general_purpose::STANDARD.encode(ciphertext);
+        assert_eq!(manager.decrypt_envelope(&older, password).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_concurrent_saves_leave_a_loadable_config() {
+        let dir = unique_test_dir("concurrent-saves");
+        let password = "concurrent-password";
+        let configs: Vec<String> = (0..8)
+            .map(|i| format!(r#"{{"configs": [{{"id": "{}", "name": "Writer {}"}}]}}"#, i, i))
+            .collect();
+
+        std::thread::scope(|scope| {
+            for config in &configs {
+                let dir = dir.clone();
+                scope.spawn(move || {
+                    let manager = ConfigManager::from_dir(dir).unwrap();
+                    manager.save_config(config, password).unwrap();
+                });
+            }
+        });
+
+        let manager = ConfigManager::from_dir(dir).unwrap();
+        let loaded = manager.load_config(password).unwrap();
+        assert!(configs.contains(&loaded));
+        let backup = manager.read_envelope(&manager.get_backup_path()).unwrap();
+        assert!(configs.contains(&manager.decrypt_envelope(&backup, password).unwrap()));
+    }
+
+    #[test]
+    fn test_concurrent_vault_upserts_keep_every_profile() {
+        let dir = unique_test_dir("concurrent-upserts");
+        let password = "concurrent-password";
+        let names: Vec<String> = (0..6).map(|i| format!("profile-{}", i)).collect();
+
+        std::thread::scope(|scope| {
+            for name in &names {
+                let dir = dir.clone();
+                scope.spawn(move || {
+                    let manager = ConfigManager::from_dir(dir).unwrap();
+                    manager
+                        .upsert_vault_profile(password, name, serde_json::json!({"bucketName": name}))
+                        .unwrap();
+                });
+            }
+        });
+
+        let manager = ConfigManager::from_dir(dir).unwrap();
+        assert_eq!(manager.list_vault_profiles(password).unwrap(), names);
+    }
+
+    #[test]
+    fn test_warm_up_crypto_is_repeatable_and_touches_no_files() {
+        let listing = || {
+            let mut names: Vec<_> = fs::read_dir(".").unwrap().map(|entry| entry.unwrap().file_name()).collect();
+            names.sort();
+            names
+        };
+        let before = listing();
+
+        warm_up_crypto().unwrap();
+        warm_up_crypto().unwrap();
+
+        assert_eq!(listing(), before);
+    }
+
+    #[test]
+    fn test_rng_health_passes_normally() {
+        let health = rng_health();
+        assert!(health.healthy, "{:?}", health.error);
+        assert_eq!(health.sample_len, RNG_HEALTH_SAMPLE_LEN);
+        assert!(health.distinct_bytes >= RNG_MIN_DISTINCT_BYTES);
+    }
+
+    #[test]
+    fn test_save_refuses_when_rng_has_no_entropy() {
+        crate::audit::test_capture::install();
+        let manager = ConfigManager::from_dir(unique_test_dir("rng-broken")).unwrap();
+
+        test_rng::break_rng();
+        let health = rng_health();
+        let saved = manager.save_config(r#"{"configs": []}"#, "rng-password");
+        test_rng::clear();
+
+        assert!(!health.healthy);
+        assert!(matches!(saved, Err(ConfigError::RandomnessUnavailable(_))));
+        assert!(!manager.config_exists());
+        assert_eq!(health.distinct_bytes, 1);
+        assert!(!crate::audit::test_capture::lines_containing("event=rng_unhealthy").is_empty());
+    }
+
+    #[test]
+    fn test_future_envelope_version_is_reported_as_unsupported() {
+        let manager = ConfigManager::from_dir(unique_test_dir("future-version")).unwrap();
+        manager.save_config(r#"{"configs": []}"#, "future-password").unwrap();
+
+        let mut envelope = manager.read_envelope(&manager.get_config_path()).unwrap();
+        envelope.version = "2.0".to_string();
+        fs::write(manager.get_config_path(), serde_json::to_string(&envelope).unwrap()).unwrap();
+
+        match manager.load_config("future-password") {
+            Err(ConfigError::UnsupportedVersion { found, max_supported }) => {
+                assert_eq!(found, "2.0");
+                assert_eq!(max_supported, ENCRYPTION_VERSION);
+            }
+            other => panic!("expected UnsupportedVersion, got {:?}", other),
+        }
+
+        // Unparseable versions are still a plain decryption error
+        envelope.version = "next".to_string();
+        assert!(matches!(
+            manager.try_decrypt_envelope(&envelope, "future-password"),
+            Err(ConfigError::Decryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_encryption_time_estimate_is_cached() {
+        for kdf in [KdfAlgorithm::Pbkdf2Sha256, KdfAlgorithm::Argon2id] {
+            let first = encryption_time_estimate_ms(kdf);
+            assert!(first > 0);
+            assert_eq!(encryption_time_estimate_ms(kdf), first);
+        }
+    }
+
+    #[test]
+    fn test_config_wrong_password() {
+        let manager = MockConfigManager::new().unwrap();
+        let test_config = r#"{"test": "data"}"#;
+        let password = "correct-password";
+        let wrong_password = "wrong-password";
+
+        // Save with correct password
+        manager.save_config(test_config, password).unwrap();
+
+        // Try to load with wrong password
+        let result = manager.load_config(wrong_password);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ConfigError::InvalidPassword));
+    }
+
+    #[test]
+    fn test_failed_decrypt_audit_log_has_no_secrets() {
+        crate::audit::test_capture::install();
+        let manager = ConfigManager::from_dir(unique_test_dir("audit-decrypt")).unwrap();
+        let password = "audit-correct-password";
+        let wrong_password = "audit-wrong-password";
+        let secret = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYAUDITKEY";
+
+        manager
+            .save_config(&format!(r#"{{"secretAccessKey": "{}"}}"#, secret), password)
+            .unwrap();
+        assert!(matches!(
+            manager.load_config(wrong_password),
+            Err(ConfigError::InvalidPassword)
+        ));
+
+        let lines = crate::audit::test_capture::lines_containing("event=decrypt_failed");
+        assert!(lines.iter().any(|line| line.contains("invalid_password")));
+        for line in crate::audit::test_capture::lines_containing("audit") {
+            assert!(!line.contains(password));
+            assert!(!line.contains(wrong_password));
+            assert!(!line.contains(secret));
+        }
+    }
+
+    #[test]
+    fn test_wrong_passwords_back_off_until_success() {
+        let dir = unique_test_dir("password-throttle");
+        let throttle = PasswordThrottle {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        };
+        let manager = ConfigManager::from_dir(dir.clone())
+            .unwrap()
+            .with_password_throttle(throttle);
+        let password = "throttle-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+
+        let mut previous_delay = Duration::ZERO;
+        for expected_wait in [Duration::ZERO, Duration::from_millis(100), Duration::from_millis(200)] {
+            let started = Instant::now();
+            assert!(matches!(manager.load_config("wrong"), Err(ConfigError::InvalidPassword)));
+            assert!(started.elapsed() >= expected_wait);
+
+            let delay = manager.password_retry_delay();
+            assert!(delay > previous_delay);
+            previous_delay = delay;
+        }
+
+        // A fresh manager (as after a restart) still sees the backoff, read back from disk
+        failed_attempts().lock().unwrap().remove(&manager.get_config_path());
+        let restarted = ConfigManager::from_dir(dir).unwrap().with_password_throttle(throttle);
+        assert!(restarted.password_retry_delay() > Duration::ZERO);
+
+        assert!(manager.load_config(password).is_ok());
+        assert_eq!(manager.password_retry_delay(), Duration::ZERO);
+        assert!(!manager.get_password_attempts_path().exists());
+        assert!(!ConfigManager::backup_path_for(&manager.get_password_attempts_path()).exists());
+    }
+
+    #[test]
+    fn test_config_not_found() {
+        let manager = MockConfigManager::new().unwrap();
+        let password = "test-password";
+
+        // Try to load non-existent config
+        let result = manager.load_config(password);
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), ConfigError::ConfigNotFound));
+    }
+
+    #[test]
+    fn test_config_delete() {
+        let manager = MockConfigManager::new().unwrap();
+        let test_config = r#"{"test": "data"}"#;
+        let password = "test-password";
+
+        // Save config
+        manager.save_config(test_config, password).unwrap();
+        assert!(manager.config_exists());
+
+        // Delete config
+        manager.delete_config().unwrap();
+        assert!(!manager.config_exists());
+    }
+
+    #[test]
+    fn test_encryption_decryption_roundtrip() {
+        let manager = MockConfigManager::new().unwrap();
+        let test_configs = vec![
+            r#"{"simple": "test"}"#,
+            r#"{"complex": {"nested": {"data": [1, 2, 3]}, "unicode": "测试数据"}}"#,
+            r#"{"empty": {}}"#,
+            r#"{"special_chars": "!@#$%^&*()_+-=[]{}|;':\",./<>?"}"#,
+        ];
+
+        for test_config in test_configs {
+            let password = "test-password-123";
+
+            // Save and load
+            manager.save_config(test_config, password).unwrap();
+            let loaded_config = manager.load_config(password).unwrap();
+
+            assert_eq!(loaded_config, test_config);
+
+            // Clean up for next test
+            manager.delete_config().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_password_policy_rules() {
+        let policy = PasswordPolicy {
+            min_length: 12,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            max_repeated: Some(2),
+        };
+        let report = policy.check("Correct-Horse-42");
+        assert!(report.passed);
+        assert_eq!(report.rules.len(), 6);
+
+        let failures = [
+            ("Sh0rt-pass", "min_length"),
+            ("NO-LOWERCASE-42", "lowercase"),
+            ("no-uppercase-42", "uppercase"),
+            ("No-Digits-Here", "digit"),
+            ("NoSymbolsHere42", "symbol"),
+            ("Correct-Horsssse-42", "max_repeated"),
+        ];
+        for (password, rule) in failures {
+            let report = policy.check(password);
+            assert!(!report.passed, "{} should fail", password);
+            assert_eq!(report.failed_rules(), vec![rule.to_string()], "{}", password);
+        }
+
+        // Disabled rules aren't reported at all
+        assert!(PasswordPolicy::default().check("").rules.is_empty());
+    }
+
+    #[test]
+    fn test_save_config_enforces_password_policy() {
+        let manager = ConfigManager::from_dir(unique_test_dir("password-policy")).unwrap();
+        let options = SaveOptions {
+            password_policy: Some(PasswordPolicy {
+                min_length: 12,
+                require_digit: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let result = manager.save_config_with_options(r#"{"configs": []}"#, "weak", &options);
+        match result {
+            Err(ConfigError::WeakPassword(rules)) => assert_eq!(rules, vec!["min_length", "digit"]),
+            other => panic!("expected WeakPassword, got {:?}", other),
+        }
+        assert!(!manager.config_exists());
+
+        manager
+            .save_config_with_options(r#"{"configs": []}"#, "long-enough-password-7", &options)
+            .unwrap();
+        assert!(manager.config_exists());
+    }
+
+    #[test]
+    fn test_save_config_with_verification() {
+        let manager = ConfigManager::from_dir(unique_test_dir("verify-save")).unwrap();
+        let options = SaveOptions {
+            verify: true,
+            ..Default::default()
+        };
+
+        manager
+            .save_config_with_options(r#"{"version": 1}"#, "verify-password", &options)
+            .unwrap();
+        manager
+            .save_config_with_options(r#"{"version": 2}"#, "verify-password", &options)
+            .unwrap();
+
+        assert_eq!(manager.load_config("verify-password").unwrap(), r#"{"version": 2}"#);
+        assert!(manager.get_backup_path().exists());
+    }
+
+    #[test]
+    fn test_verification_failure_rolls_back_to_backup() {
+        let manager = ConfigManager::from_dir(unique_test_dir("verify-rollback")).unwrap();
+        let password = "rollback-password";
+        manager.save_config(r#"{"version": 1}"#, password).unwrap();
+        let original = fs::read_to_string(manager.get_config_path()).unwrap();
+
+        // Simulate a save whose written envelope got corrupted on the way to disk
+        let mut corrupted: EncryptedConfig = serde_json::from_str(&original).unwrap();
+        corrupted.data = general_purpose::STANDARD.encode(b"corrupted ciphertext bytes");
+        let had_previous = manager
+            .write_config_atomically(&serde_json::to_string_pretty(&corrupted).unwrap(), false)
+            .unwrap();
+
+        let result = manager.verify_or_rollback(r#"{"version": 2}"#, password, had_previous);
+
+        assert!(matches!(result, Err(ConfigError::VerificationFailed(_))));
+        assert_eq!(fs::read_to_string(manager.get_config_path()).unwrap(), original);
+        assert_eq!(manager.load_config(password).unwrap(), r#"{"version": 1}"#);
+    }
+
+    #[test]
+    fn test_config_fingerprint_ignores_envelope_and_key_order() {
+        let first = ConfigManager::from_dir(unique_test_dir("fingerprint-a")).unwrap();
+        let second = ConfigManager::from_dir(unique_test_dir("fingerprint-b")).unwrap();
+        first
+            .save_config(r#"{"configs": [{"id": "1", "region": "eu-west-1"}], "activeConfigId": "1"}"#, "first-password")
+            .unwrap();
+        second
+            .save_config(r#"{"activeConfigId":"1","configs":[{"region":"eu-west-1","id":"1"}]}"#, "second-password")
+            .unwrap();
+
+        let fingerprint = first.config_fingerprint("first-password").unwrap();
+        assert_eq!(fingerprint.len(), FINGERPRINT_LEN);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(second.config_fingerprint("second-password").unwrap(), fingerprint);
+
+        second
+            .save_config(r#"{"configs": [{"id": "1", "region": "eu-west-2"}], "activeConfigId": "1"}"#, "second-password")
+            .unwrap();
+        assert_ne!(second.config_fingerprint("second-password").unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn test_config_file_hash_tracks_envelope_bytes() {
+        let manager = ConfigManager::from_dir(unique_test_dir("file-hash")).unwrap();
+        assert!(matches!(manager.config_file_hash(), Err(ConfigError::ConfigNotFound)));
+
+        manager.save_config(r#"{"configs": []}"#, "hash-password").unwrap();
+        let hash = manager.config_file_hash().unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(manager.config_file_hash().unwrap(), hash);
+        manager.load_config("hash-password").unwrap();
+        assert_eq!(manager.config_file_hash().unwrap(), hash);
+
+        // Same plaintext, fresh salt and nonce
+        manager.save_config(r#"{"configs": []}"#, "hash-password").unwrap();
+        assert_ne!(manager.config_file_hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_migration_round_trips_or_rolls_back() {
+        let manager = ConfigManager::from_dir(unique_test_dir("migrate-config")).unwrap();
+        let password = "migrate-password";
+        let config = r#"{"configs": [{"id": "1"}]}"#;
+        manager.save_config(config, password).unwrap();
+        let original = fs::read_to_string(manager.get_config_path()).unwrap();
+
+        // A faulty migration step that drops part of the config
+        let result = manager.migrate_config_with(password, |_| {
+            manager.encrypt_envelope(r#"{"configs": []}"#, password, CipherAlgorithm::ChaCha20Poly1305)
+        });
+        assert!(matches!(result, Err(ConfigError::VerificationFailed(_))));
+        assert_eq!(fs::read_to_string(manager.get_config_path()).unwrap(), original);
+
+        manager
+            .migrate_config(password, CipherAlgorithm::ChaCha20Poly1305)
+            .unwrap();
+        assert_eq!(manager.active_config_crypto_info().unwrap().algorithm, "ChaCha20-Poly1305");
+        assert_eq!(manager.load_config(password).unwrap(), config);
+        manager.post_migration_verify(password).unwrap();
+
+        // A later out-of-band change no longer matches the backup and is undone
+        let tampered = manager.encrypt_envelope(r#"{"configs": []}"#, password, CipherAlgorithm::default()).unwrap();
+        fs::write(manager.get_config_path(), serde_json::to_string_pretty(&tampered).unwrap()).unwrap();
+        assert!(matches!(
+            manager.post_migration_verify(password),
+            Err(ConfigError::VerificationFailed(_))
+        ));
+        assert_eq!(fs::read_to_string(manager.get_config_path()).unwrap(), original);
+        assert!(matches!(
+            manager.post_migration_verify(password),
+            Err(ConfigError::NoPendingMigration)
+        ));
+    }
+
+    #[test]
+    fn test_save_after_migration_is_not_rolled_back() {
+        let manager = ConfigManager::from_dir(unique_test_dir("migrate-then-save")).unwrap();
+        let password = "migrate-save-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+        manager
+            .migrate_config(password, CipherAlgorithm::ChaCha20Poly1305)
+            .unwrap();
+
+        let edited = r#"{"configs": [{"id": "edited"}]}"#;
+        manager.save_config(edited, password).unwrap();
+        assert!(matches!(
+            manager.post_migration_verify(password),
+            Err(ConfigError::NoPendingMigration)
+        ));
+        assert_eq!(manager.load_config(password).unwrap(), edited);
+    }
+
+    #[test]
+    fn test_export_config_refuses_to_overwrite() {
+        let dir = unique_test_dir("export-guard");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let export_path = dir.join("export.json");
+        let export_path = export_path.to_string_lossy();
+
+        manager.export_config(&export_path, r#"{"first": true}"#, false).unwrap();
+        let result = manager.export_config(&export_path, r#"{"second": true}"#, false);
+
+        assert!(matches!(result, Err(ConfigError::ExportExists(_))));
+        assert_eq!(fs::read_to_string(&*export_path).unwrap(), r#"{"first": true}"#);
+    }
+
+    #[test]
+    fn test_export_config_forced_overwrite() {
+        let dir = unique_test_dir("export-overwrite");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let export_path = dir.join("export.json");
+        let export_path = export_path.to_string_lossy();
+
+        manager.export_config(&export_path, r#"{"first": true}"#, false).unwrap();
+        manager.export_config(&export_path, r#"{"second": true}"#, true).unwrap();
+
+        assert_eq!(fs::read_to_string(&*export_path).unwrap(), r#"{"second": true}"#);
+    }
+
+    #[test]
+    fn test_export_config_as_each_format() {
+        let dir = unique_test_dir("export-formats");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let config = r#"{"configs": []}"#;
+        let password = "export-password";
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let format = manager
+            .export_config_as(ExportFormat::PlaintextJson, &path("plain.json"), config, None, false)
+            .unwrap();
+        assert_eq!(format, ExportFormat::PlaintextJson);
+        assert_eq!(fs::read_to_string(path("plain.json")).unwrap(), config);
+
+        let format = manager
+            .export_config_as(ExportFormat::Encrypted, &path("export.encrypted"), config, Some(password), false)
+            .unwrap();
+        assert_eq!(format, ExportFormat::Encrypted);
+        let envelope = manager.read_envelope(Path::new(&path("export.encrypted"))).unwrap();
+        assert_eq!(manager.decrypt_envelope(&envelope, password).unwrap(), config);
+
+        let format = manager
+            .export_config_as(ExportFormat::Compact, &path("export.txt"), config, Some(password), false)
+            .unwrap();
+        assert_eq!(format, ExportFormat::Compact);
+        let compact = fs::read_to_string(path("export.txt")).unwrap();
+        assert!(!compact.contains('\n'));
+        let envelope: EncryptedConfig =
+            serde_json::from_slice(&general_purpose::STANDARD.decode(compact).unwrap()).unwrap();
+        assert_eq!(manager.decrypt_envelope(&envelope, password).unwrap(), config);
+
+        for format in [ExportFormat::Encrypted, ExportFormat::Compact] {
+            for missing in [None, Some("")] {
+                assert!(matches!(
+                    manager.export_config_as(format, &path("missing.out"), config, missing, false),
+                    Err(ConfigError::PasswordRequired(_))
+                ));
+            }
+        }
+        assert!(!dir.join("missing.out").exists());
+        assert!(matches!(
+            ExportFormat::from_name("yaml"),
+            Err(ConfigError::UnknownExportFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_crypto_capabilities_match_constants() {
+        let capabilities = crypto_capabilities();
+
+        assert!(capabilities.algorithms.iter().any(|a| a == "AES-256-GCM"));
+        assert!(capabilities.kdfs.iter().any(|k| k == "PBKDF2-HMAC-SHA256"));
+        assert_eq!(capabilities.default_algorithm, ENCRYPTION_ALGORITHM);
+        assert_eq!(capabilities.default_kdf, KEY_DERIVATION);
+        assert_eq!(capabilities.default_iterations, PBKDF2_ITERATIONS);
+        assert_eq!(capabilities.envelope_version, ENCRYPTION_VERSION);
+    }
+
+    #[test]
+    fn test_chacha_config_roundtrip() {
+        let manager = ConfigManager::from_dir(unique_test_dir("chacha-roundtrip")).unwrap();
+        let options = SaveOptions {
+            algorithm: CipherAlgorithm::ChaCha20Poly1305,
+            ..Default::default()
+        };
+        let test_config = r#"{"cipher": "chacha", "unicode": "测试数据"}"#;
+
+        manager
+            .save_config_with_options(test_config, "chacha-password", &options)
+            .unwrap();
+
+        let envelope: EncryptedConfig =
+            serde_json::from_str(&fs::read_to_string(manager.get_config_path()).unwrap()).unwrap();
+        assert_eq!(envelope.algorithm, "ChaCha20-Poly1305");
+        assert_eq!(manager.load_config("chacha-password").unwrap(), test_config);
+        assert!(matches!(
+            manager.load_config("wrong-password"),
+            Err(ConfigError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn test_aes_config_still_loads_alongside_chacha() {
+        let manager = ConfigManager::from_dir(unique_test_dir("aes-default")).unwrap();
+        let test_config = r#"{"cipher": "aes"}"#;
+
+        manager.save_config(test_config, "aes-password").unwrap();
+
+        let envelope: EncryptedConfig =
+            serde_json::from_str(&fs::read_to_string(manager.get_config_path()).unwrap()).unwrap();
+        assert_eq!(envelope.algorithm, "AES-256-GCM");
+        assert_eq!(manager.load_config("aes-password").unwrap(), test_config);
+    }
+
+    #[test]
+    fn test_streaming_secure_delete_large_file() {
+        let dir = unique_test_dir("secure-delete-stream");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("large.part");
+        let file_size = SECURE_DELETE_CHUNK_SIZE * 3 + 12345;
+        fs::write(&path, vec![0xABu8; file_size]).unwrap();
+
+        let mut events = Vec::new();
+        secure_remove_file_with_progress(&path, |progress| events.push(progress)).unwrap();
+
+        assert!(!path.exists());
+        // Four chunks per pass, two passes
+        assert_eq!(events.len(), 8);
+        for pass in 1..=SECURE_DELETE_PASSES {
+            let last = events.iter().rev().find(|e| e.pass == pass).unwrap();
+            assert_eq!(last.bytes_written, file_size as u64);
+            assert_eq!(last.total_bytes, file_size as u64);
+        }
+    }
+
+    #[test]
+    fn test_vault_add_and_remove_profiles() {
+        let manager = ConfigManager::from_dir(unique_test_dir("vault-profiles")).unwrap();
+        let password = "vault-password";
+
+        manager
+            .upsert_vault_profile(password, "work", serde_json::json!({"bucketName": "work-bucket"}))
+            .unwrap();
+        manager
+            .upsert_vault_profile(password, "personal", serde_json::json!({"bucketName": "photos"}))
+            .unwrap();
+        assert!(manager.vault_exists());
+        assert_eq!(manager.list_vault_profiles(password).unwrap(), vec!["personal", "work"]);
+
+        assert!(manager.remove_vault_profile(password, "work").unwrap());
+        assert!(!manager.remove_vault_profile(password, "work").unwrap());
+
+        let profiles = manager.load_vault(password).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles["personal"]["bucketName"], "photos");
+        assert!(matches!(manager.load_vault("wrong"), Err(ConfigError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_upgrade_all_profiles_rolls_back_when_a_profile_does_not_round_trip() {
+        let manager = ConfigManager::from_dir(unique_test_dir("vault-upgrade-rollback")).unwrap();
+        let password = "upgrade-password";
+        manager
+            .upsert_vault_profile(password, "work", serde_json::json!({"bucketName": "work-bucket"}))
+            .unwrap();
+        manager
+            .upsert_vault_profile(password, "personal", serde_json::json!({"bucketName": "photos"}))
+            .unwrap();
+        let original = fs::read_to_string(manager.get_vault_path()).unwrap();
+
+        // A faulty upgrade step that loses one profile
+        let results = manager
+            .upgrade_all_profiles_with(
+                password,
+                CipherAlgorithm::ChaCha20Poly1305,
+                KdfAlgorithm::Argon2id,
+                |_| {
+                    manager.encrypt_envelope(
+                        r#"{"work": {"bucketName": "work-bucket"}}"#,
+                        password,
+                        CipherAlgorithm::ChaCha20Poly1305,
+                    )
+                },
+            )
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| !result.upgraded));
+        let personal = results.iter().find(|result| result.name == "personal").unwrap();
+        assert!(personal.error.is_some());
+        assert!(results.iter().find(|result| result.name == "work").unwrap().error.is_none());
+        assert_eq!(fs::read_to_string(manager.get_vault_path()).unwrap(), original);
+        assert_eq!(manager.list_vault_profiles(password).unwrap(), vec!["personal", "work"]);
+    }
+
+    #[test]
+    fn test_upgrade_all_profiles_to_argon2_chacha() {
+        let manager = ConfigManager::from_dir(unique_test_dir("vault-upgrade")).unwrap();
+        let password = "upgrade-password";
+        manager
+            .upsert_vault_profile(password, "work", serde_json::json!({"bucketName": "work-bucket"}))
+            .unwrap();
+        manager
+            .upsert_vault_profile(password, "personal", serde_json::json!({"bucketName": "photos"}))
+            .unwrap();
+
+        let results = manager
+            .upgrade_all_profiles(password, CipherAlgorithm::ChaCha20Poly1305, KdfAlgorithm::Argon2id)
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.upgraded && result.error.is_none()));
+
+        let envelope = manager.read_envelope(&manager.get_vault_path()).unwrap();
+        assert_eq!(envelope.algorithm, CHACHA_ALGORITHM);
+        assert_eq!(envelope.kdf.as_deref(), Some(ARGON2_KDF));
+
+        let profiles = manager.load_vault(password).unwrap();
+        assert_eq!(profiles["work"]["bucketName"], "work-bucket");
+        assert_eq!(profiles["personal"]["bucketName"], "photos");
+
+        // Later edits keep the upgraded parameters
+        manager
+            .upsert_vault_profile(password, "travel", serde_json::json!({"bucketName": "trips"}))
+            .unwrap();
+        let envelope = manager.read_envelope(&manager.get_vault_path()).unwrap();
+        assert_eq!(envelope.kdf.as_deref(), Some(ARGON2_KDF));
+        assert_eq!(manager.list_vault_profiles(password).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_config_into_vault() {
+        let manager = ConfigManager::from_dir(unique_test_dir("vault-migrate")).unwrap();
+        let password = "migrate-password";
+        let config = r#"{"configs": [{"id": "1", "region": "us-east-1"}], "activeConfigId": "1"}"#;
+        manager.save_config(config, password).unwrap();
+
+        manager.migrate_config_to_vault(password, "default").unwrap();
+
+        assert!(!manager.config_exists());
+        let profiles = manager.load_vault(password).unwrap();
+        assert_eq!(profiles["default"], serde_json::from_str::<serde_json::Value>(config).unwrap());
     }
 
-    impl MockConfigManager {
-        fn new() -> Result<Self, ConfigError> {
-            let config_dir = env::temp_dir().join("s3-upload-tool-test");
-            if config_dir.exists() {
-                fs::remove_dir_all(&config_dir)?;
-            }
-            fs::create_dir_all(&config_dir)?;
-            Ok(MockConfigManager { config_dir })
-        }
+    #[test]
+    fn test_preferences_roundtrip() {
+        let manager = ConfigManager::from_dir(unique_test_dir("preferences")).unwrap();
+        assert_eq!(manager.load_preferences().unwrap(), None);
 
-        fn get_config_path(&self) -> PathBuf {
-            self.config_dir.join("config.encrypted")
-        }
+        let preferences = r#"{"theme":"dark","window":{"width":1200,"height":800}}"#;
+        manager.save_preferences(preferences).unwrap();
 
-        fn derive_key(&self, password: &str, salt: &[u8]) -> Result<SecureKey, ConfigError> {
-            let secure_password = SecureString::new(password.to_string());
-            let mut key = [0u8; 32];
-            pbkdf2::pbkdf2::<Hmac<Sha256>>(
-                secure_password.as_bytes(),
-                salt,
-                PBKDF2_ITERATIONS,
-                &mut key,
-            );
-            Ok(SecureKey::new(key))
-        }
+        assert_eq!(manager.load_preferences().unwrap().as_deref(), Some(preferences));
+        // Preferences live beside the encrypted config, not inside it
+        assert!(!manager.config_exists());
+    }
 
-        fn generate_secure_salt(&self) -> [u8; 32] {
-            let mut salt = [0u8; 32];
-            OsRng.fill_bytes(&mut salt);
-            salt
-        }
+    #[test]
+    fn test_preferences_reject_invalid_json() {
+        let manager = ConfigManager::from_dir(unique_test_dir("preferences-invalid")).unwrap();
+        manager.save_preferences(r#"{"theme":"light"}"#).unwrap();
 
-        fn generate_secure_nonce(&self) -> [u8; 12] {
-            let mut nonce = [0u8; 12];
-            OsRng.fill_bytes(&mut nonce);
-            nonce
+        let result = manager.save_preferences("{not json");
+        assert!(matches!(result, Err(ConfigError::Serialization(_))));
+        assert_eq!(
+            manager.load_preferences().unwrap().as_deref(),
+            Some(r#"{"theme":"light"}"#)
+        );
+    }
+
+    const VALID_IMPORT: &str = r#"{
+        "version": "1.0",
+        "configs": [{"id": "1", "name": "Work", "region": "us-east-1", "bucketName": "work",
+                     "accessKeyId": "", "secretAccessKey": "", "endpoint": "https://s3.example.com"}],
+        "activeConfigId": "1"
+    }"#;
+
+    #[test]
+    fn test_redacted_export_drops_credentials() {
+        let dir = unique_test_dir("export-redacted");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let config = r#"{"configs": [{"id": "1", "name": "Work", "region": "eu-west-1",
+            "bucketName": "team-bucket", "accessKeyId": "AKIAEXAMPLE",
+            "secretAccessKey": "wJalrXUtnFEMI/K7MDENG"}], "activeConfigId": "1"}"#;
+        manager.save_config(config, "redact-password").unwrap();
+
+        let export_path = dir.join("shared.json");
+        manager
+            .export_config_redacted("redact-password", export_path.to_str().unwrap(), false)
+            .unwrap();
+
+        let exported = fs::read_to_string(&export_path).unwrap();
+        assert!(exported.contains("team-bucket"));
+        assert!(exported.contains("eu-west-1"));
+        assert!(!exported.contains("accessKeyId"));
+        assert!(!exported.contains("secretAccessKey"));
+        assert!(!exported.contains("AKIAEXAMPLE"));
+        assert!(validate_config_json(&exported).is_empty());
+    }
+
+    #[test]
+    fn test_zeroize_json_clears_every_string() {
+        let mut value = serde_json::json!({
+            "configs": [{"id": "1", "secretAccessKey": "wJalrXUtnFEMI", "port": 443}],
+            "name": "Work",
+        });
+        zeroize_json(&mut value);
+        assert_eq!(value, serde_json::json!({}));
+
+        let mut strings = serde_json::json!(["AKIAEXAMPLE", ["nested-secret"], null]);
+        zeroize_json(&mut strings);
+        assert_eq!(strings, serde_json::json!(["", [""], null]));
+    }
+
+    #[test]
+    fn test_clear_config_field_keeps_rest_of_profile() {
+        let manager = ConfigManager::from_dir(unique_test_dir("clear-field")).unwrap();
+        let password = "clear-password";
+        let config = r#"{"configs": [
+            {"id": "1", "name": "Work", "region": "eu-west-1", "bucketName": "team-bucket",
+             "accessKeyId": "AKIAEXAMPLE", "secretAccessKey": "wJalrXUtnFEMI/K7MDENG"},
+            {"id": "2", "name": "Home", "region": "us-east-1", "bucketName": "photos",
+             "accessKeyId": "AKIAHOME", "secretAccessKey": "homeSecret"}],
+            "activeConfigId": "1"}"#;
+        manager.save_config(config, password).unwrap();
+
+        manager.clear_config_field(password, "1", "secretAccessKey").unwrap();
+
+        let reloaded: serde_json::Value = serde_json::from_str(&manager.load_config(password).unwrap()).unwrap();
+        let mut expected: serde_json::Value = serde_json::from_str(config).unwrap();
+        expected["configs"][0]["secretAccessKey"] = serde_json::json!("");
+        assert_eq!(reloaded, expected);
+        assert!(!manager.get_backup_path().exists());
+
+        match manager.clear_config_field(password, "1", "bucketName") {
+            Err(ConfigError::FieldNotClearable(field)) => assert_eq!(field, "bucketName"),
+            other => panic!("expected FieldNotClearable, got {:?}", other),
+        }
+        match manager.clear_config_field(password, "missing", "accessKeyId") {
+            Err(ConfigError::ConfigEntryNotFound(id)) => assert_eq!(id, "missing"),
+            other => panic!("expected ConfigEntryNotFound, got {:?}", other),
         }
+    }
 
-        fn secure_delete_file(&self, path: &PathBuf) -> Result<(), ConfigError> {
-            if path.exists() {
-                let file_size = fs::metadata(path)?.len() as usize;
-                if file_size > 0 {
-                    let mut random_data = vec![0u8; file_size];
-                    OsRng.fill_bytes(&mut random_data);
-                    fs::write(path, &random_data)?;
-                    
-                    let zero_data = vec![0u8; file_size];
-                    fs::write(path, &zero_data)?;
-                }
-                
-                fs::remove_file(path)?;
+    #[test]
+    fn test_import_valid_config() {
+        let dir = unique_test_dir("import-valid");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let import_path = dir.join("import.json");
+        fs::write(&import_path, VALID_IMPORT).unwrap();
+
+        let imported = manager
+            .import_config(import_path.to_str().unwrap(), false)
+            .unwrap();
+        assert_eq!(imported, VALID_IMPORT);
+    }
+
+    #[test]
+    fn test_streaming_validation_of_large_configs() {
+        let entry = r#"{"id": "1", "name": "Big", "region": "us-east-1", "bucketName": "b"}"#;
+        let entries = vec![entry; 40_000].join(",");
+        let large = format!(r#"{{"configs": [{}], "activeConfigId": "1"}}"#, entries);
+        assert!(large.len() > STREAMING_VALIDATION_THRESHOLD);
+        assert!(validate_config_json(&large).is_empty());
+
+        // Broken near the start: the rest of the document is never looked at
+        let malformed = format!(r#"{{"configs": [{{"id": }}, {}]}}"#, entries);
+        let problems = validate_config_json(&malformed);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("Not valid JSON"));
+        assert!(problems[0].contains("line 1 column 21"));
+
+        // Streaming and full validation agree on what's wrong
+        let invalid = r#"{"activeConfigId": 7, "configs": [{"id": "1", "name": "Broken", "endpoint": 42}, 3]}"#;
+        let mut streamed = validate_config_reader(invalid.as_bytes());
+        let mut full = validate_config_json(invalid);
+        streamed.sort();
+        full.sort();
+        assert_eq!(streamed, full);
+        assert_eq!(
+            validate_config_reader(r#"{"configs": {}}"#.as_bytes()),
+            vec!["Missing \"configs\" array"]
+        );
+    }
+
+    #[test]
+    fn test_import_invalid_config_rejected_unless_skipped() {
+        let dir = unique_test_dir("import-invalid");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let import_path = dir.join("import.json");
+        let invalid = r#"{"configs": [{"id": "1", "name": "Broken", "endpoint": 42}]}"#;
+        fs::write(&import_path, invalid).unwrap();
+        let import_path = import_path.to_str().unwrap();
+
+        match manager.import_config(import_path, false) {
+            Err(ConfigError::InvalidConfig(problems)) => {
+                assert_eq!(
+                    problems,
+                    vec![
+                        "configs[0] is missing \"region\"",
+                        "configs[0] is missing \"bucketName\"",
+                        "configs[0].endpoint must be an http(s) URL",
+                    ]
+                );
             }
-            Ok(())
+            other => panic!("expected InvalidConfig, got {:?}", other),
         }
 
-        fn save_config(&self, config_json: &str, password: &str) -> Result<(), ConfigError> {
-            let secure_password = SecureString::new(password.to_string());
-            let secure_config = SecureString::new(config_json.to_string());
-            
-            let salt = self.generate_secure_salt();
-            let nonce_bytes = self.generate_secure_nonce();
+        assert_eq!(manager.import_config(import_path, true).unwrap(), invalid);
+    }
 
-            let secure_key = self.derive_key(password, &salt)?;
+    #[test]
+    fn test_backup_config_to_timestamped_file() {
+        let manager = ConfigManager::from_dir(unique_test_dir("backup-source")).unwrap();
+        let backup_dir = unique_test_dir("backup-target");
+        let password = "backup-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
 
-            let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
-                .map_err(|e| ConfigError::Encryption(format!("Cipher creation error: {}", e)))?;
+        let backup_path = manager.backup_config_to(&backup_dir).unwrap();
 
-            let nonce = Nonce::from_slice(&nonce_bytes);
+        let file_name = backup_path.file_name().unwrap().to_str().unwrap();
+        let stamp = file_name
+            .strip_prefix("config-")
+            .and_then(|rest| rest.strip_suffix(".encrypted"))
+            .unwrap();
+        assert_eq!(stamp.len(), "YYYYMMDD-HHMMSS".len());
+        assert!(stamp.chars().all(|c| c.is_ascii_digit() || c == '-'));
+        assert_eq!(fs::read(&backup_path).unwrap(), fs::read(manager.get_config_path()).unwrap());
 
-            let encrypted_data = cipher
-                .encrypt(nonce, secure_config.as_bytes())
-                .map_err(|e| ConfigError::Encryption(format!("Encryption failed: {}", e)))?;
+        // Backups within the same second get their own names
+        let second = backup_dir.join(format!("config-{}-2.encrypted", stamp));
+        fs::write(&second, b"older backup").unwrap();
+        let again = manager.backup_config_to(&backup_dir).unwrap();
+        assert_ne!(again, backup_path);
+        assert_ne!(again, second);
+        assert_eq!(fs::read(&second).unwrap(), b"older backup");
+        assert_eq!(fs::read(&again).unwrap(), fs::read(manager.get_config_path()).unwrap());
 
-            let encrypted_config = EncryptedConfig {
-                data: general_purpose::STANDARD.encode(&encrypted_data),
-                salt: general_purpose::STANDARD.encode(&salt),
-                nonce: general_purpose::STANDARD.encode(&nonce_bytes),
-                version: ENCRYPTION_VERSION.to_string(),
-                algorithm: ENCRYPTION_ALGORITHM.to_string(),
-                iterations: PBKDF2_ITERATIONS,
-            };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&backup_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
 
-            let config_path = self.get_config_path();
-            let json_data = serde_json::to_string_pretty(&encrypted_config)?;
-            fs::write(&config_path, json_data)?;
+    #[test]
+    fn test_restore_config_from_backup() {
+        let manager = ConfigManager::from_dir(unique_test_dir("restore")).unwrap();
+        let backup_dir = unique_test_dir("restore-backups");
+        let password = "restore-password";
+        let original = r#"{"configs": [{"id": "1"}]}"#;
+        manager.save_config(original, password).unwrap();
+        let backup_path = manager.backup_config_to(&backup_dir).unwrap();
 
-            Ok(())
-        }
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
 
-        fn load_config(&self, password: &str) -> Result<String, ConfigError> {
-            let config_path = self.get_config_path();
+        assert!(matches!(
+            manager.restore_config_from(&backup_path, "wrong-password"),
+            Err(ConfigError::InvalidPassword)
+        ));
+        assert_eq!(manager.load_config(password).unwrap(), r#"{"configs": []}"#);
 
-            if !config_path.exists() {
-                return Err(ConfigError::ConfigNotFound);
-            }
+        manager.restore_config_from(&backup_path, password).unwrap();
+        assert_eq!(manager.load_config(password).unwrap(), original);
+    }
 
-            let file_content = fs::read_to_string(config_path)?;
-            let encrypted_config: EncryptedConfig = serde_json::from_str(&file_content)?;
+    #[test]
+    fn test_verify_all_backups_reports_each_backup() {
+        let manager = ConfigManager::from_dir(unique_test_dir("verify-backups")).unwrap();
+        let password = "verify-backups-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+        manager.save_config(r#"{"configs": [{"id": "1"}]}"#, password).unwrap();
+        assert!(manager.verify_all_backups(password).unwrap()[0].decryptable);
 
-            // For test compatibility, handle both old and new format
-            let version = encrypted_config.version.as_deref().unwrap_or("1.0");
-            let algorithm = encrypted_config.algorithm.as_deref().unwrap_or("AES-256-GCM");
+        let backup = manager.get_backup_path();
+        let rotated = |n: u32| PathBuf::from(format!("{}.{}", backup.display(), n));
+        fs::copy(&backup, rotated(1)).unwrap();
+        fs::write(rotated(2), "{ not json").unwrap();
+        let mut envelope = manager.read_envelope(&backup).unwrap();
+        envelope.data = general_purpose::STANDARD.encode(b"corrupted ciphertext");
+        fs::write(rotated(3), serde_json::to_string(&envelope).unwrap()).unwrap();
 
-            let encrypted_data = general_purpose::STANDARD
-                .decode(&encrypted_config.data)
-                .map_err(|e| ConfigError::Decryption(format!("Base64 decode error: {}", e)))?;
+        let statuses = manager.verify_all_backups(password).unwrap();
+        let summary: Vec<_> = statuses
+            .iter()
+            .map(|s| (s.index, s.parseable, s.decryptable, s.version.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, true, true, Some(ENCRYPTION_VERSION)),
+                (1, true, true, Some(ENCRYPTION_VERSION)),
+                (2, false, false, None),
+                (3, true, false, Some(ENCRYPTION_VERSION)),
+            ]
+        );
 
-            let salt = general_purpose::STANDARD
-                .decode(&encrypted_config.salt)
-                .map_err(|e| ConfigError::Decryption(format!("Salt decode error: {}", e)))?;
+        let wrong_password = manager.verify_all_backups("wrong-password").unwrap();
+        assert!(wrong_password.iter().all(|s| !s.decryptable));
+    }
 
-            let nonce_bytes = general_purpose::STANDARD
-                .decode(&encrypted_config.nonce)
-                .map_err(|e| ConfigError::Decryption(format!("Nonce decode error: {}", e)))?;
+    #[test]
+    fn test_verify_all_backups_counts_wrong_passwords() {
+        let throttle = PasswordThrottle {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        };
+        let manager = ConfigManager::from_dir(unique_test_dir("verify-backups-throttle"))
+            .unwrap()
+            .with_password_throttle(throttle);
+        let password = "verify-throttle-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+        manager.save_config(r#"{"configs": [{"id": "1"}]}"#, password).unwrap();
 
-            let secure_key = self.derive_key(password, &salt)?;
+        manager.verify_all_backups("wrong-password").unwrap();
+        assert!(manager.password_retry_delay() > Duration::ZERO);
 
-            let cipher = Aes256Gcm::new_from_slice(secure_key.as_bytes())
-                .map_err(|e| ConfigError::Decryption(format!("Cipher creation error: {}", e)))?;
+        // The backoff applies to the next guess, whichever command makes it
+        let started = Instant::now();
+        assert!(matches!(manager.load_config("wrong-password"), Err(ConfigError::InvalidPassword)));
+        assert!(started.elapsed() >= Duration::from_millis(50));
 
-            let nonce = Nonce::from_slice(&nonce_bytes);
+        assert!(manager.verify_all_backups(password).unwrap()[0].decryptable);
+        assert_eq!(manager.password_retry_delay(), Duration::ZERO);
+    }
 
-            let decrypted_data = cipher
-                .decrypt(nonce, encrypted_data.as_ref())
-                .map_err(|_| ConfigError::InvalidPassword)?;
+    #[test]
+    fn test_profile_labels_readable_without_password() {
+        let manager = ConfigManager::from_dir(unique_test_dir("profile-labels")).unwrap();
+        let password = "labels-password";
+        let secret = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+        manager
+            .upsert_vault_profile(
+                password,
+                "work",
+                serde_json::json!({"name": "Work bucket", "secretAccessKey": secret}),
+            )
+            .unwrap();
+        manager
+            .upsert_vault_profile(password, "scratch", serde_json::json!({"region": "eu-west-1"}))
+            .unwrap();
 
-            let secure_config = SecureString::from_bytes(decrypted_data);
-            let config_json = secure_config.as_str()
-                .map_err(|e| ConfigError::Decryption(format!("UTF-8 conversion error: {}", e)))?
-                .to_string();
+        let labels = manager.get_profile_labels().unwrap();
+        let summary: Vec<(&str, Option<&str>)> = labels
+            .iter()
+            .map(|info| (info.name.as_str(), info.label.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![("scratch", None), ("work", Some("Work bucket"))]);
+        assert!(labels.iter().all(|info| info.last_modified > 0));
 
-            Ok(config_json)
-        }
+        let sidecar = fs::read_to_string(manager.get_vault_meta_path()).unwrap();
+        assert!(!sidecar.contains(secret));
+        assert!(!sidecar.contains("eu-west-1"));
+    }
 
-        fn config_exists(&self) -> bool {
-            self.get_config_path().exists()
-        }
+    #[test]
+    fn test_password_hints_round_trip_without_password() {
+        let dir = unique_test_dir("password-hints");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let password = "hint-password";
+        manager
+            .upsert_vault_profile(password, "work", serde_json::json!({"label": "Work"}))
+            .unwrap();
 
-        fn delete_config(&self) -> Result<(), ConfigError> {
-            let config_path = self.get_config_path();
-            self.secure_delete_file(&config_path)?;
-            Ok(())
-        }
+        assert_eq!(manager.get_password_hint("work").unwrap(), None);
+        manager
+            .set_password_hint("work", Some("  the usual, with the year  "))
+            .unwrap();
+
+        // A fresh manager reads the hint from the sidecar, no password involved
+        let reopened = ConfigManager::from_dir(dir).unwrap();
+        assert_eq!(
+            reopened.get_password_hint("work").unwrap().as_deref(),
+            Some("the usual, with the year")
+        );
+
+        // Hints survive vault saves and renames, and can be cleared
+        manager
+            .upsert_vault_profile(password, "work", serde_json::json!({"label": "Work 2"}))
+            .unwrap();
+        manager.rename_vault_profile(password, "work", "office").unwrap();
+        assert_eq!(
+            manager.get_password_hint("office").unwrap().as_deref(),
+            Some("the usual, with the year")
+        );
+        manager.set_password_hint("office", None).unwrap();
+        assert_eq!(manager.get_password_hint("office").unwrap(), None);
+
+        let too_long = "x".repeat(MAX_PASSWORD_HINT_LEN + 1);
+        assert!(matches!(
+            manager.set_password_hint("office", Some(&too_long)),
+            Err(ConfigError::InvalidHint(_))
+        ));
+        assert!(matches!(
+            manager.set_password_hint("missing", Some("hint")),
+            Err(ConfigError::ConfigNotFound)
+        ));
     }
 
     #[test]
-    fn test_config_save_and_load() {
-        let manager = MockConfigManager::new().unwrap();
-        let test_config = r#"{"test": "data", "number": 42}"#;
-        let password = "test-password";
+    fn test_profile_labels_follow_rename_and_remove() {
+        let manager = ConfigManager::from_dir(unique_test_dir("profile-labels-sync")).unwrap();
+        let password = "labels-password";
+        manager
+            .upsert_vault_profile(password, "a", serde_json::json!({"label": "Alpha"}))
+            .unwrap();
+        manager
+            .upsert_vault_profile(password, "b", serde_json::json!({"label": "Beta"}))
+            .unwrap();
 
-        // Test saving config
-        assert!(manager.save_config(test_config, password).is_ok());
-        assert!(manager.config_exists());
+        manager.rename_vault_profile(password, "a", "archive").unwrap();
+        assert!(matches!(
+            manager.rename_vault_profile(password, "b", "archive"),
+            Err(ConfigError::ProfileExists(_))
+        ));
+        manager.remove_vault_profile(password, "b").unwrap();
 
-        // Test loading config
-        let loaded_config = manager.load_config(password).unwrap();
-        assert_eq!(loaded_config, test_config);
+        let labels = manager.get_profile_labels().unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].name, "archive");
+        assert_eq!(labels[0].label.as_deref(), Some("Alpha"));
+        assert_eq!(
+            manager.list_vault_profiles(password).unwrap(),
+            vec!["archive".to_string()]
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_config_wrong_password() {
-        let manager = MockConfigManager::new().unwrap();
-        let test_config = r#"{"test": "data"}"#;
-        let password = "correct-password";
-        let wrong_password = "wrong-password";
+    fn test_harden_permissions_tightens_world_readable_config() {
+        use std::os::unix::fs::PermissionsExt;
 
-        // Save with correct password
-        manager.save_config(test_config, password).unwrap();
+        let manager = ConfigManager::from_dir(unique_test_dir("harden")).unwrap();
+        let password = "harden-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+        manager.save_config(r#"{"configs": [{"id": "1"}]}"#, password).unwrap();
 
-        // Try to load with wrong password
-        let result = manager.load_config(wrong_password);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ConfigError::InvalidPassword));
+        let config_path = manager.get_config_path();
+        fs::set_permissions(&config_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let changed = manager.harden_permissions().unwrap();
+
+        assert_eq!(changed, vec![config_path.clone()]);
+        let mode = fs::metadata(&config_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(manager.harden_permissions().unwrap().is_empty());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_config_not_found() {
-        let manager = MockConfigManager::new().unwrap();
-        let password = "test-password";
+    fn test_import_warns_about_world_readable_file() {
+        use std::os::unix::fs::PermissionsExt;
 
-        // Try to load non-existent config
-        let result = manager.load_config(password);
-        assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), ConfigError::ConfigNotFound));
+        let dir = unique_test_dir("import-permissions");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let import_path = dir.join("import.json");
+        fs::write(&import_path, VALID_IMPORT).unwrap();
+        let import_str = import_path.to_str().unwrap();
+
+        fs::set_permissions(&import_path, fs::Permissions::from_mode(0o644)).unwrap();
+        let imported = manager.import_config_checked(import_str, false).unwrap();
+        assert_eq!(imported.config_json, VALID_IMPORT);
+        assert_eq!(imported.warnings.len(), 1);
+        assert!(imported.warnings[0].contains("readable by other users"));
+
+        fs::set_permissions(&import_path, fs::Permissions::from_mode(0o600)).unwrap();
+        let imported = manager.import_config_checked(import_str, false).unwrap();
+        assert!(imported.warnings.is_empty());
     }
 
     #[test]
-    fn test_config_delete() {
-        let manager = MockConfigManager::new().unwrap();
-        let test_config = r#"{"test": "data"}"#;
-        let password = "test-password";
+    fn test_import_config_strips_bom() {
+        let dir = unique_test_dir("import-bom");
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let import_path = dir.join("import.json");
+        fs::write(&import_path, format!("\u{feff}{}", VALID_IMPORT)).unwrap();
 
-        // Save config
-        manager.save_config(test_config, password).unwrap();
-        assert!(manager.config_exists());
+        let imported = manager
+            .import_config(import_path.to_str().unwrap(), false)
+            .unwrap();
 
-        // Delete config
-        manager.delete_config().unwrap();
-        assert!(!manager.config_exists());
+        assert_eq!(imported, VALID_IMPORT);
+        assert!(serde_json::from_str::<serde_json::Value>(&imported).is_ok());
     }
 
     #[test]
-    fn test_encryption_decryption_roundtrip() {
-        let manager = MockConfigManager::new().unwrap();
-        let test_configs = vec![
-            r#"{"simple": "test"}"#,
-            r#"{"complex": {"nested": {"data": [1, 2, 3]}, "unicode": "测试数据"}}"#,
-            r#"{"empty": {}}"#,
-            r#"{"special_chars": "!@#$%^&*()_+-=[]{}|;':\",./<>?"}"#,
-        ];
+    fn test_load_config_with_bom_prefixed_envelope() {
+        let manager = ConfigManager::from_dir(unique_test_dir("load-bom")).unwrap();
+        let password = "bom-password";
+        let config = r#"{"configs": []}"#;
+        manager.save_config(config, password).unwrap();
 
-        for test_config in test_configs {
-            let password = "test-password-123";
+        let config_path = manager.get_config_path();
+        let envelope = fs::read_to_string(&config_path).unwrap();
+        fs::write(&config_path, format!("\u{feff}{}", envelope)).unwrap();
 
-            // Save and load
-            manager.save_config(test_config, password).unwrap();
-            let loaded_config = manager.load_config(password).unwrap();
+        assert_eq!(manager.load_config(password).unwrap(), config);
+        assert_eq!(strip_bom(config), config);
+    }
 
-            assert_eq!(loaded_config, test_config);
+    #[test]
+    fn test_active_config_crypto_info() {
+        let manager = ConfigManager::from_dir(unique_test_dir("crypto-info")).unwrap();
+        assert!(matches!(
+            manager.active_config_crypto_info(),
+            Err(ConfigError::ConfigNotFound)
+        ));
 
-            // Clean up for next test
-            manager.delete_config().unwrap();
-        }
+        let options = SaveOptions {
+            algorithm: CipherAlgorithm::ChaCha20Poly1305,
+            ..Default::default()
+        };
+        manager
+            .save_config_with_options(r#"{"configs": []}"#, "info-password", &options)
+            .unwrap();
+        assert_eq!(
+            manager.active_config_crypto_info().unwrap(),
+            CryptoInfo {
+                algorithm: CHACHA_ALGORITHM.to_string(),
+                kdf: KEY_DERIVATION.to_string(),
+                iterations: PBKDF2_ITERATIONS,
+                salt_len: 32,
+                nonce_len: 12,
+                version: ENCRYPTION_VERSION.to_string(),
+            }
+        );
+
+        // A legacy envelope with a short salt and a low iteration count
+        let legacy = EncryptedConfig {
+            data: general_purpose::STANDARD.encode(b"ciphertext"),
+            salt: general_purpose::STANDARD.encode([1u8; 16]),
+            nonce: general_purpose::STANDARD.encode([2u8; 12]),
+            version: "1.0".to_string(),
+            algorithm: ENCRYPTION_ALGORITHM.to_string(),
+            iterations: 10_000,
+            kdf: None,
+            memory_kib: None,
+            parallelism: None,
+        };
+        fs::write(manager.get_config_path(), serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        let info = manager.active_config_crypto_info().unwrap();
+        assert_eq!(info.algorithm, ENCRYPTION_ALGORITHM);
+        assert_eq!(info.iterations, 10_000);
+        assert_eq!(info.salt_len, 16);
+        assert_eq!(info.nonce_len, 12);
+    }
+
+    #[test]
+    fn test_durable_save_is_loadable() {
+        let manager = ConfigManager::from_dir(unique_test_dir("durable-save")).unwrap();
+        let password = "durable-password";
+        let options = SaveOptions {
+            durable: true,
+            verify: true,
+            ..Default::default()
+        };
+
+        manager
+            .save_config_with_options(r#"{"configs": []}"#, password, &options)
+            .unwrap();
+        manager
+            .save_config_with_options(r#"{"configs": [{"id": "1"}]}"#, password, &options)
+            .unwrap();
+
+        assert_eq!(manager.load_config(password).unwrap(), r#"{"configs": [{"id": "1"}]}"#);
+        assert!(manager.get_backup_path().exists());
+        assert!(!ConfigManager::temp_path_for(&manager.get_config_path()).exists());
+    }
+
+    #[test]
+    fn test_save_writes_temp_file_with_configured_prefix() {
+        let _prefix = crate::download::test_temp_prefix::hold();
+        crate::download::set_temp_file_prefix(".custom-prefix-").unwrap();
+        let manager = ConfigManager::from_dir(unique_test_dir("temp-prefix-save")).unwrap();
+
+        // A directory in the config's place stops the save after the temp file is
+        // written, so it is left behind to inspect
+        fs::create_dir_all(manager.get_config_path()).unwrap();
+        assert!(manager.save_config(r#"{"configs": []}"#, "prefix-password").is_err());
+
+        let names: Vec<String> = fs::read_dir(&manager.config_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".tmp"))
+            .collect();
+        assert_eq!(names, vec![".custom-prefix-config.encrypted.tmp"]);
     }
-}
\ No newline at end of file
+}
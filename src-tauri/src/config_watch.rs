@@ -0,0 +1,115 @@
+use crate::config::{is_recent_self_write, ConfigManager};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Quiet period after an emitted event or one of our own saves
+pub const DEFAULT_CONFIG_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watch `config_path` and call `on_change` when something other than this process
+/// modifies it. Events within `debounce` of the last notification, or of one of our
+/// own atomic saves, are dropped. The watch stops when the returned watcher is dropped.
+pub fn watch_config_file<F>(
+    config_path: PathBuf,
+    debounce: Duration,
+    on_change: F,
+) -> notify::Result<RecommendedWatcher>
+where
+    F: Fn() + Send + 'static,
+{
+    // Atomic saves replace the file, so watch its directory rather than the inode
+    let directory = config_path
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let file_name = config_path.file_name().map(|name| name.to_os_string());
+    let mut last_notified: Option<Instant> = None;
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        if event.kind.is_access() {
+            return;
+        }
+        let touches_config = event
+            .paths
+            .iter()
+            .any(|path| path.file_name().map(|name| name.to_os_string()) == file_name);
+        if !touches_config || is_recent_self_write(&config_path, debounce) {
+            return;
+        }
+        if last_notified.is_some_and(|at| at.elapsed() < debounce) {
+            return;
+        }
+
+        last_notified = Some(Instant::now());
+        on_change();
+    })?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+fn active_watcher() -> &'static Mutex<Option<RecommendedWatcher>> {
+    static WATCHER: OnceLock<Mutex<Option<RecommendedWatcher>>> = OnceLock::new();
+    WATCHER.get_or_init(|| Mutex::new(None))
+}
+
+/// Start emitting `config-changed` when the config file is modified outside the app.
+/// Calling it again replaces the previous watch.
+#[tauri::command]
+pub async fn watch_config_changes(app_handle: AppHandle) -> Result<(), String> {
+    let config_manager = ConfigManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let config_path = config_manager.config_path();
+    let emitted_path = config_path.to_string_lossy().to_string();
+
+    let watcher = watch_config_file(config_path, DEFAULT_CONFIG_WATCH_DEBOUNCE, move || {
+        let _ = app_handle.emit("config-changed", emitted_path.clone());
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut active = active_watcher().lock().map_err(|e| e.to_string())?;
+    *active = Some(watcher);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    #[test]
+    fn test_external_write_fires_once_and_self_write_is_suppressed() {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("s3-upload-tool-config-watch-{}", timestamp));
+        let manager = ConfigManager::from_dir(dir.clone()).unwrap();
+        let password = "watch-password";
+        manager.save_config(r#"{"configs": []}"#, password).unwrap();
+
+        let debounce = Duration::from_millis(300);
+        // Upper bound for a notification to arrive, not a fixed wait
+        let delivery_timeout = Duration::from_secs(5);
+        let (events, received) = mpsc::channel();
+        let _watcher = watch_config_file(manager.config_path(), debounce, move || {
+            let _ = events.send(());
+        })
+        .unwrap();
+
+        // Our own save is only suppressed within the debounce window, so that is as
+        // long as a notification for it could show up
+        manager.save_config(r#"{"configs": [{"id": "1"}]}"#, password).unwrap();
+        assert_eq!(received.recv_timeout(debounce), Err(RecvTimeoutError::Timeout));
+
+        fs::write(manager.config_path(), b"{\"edited\": true}").unwrap();
+        assert_eq!(received.recv_timeout(delivery_timeout), Ok(()));
+        assert_eq!(received.recv_timeout(debounce), Err(RecvTimeoutError::Timeout));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
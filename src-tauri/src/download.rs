@@ -1,17 +1,103 @@
+use crate::config::{secure_remove_file_with_progress, SecureDeleteProgress};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::{Notify, Semaphore};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadProgress {
     pub task_id: String,
     pub downloaded_bytes: u64,
     pub total_bytes: u64,
     pub progress: f64,
     pub speed: f64,
+    /// Seconds remaining at the current speed, `None` while it can't be estimated
+    pub eta_secs: Option<u64>,
+}
+
+impl DownloadProgress {
+    pub fn new(task_id: String, downloaded_bytes: u64, total_bytes: u64, speed: f64) -> Self {
+        let progress = if total_bytes > 0 {
+            downloaded_bytes as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        DownloadProgress {
+            task_id,
+            downloaded_bytes,
+            total_bytes,
+            progress,
+            speed,
+            eta_secs: estimate_eta(downloaded_bytes, total_bytes, speed),
+        }
+    }
+}
+
+/// Seconds left to fetch `total_bytes` at `speed_bytes_per_sec`, rounded up.
+/// `None` when the total is unknown (0) or nothing is moving.
+pub fn estimate_eta(downloaded_bytes: u64, total_bytes: u64, speed_bytes_per_sec: f64) -> Option<u64> {
+    if total_bytes == 0 {
+        return None;
+    }
+    if downloaded_bytes >= total_bytes {
+        return Some(0);
+    }
+    if !speed_bytes_per_sec.is_finite() || speed_bytes_per_sec <= 0.0 {
+        return None;
+    }
+
+    let remaining = (total_bytes - downloaded_bytes) as f64;
+    Some((remaining / speed_bytes_per_sec).ceil() as u64)
+}
+
+/// Seconds a download of `total_bytes` should take at a previously measured speed,
+/// rounded to the nearest second but at least 1 for a non-empty file. `None` when
+/// the sample shows nothing moving.
+pub fn predict_duration(total_bytes: u64, sample_speed_bytes_per_sec: f64) -> Option<u64> {
+    if !sample_speed_bytes_per_sec.is_finite() || sample_speed_bytes_per_sec <= 0.0 {
+        return None;
+    }
+    if total_bytes == 0 {
+        return Some(0);
+    }
+
+    let seconds = (total_bytes as f64 / sample_speed_bytes_per_sec).round() as u64;
+    Some(seconds.max(1))
+}
+
+/// Progress of one byte range of a parallel download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartProgress {
+    pub downloaded_bytes: u64,
+    /// Length of the range, `None` until the server has reported it
+    pub total_bytes: Option<u64>,
+    pub speed: f64,
+}
+
+/// Combine the parts of a parallel download into one `DownloadProgress`. Bytes and
+/// speeds are summed; while any part's length is unknown the total is reported as
+/// unknown (0) rather than understating it.
+pub fn aggregate_progress(task_id: String, parts: &[PartProgress]) -> DownloadProgress {
+    let downloaded: u64 = parts.iter().map(|part| part.downloaded_bytes).sum();
+    let speed: f64 = parts
+        .iter()
+        .map(|part| part.speed)
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .sum();
+    let total = parts
+        .iter()
+        .map(|part| part.total_bytes)
+        .sum::<Option<u64>>()
+        .unwrap_or(0);
+    DownloadProgress::new(task_id, downloaded, total, speed)
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +120,55 @@ pub enum DownloadError {
     Cancelled,
     #[error("Resume data invalid")]
     InvalidResumeData,
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("File too large: {size} bytes exceeds the {limit} byte limit")]
+    FileTooLarge { size: u64, limit: u64 },
+    #[error("Invalid base64 data: {0}")]
+    InvalidBase64(String),
+    #[error("Size mismatch: expected {expected} bytes, got {actual}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+    #[error("Unsupported on this platform: {0}")]
+    Unsupported(String),
+    #[error("Invalid block size: {0}")]
+    InvalidBlockSize(u64),
+    #[error("File is still being written: {0}")]
+    WriteInProgress(String),
+}
+
+impl DownloadError {
+    /// Whether the same operation may succeed if retried: network failures and timeouts
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DownloadError::Http(_) => true,
+            DownloadError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Platform directories consulted when locating the downloads folder
+pub trait DirectoryProvider {
+    fn download_dir(&self) -> Result<PathBuf, String>;
+    fn home_dir(&self) -> Result<PathBuf, String>;
+}
+
+impl<R: tauri::Runtime> DirectoryProvider for AppHandle<R> {
+    fn download_dir(&self) -> Result<PathBuf, String> {
+        self.path().download_dir().map_err(|e| e.to_string())
+    }
+
+    fn home_dir(&self) -> Result<PathBuf, String> {
+        self.path().home_dir().map_err(|e| e.to_string())
+    }
 }
 
 pub struct DownloadManager {
@@ -42,12 +177,44 @@ pub struct DownloadManager {
 
 impl DownloadManager {
     pub fn new(app_handle: &AppHandle) -> Result<Self, DownloadError> {
-        let downloads_dir = app_handle
-            .path()
-            .download_dir()
-            .map_err(|e| DownloadError::Path(format!("Failed to get downloads directory: {}", e)))?;
+        Ok(Self::with_directories(app_handle))
+    }
+
+    /// Resolve the downloads directory, falling back to the home directory and then
+    /// the temp directory (e.g. headless Linux without XDG dirs) so downloads keep working.
+    pub fn with_directories(directories: &impl DirectoryProvider) -> Self {
+        let downloads_dir = match directories.download_dir() {
+            Ok(dir) => dir,
+            Err(download_err) => match directories.home_dir() {
+                Ok(home) => {
+                    log::warn!(
+                        "Downloads directory unavailable ({}), falling back to home directory",
+                        download_err
+                    );
+                    home
+                }
+                Err(home_err) => {
+                    log::warn!(
+                        "Downloads ({}) and home ({}) directories unavailable, falling back to temp directory",
+                        download_err,
+                        home_err
+                    );
+                    std::env::temp_dir()
+                }
+            },
+        };
 
-        Ok(DownloadManager { downloads_dir })
+        Self::from_dir(downloads_dir)
+    }
+
+    /// Create a manager rooted at an explicit downloads directory
+    pub fn from_dir(downloads_dir: PathBuf) -> Self {
+        DownloadManager { downloads_dir }
+    }
+
+    /// The directory downloads default to, after any fallback was applied
+    pub fn downloads_dir(&self) -> &Path {
+        &self.downloads_dir
     }
 
     pub fn validate_download_path(&self, path: &str) -> Result<PathBuf, DownloadError> {
@@ -122,442 +289,4581 @@ impl DownloadManager {
     }
 
     pub fn get_default_download_path(&self, filename: &str) -> PathBuf {
-        self.downloads_dir.join(filename)
+        self.downloads_dir.join(truncate_filename_preserving_extension(filename, MAX_FILENAME_BYTES))
     }
 
-    pub fn check_disk_space(&self, _path: &PathBuf, _required_bytes: u64) -> Result<bool, DownloadError> {
-        // This is a simplified check - in a real implementation, you'd want to
-        // check the actual available disk space on the target drive
-        // For now, we'll just return true
-        Ok(true)
+    pub fn check_disk_space(&self, path: &Path, required_bytes: u64) -> Result<bool, DownloadError> {
+        let available = available_disk_space(path)?;
+        Ok(available >= required_bytes)
     }
 
     pub fn generate_unique_filename(&self, base_path: &PathBuf) -> PathBuf {
-        if !base_path.exists() {
-            return base_path.clone();
-        }
+        self.generate_unique_filename_with(base_path, CASE_INSENSITIVE_FILESYSTEM)
+    }
+
+    /// Like `generate_unique_filename`, optionally treating names that differ only
+    /// in case as taken
+    pub fn generate_unique_filename_with(&self, base_path: &Path, case_insensitive: bool) -> PathBuf {
+        unique_filename(base_path, case_insensitive)
+    }
+}
 
-        let parent = base_path.parent().unwrap_or_else(|| std::path::Path::new("."));
-        let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-        let extension = base_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+/// `base_path`, or the first free `name (N).ext` next to it, optionally treating names
+/// that differ only in case as taken
+fn unique_filename(base_path: &Path, case_insensitive: bool) -> PathBuf {
+    let is_taken = |path: &Path| {
+        path.exists() || (case_insensitive && collides_case_insensitive(path).unwrap_or(false))
+    };
 
-        for i in 1..1000 {
-            let new_filename = if extension.is_empty() {
-                format!("{} ({})", stem, i)
-            } else {
-                format!("{} ({}).{}", stem, i, extension)
-            };
-            
-            let new_path = parent.join(new_filename);
-            if !new_path.exists() {
-                return new_path;
-            }
-        }
+    if !is_taken(base_path) {
+        return base_path.to_path_buf();
+    }
 
-        // Fallback with timestamp
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
+    let parent = base_path.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let extension = base_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+    for i in 1..1000 {
         let new_filename = if extension.is_empty() {
-            format!("{}-{}", stem, timestamp)
+            format!("{} ({})", stem, i)
         } else {
-            format!("{}-{}.{}", stem, timestamp, extension)
+            format!("{} ({}).{}", stem, i, extension)
         };
         
-        parent.join(new_filename)
+        let new_path = parent.join(new_filename);
+        if !is_taken(&new_path) {
+            return new_path;
+        }
     }
-}
 
-// Tauri commands
-#[tauri::command]
-pub async fn select_download_path(
-    app_handle: AppHandle,
-    default_filename: Option<String>,
-) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
+    // Fallback with timestamp
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    
+    let new_filename = if extension.is_empty() {
+        format!("{}-{}", stem, timestamp)
+    } else {
+        format!("{}-{}.{}", stem, timestamp, extension)
+    };
+    
+    parent.join(new_filename)
+}
 
-    let mut dialog = app_handle.dialog().file();
+/// Default filesystems on these platforms treat `file.txt` and `File.txt` as one name
+const CASE_INSENSITIVE_FILESYSTEM: bool = cfg!(any(target_os = "macos", target_os = "windows"));
 
-    if let Some(filename) = default_filename {
-        dialog = dialog.set_file_name(&filename);
+/// Whether the parent directory holds an entry whose name matches `path`'s file name
+/// ignoring case
+pub fn collides_case_insensitive(path: &Path) -> Result<bool, DownloadError> {
+    let Some(file_name) = path.file_name() else {
+        return Ok(false);
+    };
+    let wanted = file_name.to_string_lossy().to_lowercase();
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if !parent.exists() {
+        return Ok(false);
     }
 
-    use std::sync::{Arc, Mutex};
-    
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = Arc::clone(&result);
-    
-    dialog.save_file(move |path| {
-        *result_clone.lock().unwrap() = path;
-    });
-    
-    // In a real implementation, you'd want to use async/await properly
-    // For now, we'll return None as this is just for testing
-    Ok(None)
+    for entry in fs::read_dir(parent)? {
+        if entry?.file_name().to_string_lossy().to_lowercase() == wanted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
-#[tauri::command]
-pub async fn select_download_directory(app_handle: AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
+/// Longest file name, in bytes, accepted by common filesystems (ext4, APFS, NTFS in UTF-16 units)
+pub const MAX_FILENAME_BYTES: usize = 255;
 
-    use std::sync::{Arc, Mutex};
-    
-    let result = Arc::new(Mutex::new(None));
-    let result_clone = Arc::clone(&result);
-    
-    app_handle.dialog().file().pick_folder(move |path| {
-        *result_clone.lock().unwrap() = path;
-    });
-    
-    // In a real implementation, you'd want to use async/await properly
-    // For now, we'll return None as this is just for testing
-    Ok(None)
+/// Hex digits of the original name's hash appended to a truncated stem
+const TRUNCATED_NAME_HASH_LEN: usize = 8;
+
+/// Largest prefix of `text` that fits in `max_bytes` without splitting a character
+fn truncate_at_char_boundary(text: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
 }
 
-#[tauri::command]
-pub async fn validate_download_path(
-    app_handle: AppHandle,
-    path: String,
-) -> Result<bool, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    match download_manager.validate_download_path(&path) {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
+/// Shorten `name` to at most `max_bytes` bytes of UTF-8. The stem is cut and followed by
+/// `~` and a short hash of the full name, so long keys sharing a prefix stay distinct;
+/// the extension is kept whenever it leaves room for the hash.
+pub fn truncate_filename_preserving_extension(name: &str, max_bytes: usize) -> String {
+    use sha2::Digest;
+
+    if name.len() <= max_bytes {
+        return name.to_string();
     }
+
+    let digest = format!("{:x}", sha2::Sha256::digest(name.as_bytes()));
+    let suffix = format!("~{}", &digest[..TRUNCATED_NAME_HASH_LEN]);
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    let (stem, extension) = if suffix.len() + extension.len() <= max_bytes {
+        (stem, extension)
+    } else {
+        (name, "")
+    };
+
+    let Some(stem_budget) = max_bytes.checked_sub(suffix.len() + extension.len()) else {
+        return truncate_at_char_boundary(&digest, max_bytes).to_string();
+    };
+    format!("{}{}{}", truncate_at_char_boundary(stem, stem_budget), suffix, extension)
 }
 
-#[tauri::command]
-pub async fn check_file_exists(
-    app_handle: AppHandle,
-    path: String,
-) -> Result<bool, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    let path_buf = PathBuf::from(path);
-    Ok(download_manager.check_file_exists(&path_buf))
+/// Walk up from `path` to the nearest ancestor that exists on disk, so space
+/// queries work for download targets that haven't been created yet.
+fn nearest_existing_ancestor(path: &Path) -> Result<&Path, DownloadError> {
+    path.ancestors()
+        .find(|candidate| candidate.exists())
+        .ok_or_else(|| DownloadError::InvalidPath(format!(
+            "No existing parent for path: {}",
+            path.display()
+        )))
 }
 
-#[tauri::command]
-pub async fn get_file_size(
-    app_handle: AppHandle,
-    path: String,
-) -> Result<u64, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    let path_buf = PathBuf::from(path);
-    download_manager.get_file_size(&path_buf).map_err(|e| e.to_string())
+/// Free space available to the current user on the filesystem holding `path`.
+pub fn available_disk_space(path: &Path) -> Result<u64, DownloadError> {
+    let existing = nearest_existing_ancestor(path)?;
+    Ok(fs2::available_space(existing)?)
 }
 
-#[tauri::command]
-pub async fn get_default_download_path(
-    app_handle: AppHandle,
-    filename: String,
-) -> Result<String, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    let path = download_manager.get_default_download_path(&filename);
-    Ok(path.to_string_lossy().to_string())
+/// `statvfs` of the filesystem holding `path`
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Result<libc::statvfs, DownloadError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = nearest_existing_ancestor(path)?;
+    let c_path = std::ffi::CString::new(existing.as_os_str().as_bytes())
+        .map_err(|_| DownloadError::InvalidPath(existing.display().to_string()))?;
+
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: c_path is NUL-terminated and stats is only read once statvfs has filled it
+    if unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(unsafe { stats.assume_init() })
 }
 
-#[tauri::command]
-pub async fn generate_unique_filename(
-    app_handle: AppHandle,
-    base_path: String,
-) -> Result<String, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    let base_path_buf = PathBuf::from(base_path);
-    let unique_path = download_manager.generate_unique_filename(&base_path_buf);
-    Ok(unique_path.to_string_lossy().to_string())
+/// Allocation unit of the filesystem holding `path`.
+pub fn filesystem_block_size(path: &Path) -> Result<u64, DownloadError> {
+    let existing = nearest_existing_ancestor(path)?;
+    Ok(fs2::allocation_granularity(existing)?)
 }
 
-#[tauri::command]
-pub async fn create_directory(
-    _app_handle: AppHandle,
-    path: String,
-) -> Result<(), String> {
-    let path_buf = PathBuf::from(path);
-    fs::create_dir_all(path_buf).map_err(|e| e.to_string())
+/// Preferred I/O size of the filesystem holding `path`, for sizing buffers:
+/// `statvfs`'s `f_bsize` on Unix and the cluster size elsewhere. Network filesystems
+/// can report far more than the allocation unit here.
+#[cfg(unix)]
+pub fn preferred_io_block_size(path: &Path) -> Result<u64, DownloadError> {
+    // f_bsize's width differs between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let block_size = statvfs(path)?.f_bsize as u64;
+    Ok(block_size)
 }
 
-#[tauri::command]
-pub async fn check_disk_space(
-    app_handle: AppHandle,
-    path: String,
-    required_bytes: u64,
-) -> Result<bool, String> {
-    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
-    let path_buf = PathBuf::from(path);
-    download_manager
-        .check_disk_space(&path_buf, required_bytes)
-        .map_err(|e| e.to_string())
+#[cfg(not(unix))]
+pub fn preferred_io_block_size(path: &Path) -> Result<u64, DownloadError> {
+    filesystem_block_size(path)
 }
 
-#[tauri::command]
-pub async fn write_file_chunk(
-    _app_handle: AppHandle,
-    path: String,
-    data: Vec<u8>,
-    append: bool,
-) -> Result<(), String> {
-    use tokio::fs::OpenOptions;
-    use tokio::io::AsyncWriteExt;
+/// Inodes available to the current user on the filesystem holding `path`, i.e. how
+/// many more files can be created regardless of free bytes. Filesystems without a
+/// fixed inode table (btrfs, for one) report zero total inodes and give `u64::MAX`.
+#[cfg(unix)]
+pub fn available_inodes(path: &Path) -> Result<u64, DownloadError> {
+    let stats = statvfs(path)?;
+    if stats.f_files == 0 {
+        return Ok(u64::MAX);
+    }
+    // fsfilcnt_t's width differs between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = stats.f_favail as u64;
+    Ok(available)
+}
 
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(append)
-        .truncate(!append)
-        .open(&path)
-        .await
-        .map_err(|e| format!("Failed to open file: {}", e))?;
+#[cfg(not(unix))]
+pub fn available_inodes(_path: &Path) -> Result<u64, DownloadError> {
+    Err(DownloadError::Unsupported(
+        "Inode counts are only available on Unix filesystems".to_string(),
+    ))
+}
 
-    file.write_all(&data)
-        .await
-        .map_err(|e| format!("Failed to write data: {}", e))?;
+/// Largest file FAT32 can hold: 4 GiB minus one byte
+pub const FAT32_MAX_FILE_SIZE: u64 = u32::MAX as u64;
 
-    file.flush()
-        .await
-        .map_err(|e| format!("Failed to flush file: {}", e))?;
+/// Largest single file a filesystem of type `fs_type` can store, `None` when it
+/// has no limit a download could plausibly hit
+pub fn max_file_size_for_filesystem(fs_type: &str) -> Option<u64> {
+    match fs_type.to_ascii_lowercase().as_str() {
+        "msdos" | "vfat" | "fat" | "fat32" => Some(FAT32_MAX_FILE_SIZE),
+        _ => None,
+    }
+}
 
-    Ok(())
+/// Whether a file of `required_bytes` can be created at `path`, given the per-file
+/// limit of the filesystem holding it. Unidentified filesystems are assumed to fit.
+pub fn fits_filesystem_max_file_size(path: &Path, required_bytes: u64) -> Result<bool, DownloadError> {
+    fits_filesystem_max_file_size_with(path, required_bytes, |path| {
+        Ok(filesystem_type(nearest_existing_ancestor(path)?))
+    })
 }
 
-#[tauri::command]
-pub async fn read_file_chunk(
-    _app_handle: AppHandle,
-    path: String,
-    offset: u64,
-    length: usize,
-) -> Result<Vec<u8>, String> {
-    use tokio::fs::File;
-    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+fn fits_filesystem_max_file_size_with<F>(path: &Path, required_bytes: u64, detect: F) -> Result<bool, DownloadError>
+where
+    F: FnOnce(&Path) -> Result<Option<String>, DownloadError>,
+{
+    let limit = detect(path)?.as_deref().and_then(max_file_size_for_filesystem);
+    Ok(limit.map_or(true, |limit| required_bytes <= limit))
+}
 
-    let mut file = File::open(&path)
-        .await
-        .map_err(|e| format!("Failed to open file: {}", e))?;
+fn round_up_to_block(size: u64, block_size: u64) -> u64 {
+    if block_size == 0 || size % block_size == 0 {
+        return size;
+    }
+    size.saturating_add(block_size - size % block_size)
+}
 
-    file.seek(SeekFrom::Start(offset))
-        .await
-        .map_err(|e| format!("Failed to seek file: {}", e))?;
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchSpaceReport {
+    pub required_total: u64,
+    pub available: u64,
+    pub fits: bool,
+    pub shortfall: u64,
+}
 
-    let mut buffer = vec![0u8; length];
-    let bytes_read = file.read(&mut buffer)
-        .await
-        .map_err(|e| format!("Failed to read file: {}", e))?;
+impl BatchSpaceReport {
+    /// Build a report, rounding each size up to `block_size` to approximate on-disk usage
+    pub fn new(sizes: &[u64], block_size: u64, available: u64) -> Self {
+        let required_total = sizes
+            .iter()
+            .map(|size| round_up_to_block(*size, block_size))
+            .fold(0u64, |total, size| total.saturating_add(size));
 
-    buffer.truncate(bytes_read);
-    Ok(buffer)
+        BatchSpaceReport {
+            required_total,
+            available,
+            fits: required_total <= available,
+            shortfall: required_total.saturating_sub(available),
+        }
+    }
 }
 
-#[tauri::command]
-pub async fn calculate_file_checksum(
-    _app_handle: AppHandle,
-    path: String,
-) -> Result<String, String> {
-    use sha2::{Sha256, Digest};
-    use tokio::fs::File;
-    use tokio::io::AsyncReadExt;
+/// Identifier of the filesystem (device on Unix, volume serial on Windows) holding `path`
+fn filesystem_id(path: &Path) -> Result<u64, DownloadError> {
+    let existing = nearest_existing_ancestor(path)?;
 
-    let mut file = File::open(&path)
-        .await
-        .map_err(|e| format!("Failed to open file: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(existing)?.dev())
+    }
 
-    let mut hasher = Sha256::new();
-    let mut buffer = vec![0u8; 8192]; // 8KB buffer
+    #[cfg(windows)]
+    {
+        let handle = winapi_util::Handle::from_path_any(existing)?;
+        let info = winapi_util::file::information(&handle)?;
+        Ok(info.volume_serial_number())
+    }
 
-    loop {
-        let bytes_read = file.read(&mut buffer)
-            .await
-            .map_err(|e| format!("Failed to read file: {}", e))?;
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = existing;
+        Err(DownloadError::Path(
+            "Filesystem identification is not supported on this platform".to_string(),
+        ))
+    }
+}
 
-        if bytes_read == 0 {
-            break;
+/// Whether two paths live on the same filesystem, i.e. whether `fs::rename`
+/// between them is atomic. Paths that don't exist yet use their nearest existing parent.
+pub fn is_same_filesystem(path_a: &Path, path_b: &Path) -> Result<bool, DownloadError> {
+    Ok(filesystem_id(path_a)? == filesystem_id(path_b)?)
+}
+
+/// Whether a download location is currently reachable and writable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MountStatus {
+    /// The directory exists and can be inspected
+    pub mounted: bool,
+    /// A probe file could be created and removed there
+    pub writable: bool,
+    /// E.g. `ext4`, `nfs4`, `cifs`; only known on Linux
+    pub filesystem_type: Option<String>,
+}
+
+/// Check the directory that would receive `path` (the path itself when it is a
+/// directory). A disconnected removable drive shows up as not mounted, while a
+/// network share that dropped usually stays mounted but fails the write probe;
+/// its `nfs`/`cifs`/`smbfs` filesystem type lets the UI warn either way.
+pub fn check_mount_status(path: &Path) -> MountStatus {
+    let directory = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+
+    if !fs::metadata(directory).is_ok_and(|metadata| metadata.is_dir()) {
+        return MountStatus {
+            mounted: false,
+            writable: false,
+            filesystem_type: None,
+        };
+    }
+
+    let probe = temp_path_for(&directory.join("write-probe"));
+    let writable = match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(file) => {
+            drop(file);
+            fs::remove_file(&probe).is_ok()
         }
+        Err(_) => false,
+    };
 
-        hasher.update(&buffer[..bytes_read]);
+    MountStatus {
+        mounted: true,
+        writable,
+        filesystem_type: filesystem_type(directory),
     }
+}
+
+/// Filesystem type of the mount holding `path`, from the longest matching mount point
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
 
-    let result = hasher.finalize();
-    Ok(format!("{:x}", result))
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape_mount_field(fields.next()?);
+            let fs_type = fields.next()?;
+            Some((PathBuf::from(mount_point), fs_type.to_string()))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .map(|(_, fs_type)| fs_type)
 }
 
-#[tauri::command]
-pub async fn get_file_metadata(
-    _app_handle: AppHandle,
-    path: String,
-) -> Result<FileMetadata, String> {
-    use std::time::SystemTime;
+#[cfg(target_os = "macos")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
 
-    let metadata = std::fs::metadata(&path)
-        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
 
-    let modified = metadata.modified()
-        .map_err(|e| format!("Failed to get modification time: {}", e))?;
+    let mut stats = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    // SAFETY: c_path is NUL-terminated and stats is only read once statfs has filled it
+    if unsafe { libc::statfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stats = unsafe { stats.assume_init() };
 
-    let modified_timestamp = modified.duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|e| format!("Failed to convert time: {}", e))?
-        .as_secs();
+    // SAFETY: the kernel NUL-terminates f_fstypename
+    let name = unsafe { std::ffi::CStr::from_ptr(stats.f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().to_lowercase())
+}
 
-    Ok(FileMetadata {
-        size: metadata.len(),
-        modified: modified_timestamp,
-        is_file: metadata.is_file(),
-        is_dir: metadata.is_dir(),
-    })
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
 }
 
-#[derive(serde::Serialize)]
-pub struct FileMetadata {
-    pub size: u64,
-    pub modified: u64,
-    pub is_file: bool,
-    pub is_dir: bool,
+/// Undo the octal escapes (`\040` for a space) used in `/proc/self/mounts`
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let octal = bytes
+            .get(index + 1..index + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (bytes[index], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                index += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).to_string()
+}
+
+/// Move a file, renaming when both paths share a filesystem and otherwise copying
+/// to a temp file beside the destination, syncing it, and renaming it into place.
+/// Unless `overwrite` is set an existing destination is left alone and reported as
+/// `FileExists`.
+pub fn move_file(source: &Path, destination: &Path, overwrite: bool) -> Result<(), DownloadError> {
+    if !overwrite && destination.symlink_metadata().is_ok() {
+        return Err(DownloadError::FileExists(destination.display().to_string()));
+    }
+    if is_same_filesystem(source, destination)? {
+        return rename_into_place(source, destination, overwrite);
+    }
+
+    if destination.file_name().is_none() {
+        return Err(DownloadError::InvalidPath(destination.display().to_string()));
+    }
+    let temp_path = temp_path_for(destination);
+
+    fs::copy(source, &temp_path)?;
+    fs::File::open(&temp_path)?.sync_all()?;
+    if let Err(e) = rename_into_place(&temp_path, destination, overwrite) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    fs::remove_file(source)?;
+    Ok(())
+}
+
+/// Rename `from` to `to`. Without `overwrite`, a hardlink stands in for the rename so a
+/// file that appears at `to` in the meantime fails the move instead of being replaced;
+/// filesystems without hardlinks fall back to a rename after the existence check.
+fn rename_into_place(from: &Path, to: &Path, overwrite: bool) -> Result<(), DownloadError> {
+    if overwrite {
+        fs::rename(from, to)?;
+        return Ok(());
+    }
+    match fs::hard_link(from, to) {
+        Ok(()) => {
+            fs::remove_file(from)?;
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            Err(DownloadError::FileExists(to.display().to_string()))
+        }
+        Err(_) if to.symlink_metadata().is_ok() => Err(DownloadError::FileExists(to.display().to_string())),
+        Err(_) => {
+            fs::rename(from, to)?;
+            Ok(())
+        }
+    }
+}
+
+const TEMP_FILE_SUFFIX: &str = ".tmp";
+/// Shortest custom temp file prefix, counting its leading dot, so cleanup can't match
+/// broad swathes of hidden files
+const MIN_TEMP_FILE_PREFIX_LEN: usize = 4;
+/// File in the app config directory holding the temp file prefix across restarts
+const TEMP_FILE_PREFIX_SETTING: &str = "temp-file-prefix";
+/// Temp files younger than this are assumed to belong to a write in progress
+pub const DEFAULT_TEMP_FILE_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Temp files written next to their target are named `<prefix><name>.tmp`, by default
+/// with the app's name as `.s3-upload-tool-<name>.tmp`: hidden and recognisable so
+/// orphans from a crash can be cleaned up safely
+pub fn default_temp_file_prefix() -> String {
+    format!(".{}-", env!("CARGO_CRATE_NAME").replace('_', "-"))
+}
+
+fn temp_prefix_slot() -> &'static RwLock<String> {
+    static TEMP_FILE_PREFIX: OnceLock<RwLock<String>> = OnceLock::new();
+    TEMP_FILE_PREFIX.get_or_init(|| RwLock::new(default_temp_file_prefix()))
+}
+
+/// Prefix of the temp files this app writes, as set by `set_temp_file_prefix`
+pub fn temp_file_prefix() -> String {
+    temp_prefix_slot()
+        .read()
+        .map(|prefix| prefix.clone())
+        .unwrap_or_else(|_| default_temp_file_prefix())
+}
+
+/// Use `prefix` for temp files from now on. It must be a plain file-name fragment
+/// starting with `.` and at least `MIN_TEMP_FILE_PREFIX_LEN` long, so the files stay
+/// hidden and cleanup doesn't match the user's own files. Cleanup recognises the
+/// current and the default prefix, so orphans named with an earlier custom one are left
+/// alone.
+pub fn set_temp_file_prefix(prefix: &str) -> Result<(), DownloadError> {
+    let invalid = !prefix.starts_with('.')
+        || prefix.len() < MIN_TEMP_FILE_PREFIX_LEN
+        || prefix.starts_with("..")
+        || prefix.contains(['/', '\\', '\0']);
+    if invalid {
+        return Err(DownloadError::InvalidPath(format!("Invalid temp file prefix: {:?}", prefix)));
+    }
+    let mut slot = temp_prefix_slot().write().unwrap_or_else(|e| e.into_inner());
+    *slot = prefix.to_string();
+    Ok(())
+}
+
+/// `set_temp_file_prefix`, remembered in `config_dir` for `restore_temp_file_prefix`
+pub fn save_temp_file_prefix(config_dir: &Path, prefix: &str) -> Result<(), DownloadError> {
+    set_temp_file_prefix(prefix)?;
+    fs::create_dir_all(config_dir)?;
+    fs::write(config_dir.join(TEMP_FILE_PREFIX_SETTING), prefix)?;
+    Ok(())
+}
+
+/// Apply the prefix saved by `save_temp_file_prefix`, if any. A saved prefix that is no
+/// longer valid is logged and the default kept.
+pub fn restore_temp_file_prefix(config_dir: &Path) {
+    let saved = match fs::read_to_string(config_dir.join(TEMP_FILE_PREFIX_SETTING)) {
+        Ok(saved) => saved,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            log::warn!("Could not read the saved temp file prefix: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = set_temp_file_prefix(saved.trim_end()) {
+        log::warn!("Ignoring the saved temp file prefix: {}", e);
+    }
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use std::env;
-    use std::fs;
+pub(crate) mod test_temp_prefix {
+    use std::sync::{Mutex, MutexGuard};
 
-    struct MockDownloadManager {
-        downloads_dir: PathBuf,
+    static IN_USE: Mutex<()> = Mutex::new(());
+
+    /// Keeps other tests off the temp file prefix, restoring the default when dropped
+    pub struct PrefixGuard {
+        _lock: MutexGuard<'static, ()>,
     }
 
-    impl MockDownloadManager {
-        fn new() -> Result<Self, DownloadError> {
-            let downloads_dir = env::temp_dir().join("s3-upload-tool-downloads-test");
-            if downloads_dir.exists() {
-                fs::remove_dir_all(&downloads_dir)?;
+    impl Drop for PrefixGuard {
+        fn drop(&mut self) {
+            let mut slot = super::temp_prefix_slot().write().unwrap_or_else(|e| e.into_inner());
+            *slot = super::default_temp_file_prefix();
+        }
+    }
+
+    /// Taken by tests that set the prefix or assert on temp file names
+    pub fn hold() -> PrefixGuard {
+        PrefixGuard {
+            _lock: IN_USE.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+/// Temp file used while atomically replacing `target`
+pub fn temp_path_for(target: &Path) -> PathBuf {
+    temp_path_with_prefix(target, &temp_file_prefix())
+}
+
+fn temp_path_with_prefix(target: &Path, prefix: &str) -> PathBuf {
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    target.with_file_name(format!("{}{}{}", prefix, file_name, TEMP_FILE_SUFFIX))
+}
+
+fn is_temp_file_name(name: &str, prefix: &str) -> bool {
+    name.len() > prefix.len() + TEMP_FILE_SUFFIX.len()
+        && name.starts_with(prefix)
+        && name.ends_with(TEMP_FILE_SUFFIX)
+}
+
+/// Remove this app's temp files in `directory` last modified more than `max_age` ago,
+/// named with either the current or the default prefix. Files another handle holds a
+/// lock on are left alone, and files that can't be inspected or removed are logged and
+/// skipped. Returns the removed paths.
+pub fn remove_stale_temp_files(directory: &Path, max_age: Duration) -> Result<Vec<PathBuf>, DownloadError> {
+    let prefixes = [temp_file_prefix(), default_temp_file_prefix()];
+    remove_stale_temp_files_with_prefixes(directory, max_age, &prefixes)
+}
+
+fn remove_stale_temp_files_with_prefixes(
+    directory: &Path,
+    max_age: Duration,
+    prefixes: &[String],
+) -> Result<Vec<PathBuf>, DownloadError> {
+    use fs2::FileExt;
+
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Skipping unreadable entry in {}: {}", directory.display(), e);
+                continue;
             }
-            fs::create_dir_all(&downloads_dir)?;
-            Ok(MockDownloadManager { downloads_dir })
+        };
+        if !entry.file_type().is_ok_and(|file_type| file_type.is_file())
+            || !prefixes
+                .iter()
+                .any(|prefix| is_temp_file_name(&entry.file_name().to_string_lossy(), prefix))
+        {
+            continue;
         }
 
-        fn validate_download_path(&self, path: &str) -> Result<PathBuf, DownloadError> {
-            let path_buf = PathBuf::from(path);
+        let path = entry.path();
+        let age = match entry.metadata().and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified.elapsed().unwrap_or(Duration::ZERO),
+            Err(e) => {
+                log::warn!("Skipping temp file {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if age < max_age {
+            continue;
+        }
 
-            // For testing, we'll be more lenient with path validation
-            if path_buf.is_relative() {
-                return Ok(self.downloads_dir.join(path_buf));
+        let file = match fs::OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Skipping temp file {}: {}", path.display(), e);
+                continue;
             }
+        };
+        if file.try_lock_exclusive().is_err() {
+            log::debug!("Skipping temp file in use: {}", path.display());
+            continue;
+        }
+        // Windows refuses to delete a file with an open handle
+        drop(file);
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) => log::warn!("Could not remove stale temp file {}: {}", path.display(), e),
+        }
+    }
+    Ok(removed)
+}
 
-            Ok(path_buf)
+/// Digest algorithms supported by the checksum helpers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    #[default]
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn from_name(name: &str) -> Result<Self, DownloadError> {
+        match name.to_ascii_lowercase().as_str() {
+            "md5" => Ok(ChecksumAlgorithm::Md5),
+            "sha256" | "sha-256" => Ok(ChecksumAlgorithm::Sha256),
+            _ => Err(DownloadError::Path(format!(
+                "Unsupported checksum algorithm: {}",
+                name
+            ))),
         }
+    }
 
-        fn check_file_exists(&self, path: &PathBuf) -> bool {
-            path.exists()
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Sha256 => "sha256",
         }
+    }
 
-        fn get_default_download_path(&self, filename: &str) -> PathBuf {
-            self.downloads_dir.join(filename)
+    pub(crate) fn hasher(&self) -> ChecksumHasher {
+        use sha2::Digest;
+
+        match self {
+            ChecksumAlgorithm::Md5 => ChecksumHasher::Md5(md5::Md5::new()),
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(sha2::Sha256::new()),
         }
+    }
+}
 
-        fn generate_unique_filename(&self, base_path: &PathBuf) -> PathBuf {
-            if !base_path.exists() {
-                return base_path.clone();
-            }
+pub(crate) enum ChecksumHasher {
+    Md5(md5::Md5),
+    Sha256(sha2::Sha256),
+}
 
-            let parent = base_path.parent().unwrap_or_else(|| std::path::Path::new("."));
-            let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-            let extension = base_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+impl ChecksumHasher {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
 
-            for i in 1..1000 {
-                let new_filename = if extension.is_empty() {
-                    format!("{} ({})", stem, i)
-                } else {
-                    format!("{} ({}).{}", stem, i, extension)
-                };
-                
-                let new_path = parent.join(new_filename);
-                if !new_path.exists() {
-                    return new_path;
-                }
-            }
+        match self {
+            ChecksumHasher::Md5(hasher) => hasher.update(data),
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
 
-            // Fallback with timestamp
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            
-            let new_filename = if extension.is_empty() {
-                format!("{}-{}", stem, timestamp)
-            } else {
-                format!("{}-{}.{}", stem, timestamp, extension)
-            };
-            
-            parent.join(new_filename)
+    pub(crate) fn finalize_hex(self) -> String {
+        use sha2::Digest;
+
+        match self {
+            ChecksumHasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
         }
     }
+}
 
-    #[test]
-    fn test_validate_download_path() {
-        let manager = MockDownloadManager::new().unwrap();
-        
-        // Test relative path
-        let result = manager.validate_download_path("test.txt");
-        assert!(result.is_ok());
-        
-        // Test absolute path
-        let abs_path = manager.downloads_dir.join("test.txt");
-        let result = manager.validate_download_path(&abs_path.to_string_lossy());
-        assert!(result.is_ok());
+/// Stream a file through the chosen hasher and return the lowercase hex digest
+pub fn compute_file_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String, DownloadError> {
+    let mut digests = compute_file_checksums(path, &[algorithm])?;
+    Ok(digests.remove(algorithm.name()).unwrap_or_default())
+}
+
+/// Largest file mapped in one piece. 32-bit targets can't spare the address space
+/// for big mappings, so larger files go through the streaming reader instead.
+#[cfg(target_pointer_width = "64")]
+const MMAP_MAX_SIZE: u64 = 1 << 40;
+#[cfg(not(target_pointer_width = "64"))]
+const MMAP_MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Hash a file through a read-only memory map, falling back to the streaming path
+/// for empty files, files above `MMAP_MAX_SIZE`, or when mapping fails.
+pub fn compute_file_checksum_mmap(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String, DownloadError> {
+    let file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    if size == 0 || size > MMAP_MAX_SIZE {
+        return compute_file_checksum(path, algorithm);
     }
 
-    #[test]
-    fn test_file_exists() {
-        let manager = MockDownloadManager::new().unwrap();
-        let test_file = manager.downloads_dir.join("test.txt");
-        
-        // File doesn't exist initially
-        assert!(!manager.check_file_exists(&test_file));
-        
-        // Create file
-        fs::write(&test_file, "test content").unwrap();
-        
-        // File exists now
-        assert!(manager.check_file_exists(&test_file));
+    // Safety: the map is read-only and dropped before returning. A concurrent
+    // truncation by another process could fault, which is the usual mmap caveat.
+    let map = match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(map) => map,
+        Err(e) => {
+            log::warn!("mmap failed for {}, streaming instead: {}", path.display(), e);
+            return compute_file_checksum(path, algorithm);
+        }
+    };
+
+    let mut hasher = algorithm.hasher();
+    hasher.update(&map);
+    Ok(hasher.finalize_hex())
+}
+
+/// Read the file once, feeding every chunk to each requested hasher, and return
+/// the hex digests keyed by algorithm name.
+pub fn compute_file_checksums(
+    path: &Path,
+    algorithms: &[ChecksumAlgorithm],
+) -> Result<HashMap<String, String>, DownloadError> {
+    compute_file_checksums_cancellable(path, algorithms, &AtomicBool::new(false))
+}
+
+/// `compute_file_checksums` that gives up with `Cancelled` once `cancelled` is set,
+/// checked between reads
+pub fn compute_file_checksums_cancellable(
+    path: &Path,
+    algorithms: &[ChecksumAlgorithm],
+    cancelled: &AtomicBool,
+) -> Result<HashMap<String, String>, DownloadError> {
+    checksums_from_reader(fs::File::open(path)?, algorithms, cancelled)
+}
+
+/// Digests of everything read from `reader`, checking `cancelled` between chunks
+fn checksums_from_reader(
+    mut file: impl std::io::Read,
+    algorithms: &[ChecksumAlgorithm],
+    cancelled: &AtomicBool,
+) -> Result<HashMap<String, String>, DownloadError> {
+    let mut buffer = vec![0u8; 8192]; // 8KB buffer
+    let mut hashers: Vec<(ChecksumAlgorithm, ChecksumHasher)> = algorithms
+        .iter()
+        .map(|algorithm| (*algorithm, algorithm.hasher()))
+        .collect();
+
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(DownloadError::Cancelled);
+        }
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        for (_, hasher) in hashers.iter_mut() {
+            hasher.update(&buffer[..bytes_read]);
+        }
     }
 
-    #[test]
-    fn test_default_download_path() {
-        let manager = MockDownloadManager::new().unwrap();
-        let filename = "test.txt";
-        let expected_path = manager.downloads_dir.join(filename);
-        let actual_path = manager.get_default_download_path(filename);
-        
-        assert_eq!(actual_path, expected_path);
+    Ok(hashers
+        .into_iter()
+        .map(|(algorithm, hasher)| (algorithm.name().to_string(), hasher.finalize_hex()))
+        .collect())
+}
+
+/// SHA-256 of each consecutive `block_size` block of the file, the last one possibly
+/// shorter. An empty file has no blocks.
+pub fn block_checksums(path: &Path, block_size: u64) -> Result<Vec<String>, DownloadError> {
+    use std::io::Read;
+
+    if block_size == 0 {
+        return Err(DownloadError::InvalidBlockSize(block_size));
     }
 
-    #[test]
-    fn test_generate_unique_filename() {
-        let manager = MockDownloadManager::new().unwrap();
-        let base_path = manager.downloads_dir.join("test.txt");
-        
-        // First call should return the original path
-        let unique_path = manager.generate_unique_filename(&base_path);
-        assert_eq!(unique_path, base_path);
-        
-        // Create the file
-        fs::write(&base_path, "test content").unwrap();
-        
-        // Second call should return a unique path
-        let unique_path = manager.generate_unique_filename(&base_path);
-        assert_ne!(unique_path, base_path);
-        assert!(unique_path.to_string_lossy().contains("test (1).txt"));
+    let mut file = fs::File::open(path)?;
+    let mut buffer = vec![0u8; 8192];
+    let mut digests = Vec::new();
+    loop {
+        let mut hasher = ChecksumAlgorithm::Sha256.hasher();
+        let mut length = 0u64;
+        while length < block_size {
+            let wanted = (block_size - length).min(buffer.len() as u64) as usize;
+            let read = file.read(&mut buffer[..wanted])?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            length += read as u64;
+        }
+
+        if length == 0 {
+            break;
+        }
+        digests.push(hasher.finalize_hex());
+        if length < block_size {
+            break;
+        }
     }
+    Ok(digests)
+}
 
-    #[test]
-    fn test_generate_unique_filename_no_extension() {
-        let manager = MockDownloadManager::new().unwrap();
-        let base_path = manager.downloads_dir.join("test");
-        
-        // Create the file
-        fs::write(&base_path, "test content").unwrap();
-        
-        // Should generate unique name without extension
-        let unique_path = manager.generate_unique_filename(&base_path);
-        assert_ne!(unique_path, base_path);
-        assert!(unique_path.to_string_lossy().contains("test (1)"));
+/// Indices of the blocks whose digest differs from `previous`, as returned earlier by
+/// `block_checksums` with the same `block_size`. Blocks added or removed by a change in
+/// length count as changed.
+pub fn changed_blocks(path: &Path, previous: &[String], block_size: u64) -> Result<Vec<usize>, DownloadError> {
+    let current = block_checksums(path, block_size)?;
+    Ok((0..current.len().max(previous.len()))
+        .filter(|&index| match (current.get(index), previous.get(index)) {
+            (Some(now), Some(before)) => !now.eq_ignore_ascii_case(before),
+            _ => true,
+        })
+        .collect())
+}
+
+fn cancellation_registry() -> &'static Mutex<HashMap<String, Arc<CancelSignal>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Arc<CancelSignal>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Cancellation flag plus a wakeup for tasks still waiting to start
+#[derive(Default)]
+struct CancelSignal {
+    flag: AtomicBool,
+    notify: Notify,
+}
+
+/// Registers a task id so `cancel_task` can stop it; unregisters when dropped
+pub struct CancellationGuard {
+    task_id: String,
+    signal: Arc<CancelSignal>,
+}
+
+impl CancellationGuard {
+    pub fn register(task_id: &str) -> Self {
+        let signal = Arc::new(CancelSignal::default());
+        if let Ok(mut registry) = cancellation_registry().lock() {
+            registry.insert(task_id.to_string(), Arc::clone(&signal));
+        }
+        CancellationGuard {
+            task_id: task_id.to_string(),
+            signal,
+        }
+    }
+
+    pub fn flag(&self) -> &AtomicBool {
+        &self.signal.flag
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.signal.flag.load(Ordering::Relaxed)
     }
-}
\ No newline at end of file
+
+    /// Resolves once `cancel_task` has been called for this task
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.signal.notify.notified();
+            tokio::pin!(notified);
+            // Register interest before checking the flag so a concurrent cancel isn't missed
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for CancellationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = cancellation_registry().lock() {
+            // The id may have been reused by a newer task in the meantime
+            if registry
+                .get(&self.task_id)
+                .is_some_and(|signal| Arc::ptr_eq(signal, &self.signal))
+            {
+                registry.remove(&self.task_id);
+            }
+        }
+    }
+}
+
+/// Ask the task registered as `task_id` to stop, whether it is running or still queued.
+/// Returns whether such a task was registered.
+pub fn cancel_task(task_id: &str) -> bool {
+    let registry = match cancellation_registry().lock() {
+        Ok(registry) => registry,
+        Err(_) => return false,
+    };
+    match registry.get(task_id) {
+        Some(signal) => {
+            signal.flag.store(true, Ordering::Relaxed);
+            signal.notify.notify_waiters();
+            true
+        }
+        None => false,
+    }
+}
+
+/// A download driven through `begin_file_write` that hasn't written a chunk for this
+/// long is taken as abandoned, so a `begin_file_write` never matched by
+/// `finish_file_write` (say the page reloaded mid-download) doesn't block reads of the
+/// file until the app restarts
+const ABANDONED_WRITE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Writers currently marked on one path
+#[derive(Debug, Default)]
+struct WriteMarks {
+    /// Backend downloads, marked through `mark_write_started`
+    backend: usize,
+    /// Frontend downloads, marked through `begin_file_write`
+    frontend: usize,
+    /// When a frontend download last began or wrote a chunk
+    frontend_activity: Option<Instant>,
+}
+
+impl WriteMarks {
+    /// Forget frontend marks that went quiet for `ABANDONED_WRITE_TIMEOUT`
+    fn expire_abandoned(&mut self) {
+        if self
+            .frontend_activity
+            .is_some_and(|at| at.elapsed() >= ABANDONED_WRITE_TIMEOUT)
+        {
+            self.frontend = 0;
+            self.frontend_activity = None;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.backend == 0 && self.frontend == 0
+    }
+}
+
+fn writes_in_progress() -> &'static Mutex<HashMap<PathBuf, WriteMarks>> {
+    static WRITES: OnceLock<Mutex<HashMap<PathBuf, WriteMarks>>> = OnceLock::new();
+    WRITES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Key for `path` in the write registry: absolute, with the parent canonicalised when
+/// it exists, so `./a.bin`, `dir/../a.bin` and the absolute path share one entry. The
+/// file itself may not exist yet.
+fn write_registry_key(path: &Path) -> PathBuf {
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|parent| parent.join(name))
+            .unwrap_or(absolute),
+        _ => absolute,
+    }
+}
+
+/// Apply `update` to the marks on `path`, dropping the entry once no writer is left
+fn update_write_marks(path: &Path, update: impl FnOnce(&mut WriteMarks)) {
+    update_write_marks_at(write_registry_key(path), update);
+}
+
+fn update_write_marks_at(key: PathBuf, update: impl FnOnce(&mut WriteMarks)) {
+    let mut writes = writes_in_progress().lock().unwrap_or_else(|e| e.into_inner());
+    let marks = writes.entry(key.clone()).or_default();
+    marks.expire_abandoned();
+    update(marks);
+    if marks.is_empty() {
+        writes.remove(&key);
+    }
+}
+
+/// Record that a download has started writing `path`. Calls nest, so the path stays
+/// marked until each one is matched by `mark_write_finished`.
+pub fn mark_write_started(path: &Path) {
+    update_write_marks(path, |marks| marks.backend += 1);
+}
+
+pub fn mark_write_finished(path: &Path) {
+    update_write_marks(path, |marks| marks.backend = marks.backend.saturating_sub(1));
+}
+
+/// Whether a download is still writing `path`, so reading it now may return partial data
+pub fn is_write_in_progress(path: &Path) -> bool {
+    let mut in_progress = false;
+    update_write_marks(path, |marks| in_progress = !marks.is_empty());
+    in_progress
+}
+
+/// Marks a path as being written for as long as it is alive. The registry key is
+/// worked out once, so the mark is released even if the path resolves differently by
+/// the time the guard drops, e.g. because its parent directory was created meanwhile.
+pub struct WriteInProgressGuard {
+    key: PathBuf,
+}
+
+impl WriteInProgressGuard {
+    pub fn register(path: &Path) -> Self {
+        let key = write_registry_key(path);
+        update_write_marks_at(key.clone(), |marks| marks.backend += 1);
+        WriteInProgressGuard { key }
+    }
+}
+
+impl Drop for WriteInProgressGuard {
+    fn drop(&mut self) {
+        update_write_marks_at(self.key.clone(), |marks| {
+            marks.backend = marks.backend.saturating_sub(1)
+        });
+    }
+}
+
+/// Default number of queued downloads running at the same time
+const DEFAULT_QUEUE_CONCURRENCY: usize = 3;
+
+/// State of a task in flight on a `DownloadQueue`. Finished tasks are dropped from the
+/// queue and report their outcome through the task's result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueuedTaskState {
+    Queued,
+    Running,
+}
+
+/// Runs download tasks with at most `concurrency` of them in flight. Tasks are
+/// registered for cancellation as soon as they are enqueued, so cancelling one that
+/// is still waiting drops it without it ever taking a permit. The app manages one
+/// queue, shared by `queue_download_to_file` and `cancel_queued_download`.
+#[derive(Clone)]
+pub struct DownloadQueue {
+    semaphore: Arc<Semaphore>,
+    states: Arc<Mutex<HashMap<String, QueuedTaskState>>>,
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        DownloadQueue::new(DEFAULT_QUEUE_CONCURRENCY)
+    }
+}
+
+impl DownloadQueue {
+    pub fn new(concurrency: usize) -> Self {
+        DownloadQueue {
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+            states: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn set_state(&self, task_id: &str, state: QueuedTaskState) {
+        if let Ok(mut states) = self.states.lock() {
+            states.insert(task_id.to_string(), state);
+        }
+    }
+
+    fn clear_state(&self, task_id: &str) {
+        if let Ok(mut states) = self.states.lock() {
+            states.remove(task_id);
+        }
+    }
+
+    /// State of `task_id`, `None` once it has finished or if it was never queued
+    pub fn state(&self, task_id: &str) -> Option<QueuedTaskState> {
+        self.states.lock().ok()?.get(task_id).copied()
+    }
+
+    /// Queue `work` under `task_id`. `work` receives the task's guard so it can
+    /// check for cancellation while running.
+    pub fn enqueue<T, F, Fut>(
+        &self,
+        task_id: &str,
+        work: F,
+    ) -> tokio::task::JoinHandle<Result<T, DownloadError>>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<CancellationGuard>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<T, DownloadError>> + Send + 'static,
+    {
+        let guard = Arc::new(CancellationGuard::register(task_id));
+        self.set_state(task_id, QueuedTaskState::Queued);
+
+        let queue = self.clone();
+        let task_id = task_id.to_string();
+        tokio::spawn(async move {
+            let permit = tokio::select! {
+                biased;
+                _ = guard.cancelled() => None,
+                permit = Arc::clone(&queue.semaphore).acquire_owned() => permit.ok(),
+            };
+            let _permit = match permit {
+                Some(permit) if !guard.is_cancelled() => permit,
+                _ => {
+                    queue.clear_state(&task_id);
+                    return Err(DownloadError::Cancelled);
+                }
+            };
+
+            queue.set_state(&task_id, QueuedTaskState::Running);
+            let result = work(Arc::clone(&guard)).await;
+            queue.clear_state(&task_id);
+            result
+        })
+    }
+
+    /// Cancel a queued or running task. Returns false if it is not in flight.
+    pub fn cancel_download(&self, task_id: &str) -> bool {
+        self.state(task_id).is_some() && cancel_task(task_id)
+    }
+}
+
+/// Digests `indexed_checksum` keeps; past this the least recently used is evicted
+const CHECKSUM_INDEX_CAPACITY: usize = 4096;
+
+/// Cached digest of a file, valid while its size and mtime are unchanged
+struct IndexedDigest {
+    size: u64,
+    modified: std::time::SystemTime,
+    digest: String,
+    last_used: Instant,
+}
+
+type ChecksumIndex = HashMap<(PathBuf, ChecksumAlgorithm), IndexedDigest>;
+
+/// Add `entry` to `index`, first evicting the least recently used entries so it holds
+/// at most `capacity`
+fn remember_digest(
+    index: &mut ChecksumIndex,
+    key: (PathBuf, ChecksumAlgorithm),
+    entry: IndexedDigest,
+    capacity: usize,
+) {
+    if !index.contains_key(&key) {
+        while index.len() >= capacity.max(1) {
+            let Some(oldest) = index
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            index.remove(&oldest);
+        }
+    }
+    index.insert(key, entry);
+}
+
+fn checksum_index() -> &'static Mutex<ChecksumIndex> {
+    static INDEX: OnceLock<Mutex<ChecksumIndex>> = OnceLock::new();
+    INDEX.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Digest of `path`, reusing the cached value when the file hasn't changed
+fn indexed_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String, DownloadError> {
+    let metadata = fs::metadata(path)?;
+    let size = metadata.len();
+    let modified = metadata.modified()?;
+    let key = (path.to_path_buf(), algorithm);
+
+    if let Some(entry) = checksum_index().lock().unwrap().get_mut(&key) {
+        if entry.size == size && entry.modified == modified {
+            entry.last_used = Instant::now();
+            return Ok(entry.digest.clone());
+        }
+    }
+
+    let digest = compute_file_checksum(path, algorithm)?;
+    remember_digest(
+        &mut checksum_index().lock().unwrap(),
+        key,
+        IndexedDigest {
+            size,
+            modified,
+            digest: digest.clone(),
+            last_used: Instant::now(),
+        },
+        CHECKSUM_INDEX_CAPACITY,
+    );
+    Ok(digest)
+}
+
+/// Find a file directly inside `directory` whose content has the given digest
+pub fn find_file_by_checksum(
+    directory: &Path,
+    digest: &str,
+    algorithm: ChecksumAlgorithm,
+) -> Result<Option<PathBuf>, DownloadError> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        // Files that vanish or can't be read mid-scan are simply not candidates
+        if let Ok(candidate) = indexed_checksum(&path, algorithm) {
+            if candidate.eq_ignore_ascii_case(digest) {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `target` already holds the expected download. The size is checked first
+/// so obviously-wrong files are never handed to `hash`. Without a checksum only a
+/// known size can prove the file complete.
+pub fn download_is_complete_with<H>(
+    target: &Path,
+    expected_size: Option<u64>,
+    expected_checksum: Option<&str>,
+    hash: H,
+) -> Result<bool, DownloadError>
+where
+    H: FnOnce(&Path) -> Result<String, DownloadError>,
+{
+    let metadata = match fs::metadata(target) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    if !metadata.is_file() || expected_size.is_some_and(|size| size != metadata.len()) {
+        return Ok(false);
+    }
+
+    match expected_checksum {
+        Some(expected) => Ok(hash(target)?.eq_ignore_ascii_case(expected)),
+        None => Ok(expected_size.is_some()),
+    }
+}
+
+pub fn download_is_complete(
+    target: &Path,
+    expected_size: Option<u64>,
+    expected_checksum: Option<&str>,
+    algorithm: ChecksumAlgorithm,
+) -> Result<bool, DownloadError> {
+    download_is_complete_with(target, expected_size, expected_checksum, |path| {
+        compute_file_checksum(path, algorithm)
+    })
+}
+
+/// Fail with `SizeMismatch` unless `path` holds exactly `expected_size` bytes, catching
+/// transfers that ended early without a socket error
+pub fn check_download_size(path: &Path, expected_size: u64) -> Result<(), DownloadError> {
+    let actual = fs::metadata(path)?.len();
+    if actual != expected_size {
+        return Err(DownloadError::SizeMismatch {
+            expected: expected_size,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Outcome of reading every entry of a ZIP archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZipReport {
+    /// The central directory parsed and every entry decompressed with a matching CRC
+    pub valid: bool,
+    pub entry_count: usize,
+    /// Sum of the uncompressed sizes recorded in the central directory
+    pub uncompressed_size: u64,
+}
+
+/// Open `path` as a ZIP archive and read each entry through, so truncation or a
+/// corrupt entry shows up as `valid: false` rather than at extraction time.
+/// Only an unreadable file is an error.
+pub fn inspect_zip(path: &Path) -> Result<ZipReport, DownloadError> {
+    let file = fs::File::open(path)?;
+    let mut archive = match zip::ZipArchive::new(std::io::BufReader::new(file)) {
+        Ok(archive) => archive,
+        Err(zip::result::ZipError::Io(e)) if e.kind() != std::io::ErrorKind::UnexpectedEof => {
+            return Err(DownloadError::Io(e))
+        }
+        Err(_) => {
+            return Ok(ZipReport {
+                valid: false,
+                entry_count: 0,
+                uncompressed_size: 0,
+            })
+        }
+    };
+
+    let entry_count = archive.len();
+    let mut uncompressed_size = 0u64;
+    let mut valid = true;
+    for index in 0..entry_count {
+        let mut entry = match archive.by_index(index) {
+            Ok(entry) => entry,
+            Err(_) => {
+                valid = false;
+                break;
+            }
+        };
+        uncompressed_size += entry.size();
+        // Reading to the end makes the zip crate check the entry's CRC32
+        if std::io::copy(&mut entry, &mut std::io::sink()).is_err() {
+            valid = false;
+            break;
+        }
+    }
+
+    Ok(ZipReport {
+        valid,
+        entry_count,
+        uncompressed_size,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMethod {
+    Hardlink,
+    Copy,
+}
+
+/// Hardlink `source` to `destination` when they share a filesystem, copying otherwise
+/// (or when the filesystem refuses hardlinks).
+pub fn link_or_copy_file(source: &Path, destination: &Path) -> Result<LinkMethod, DownloadError> {
+    if is_same_filesystem(source, destination)? && fs::hard_link(source, destination).is_ok() {
+        return Ok(LinkMethod::Hardlink);
+    }
+
+    fs::copy(source, destination)?;
+    Ok(LinkMethod::Copy)
+}
+
+/// Size of each read/write while copying
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+/// Bytes compared at each end of an existing partial copy before resuming it
+const COPY_RESUME_VERIFY_WINDOW: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyStart {
+    /// Nothing was at the destination
+    Fresh,
+    /// The destination held a matching prefix and was continued
+    Resumed,
+    /// The destination didn't match the source and was copied over from scratch
+    Restarted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyOutcome {
+    pub start: CopyStart,
+    /// Destination bytes kept from the earlier attempt
+    pub resumed_from: u64,
+    pub bytes_copied: u64,
+}
+
+/// Checksum of `length` bytes of `file` starting at `offset`
+fn region_checksum(file: &mut fs::File, offset: u64, length: u64) -> Result<String, DownloadError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut hasher = ChecksumAlgorithm::Sha256.hasher();
+    let mut region = file.take(length);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = region.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize_hex())
+}
+
+/// Whether the first `length` bytes of both files look identical, judged by the
+/// checksums of the first and last `COPY_RESUME_VERIFY_WINDOW` bytes of that range
+fn prefixes_match(source: &mut fs::File, destination: &mut fs::File, length: u64) -> Result<bool, DownloadError> {
+    let window = length.min(COPY_RESUME_VERIFY_WINDOW);
+    for offset in [0, length - window] {
+        if region_checksum(source, offset, window)? != region_checksum(destination, offset, window)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Copy `source` to `destination`, continuing an earlier partial copy when the
+/// destination is a matching prefix of the source and starting over otherwise.
+/// `on_progress` receives the destination size and the total after every chunk.
+pub fn copy_file_resumable<F>(
+    source: &Path,
+    destination: &Path,
+    mut on_progress: F,
+) -> Result<CopyOutcome, DownloadError>
+where
+    F: FnMut(u64, u64),
+{
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut source_file = fs::File::open(source)?;
+    let total = source_file.metadata()?.len();
+    let existing = match fs::metadata(destination) {
+        Ok(metadata) => Some(metadata.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut destination_file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(destination)?;
+    let (start, resumed_from) = match existing {
+        None => (CopyStart::Fresh, 0),
+        Some(size) if size <= total && prefixes_match(&mut source_file, &mut destination_file, size)? => {
+            (CopyStart::Resumed, size)
+        }
+        Some(_) => (CopyStart::Restarted, 0),
+    };
+    destination_file.set_len(resumed_from)?;
+    source_file.seek(SeekFrom::Start(resumed_from))?;
+    destination_file.seek(SeekFrom::Start(resumed_from))?;
+
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut written = resumed_from;
+    on_progress(written, total);
+    loop {
+        let read = source_file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        destination_file.write_all(&buffer[..read])?;
+        written += read as u64;
+        on_progress(written, total);
+    }
+    destination_file.sync_all()?;
+
+    Ok(CopyOutcome {
+        start,
+        resumed_from,
+        bytes_copied: written - resumed_from,
+    })
+}
+
+/// Unit system used when formatting byte counts for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeUnits {
+    /// Powers of 1024 (KiB, MiB, ...)
+    Binary,
+    /// Powers of 1000 (KB, MB, ...)
+    #[default]
+    Decimal,
+}
+
+impl SizeUnits {
+    pub fn from_name(name: &str) -> Result<Self, DownloadError> {
+        match name.to_ascii_lowercase().as_str() {
+            "binary" | "iec" => Ok(SizeUnits::Binary),
+            "decimal" | "si" => Ok(SizeUnits::Decimal),
+            _ => Err(DownloadError::Path(format!("Unsupported size units: {}", name))),
+        }
+    }
+}
+
+/// Format a byte count for display, e.g. `"1.5 GB"` or `"1.0 KiB"`
+pub fn format_file_size(bytes: u64, units: SizeUnits) -> String {
+    let (base, labels) = match units {
+        SizeUnits::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+        SizeUnits::Decimal => (1000.0, ["B", "KB", "MB", "GB", "TB", "PB", "EB"]),
+    };
+
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut index = 0;
+    while value >= base && index < labels.len() - 1 {
+        value /= base;
+        index += 1;
+    }
+    // 999_950 bytes would otherwise round to "1000.0 KB"
+    if (value * 10.0).round() / 10.0 >= base && index < labels.len() - 1 {
+        value /= base;
+        index += 1;
+    }
+    format!("{:.1} {}", value, labels[index])
+}
+
+/// Default limit for IO-heavy commands, so a wedged network filesystem can't hang them
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 30;
+
+static COMMAND_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_COMMAND_TIMEOUT_SECS);
+
+pub fn command_timeout() -> Duration {
+    Duration::from_secs(COMMAND_TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+pub fn set_command_timeout(timeout: Duration) {
+    COMMAND_TIMEOUT_SECS.store(timeout.as_secs().max(1), Ordering::Relaxed);
+}
+
+/// Fail with a timed-out IO error if `future` doesn't finish within `timeout`
+pub async fn with_timeout<T, F>(timeout: Duration, future: F) -> Result<T, DownloadError>
+where
+    F: std::future::Future<Output = Result<T, DownloadError>>,
+{
+    tokio::time::timeout(timeout, future).await.unwrap_or_else(|_| {
+        Err(DownloadError::Io(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "operation timed out",
+        )))
+    })
+}
+
+/// Run blocking filesystem work off the async runtime under the command timeout.
+/// On expiry the command returns while the stuck thread is left to finish on its own.
+async fn run_blocking_with_timeout<T, F>(work: F) -> Result<T, DownloadError>
+where
+    F: FnOnce() -> Result<T, DownloadError> + Send + 'static,
+    T: Send + 'static,
+{
+    with_timeout(command_timeout(), async move {
+        tokio::task::spawn_blocking(work)
+            .await
+            .map_err(|e| DownloadError::Io(std::io::Error::other(e)))?
+    })
+    .await
+}
+
+// Tauri commands
+#[tauri::command]
+pub async fn select_download_path(
+    app_handle: AppHandle,
+    default_filename: Option<String>,
+) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let mut dialog = app_handle.dialog().file();
+
+    if let Some(filename) = default_filename {
+        dialog = dialog.set_file_name(&filename);
+    }
+
+    use std::sync::{Arc, Mutex};
+    
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    
+    dialog.save_file(move |path| {
+        *result_clone.lock().unwrap() = path;
+    });
+    
+    // In a real implementation, you'd want to use async/await properly
+    // For now, we'll return None as this is just for testing
+    Ok(None)
+}
+
+#[tauri::command]
+pub async fn select_download_directory(app_handle: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    use std::sync::{Arc, Mutex};
+    
+    let result = Arc::new(Mutex::new(None));
+    let result_clone = Arc::clone(&result);
+    
+    app_handle.dialog().file().pick_folder(move |path| {
+        *result_clone.lock().unwrap() = path;
+    });
+    
+    // In a real implementation, you'd want to use async/await properly
+    // For now, we'll return None as this is just for testing
+    Ok(None)
+}
+
+#[tauri::command]
+pub async fn validate_download_path(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<bool, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    match download_manager.validate_download_path(&path) {
+        Ok(_) => Ok(true),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Outcome of validating a download path, with the reason it was rejected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathValidation {
+    pub valid: bool,
+    pub reason: Option<String>,
+}
+
+impl From<Result<PathBuf, DownloadError>> for PathValidation {
+    fn from(result: Result<PathBuf, DownloadError>) -> Self {
+        match result {
+            Ok(_) => PathValidation {
+                valid: true,
+                reason: None,
+            },
+            Err(e) => PathValidation {
+                valid: false,
+                reason: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn validate_download_path_detailed(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<PathValidation, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    Ok(download_manager.validate_download_path(&path).into())
+}
+
+#[tauri::command]
+pub async fn check_file_exists(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<bool, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let path_buf = PathBuf::from(path);
+    run_blocking_with_timeout(move || Ok(download_manager.check_file_exists(&path_buf)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_file_size(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<u64, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let path_buf = PathBuf::from(path);
+    run_blocking_with_timeout(move || download_manager.get_file_size(&path_buf))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_io_command_timeout(secs: u64) -> Result<(), String> {
+    if secs == 0 {
+        return Err("Timeout must be at least one second".to_string());
+    }
+    set_command_timeout(Duration::from_secs(secs));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_download_eta(
+    downloaded_bytes: u64,
+    total_bytes: u64,
+    speed_bytes_per_sec: f64,
+) -> Option<u64> {
+    estimate_eta(downloaded_bytes, total_bytes, speed_bytes_per_sec)
+}
+
+#[tauri::command]
+pub fn predict_download_duration(total_bytes: u64, sample_speed_bytes_per_sec: f64) -> Option<u64> {
+    predict_duration(total_bytes, sample_speed_bytes_per_sec)
+}
+
+#[tauri::command]
+pub async fn get_formatted_file_size(
+    _app_handle: AppHandle,
+    path: String,
+    units: Option<String>,
+) -> Result<String, String> {
+    let units = match units {
+        Some(name) => SizeUnits::from_name(&name).map_err(|e| e.to_string())?,
+        None => SizeUnits::default(),
+    };
+    let size = fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    Ok(format_file_size(size, units))
+}
+
+#[tauri::command]
+pub async fn get_default_download_path(
+    app_handle: AppHandle,
+    filename: String,
+) -> Result<String, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let path = download_manager.get_default_download_path(&filename);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// `name` shortened to fit `max_bytes` (default 255), keeping its extension
+#[tauri::command]
+pub fn truncate_filename(name: String, max_bytes: Option<usize>) -> String {
+    truncate_filename_preserving_extension(&name, max_bytes.unwrap_or(MAX_FILENAME_BYTES))
+}
+
+/// Overall progress of a parallel download from the progress of its parts
+#[tauri::command]
+pub fn aggregate_download_progress(task_id: String, parts: Vec<PartProgress>) -> DownloadProgress {
+    aggregate_progress(task_id, &parts)
+}
+
+#[tauri::command]
+pub async fn get_downloads_directory(app_handle: AppHandle) -> Result<String, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    Ok(download_manager.downloads_dir().to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn generate_unique_filename(
+    app_handle: AppHandle,
+    base_path: String,
+    case_insensitive: Option<bool>,
+) -> Result<String, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let base_path_buf = PathBuf::from(base_path);
+    let unique_path = download_manager.generate_unique_filename_with(
+        &base_path_buf,
+        case_insensitive.unwrap_or(CASE_INSENSITIVE_FILESYSTEM),
+    );
+    Ok(unique_path.to_string_lossy().to_string())
+}
+
+/// Reserve `path` before downloading into it, so two downloads that picked the same
+/// name from `generate_unique_filename` can't both claim it
+#[tauri::command]
+pub async fn create_exclusive_file(path: String) -> Result<bool, String> {
+    run_blocking_with_timeout(move || create_placeholder(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn suggest_extension_for_mime_type(mime: String) -> Option<String> {
+    suggest_extension_for_mime(&mime)
+}
+
+#[tauri::command]
+pub async fn check_mount_writable(path: String) -> Result<MountStatus, String> {
+    run_blocking_with_timeout(move || Ok(check_mount_status(Path::new(&path))))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn path_collides_case_insensitive(path: String) -> Result<bool, String> {
+    collides_case_insensitive(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_directory(
+    _app_handle: AppHandle,
+    path: String,
+) -> Result<(), String> {
+    let path_buf = PathBuf::from(path);
+    fs::create_dir_all(path_buf).map_err(|e| e.to_string())
+}
+
+/// False when a `required_bytes` file is too big for the filesystem at `path`,
+/// e.g. over 4 GiB on a FAT32 drive
+#[tauri::command]
+pub async fn check_filesystem_max_file_size(path: String, required_bytes: u64) -> Result<bool, String> {
+    run_blocking_with_timeout(move || fits_filesystem_max_file_size(Path::new(&path), required_bytes))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Preferred I/O block size at `path`, for sizing read and write buffers
+#[tauri::command]
+pub async fn get_filesystem_block_size(path: String) -> Result<u64, String> {
+    run_blocking_with_timeout(move || preferred_io_block_size(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Free inodes at `path`, so a batch of many small files can be checked before it starts
+#[tauri::command]
+pub async fn check_free_inodes(path: String) -> Result<u64, String> {
+    run_blocking_with_timeout(move || available_inodes(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn check_disk_space(
+    app_handle: AppHandle,
+    path: String,
+    required_bytes: u64,
+) -> Result<bool, String> {
+    let download_manager = DownloadManager::new(&app_handle).map_err(|e| e.to_string())?;
+    let path_buf = PathBuf::from(path);
+    run_blocking_with_timeout(move || download_manager.check_disk_space(&path_buf, required_bytes))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn estimate_batch_space(
+    _app_handle: AppHandle,
+    sizes: Vec<u64>,
+    target_dir: String,
+) -> Result<BatchSpaceReport, String> {
+    let target = PathBuf::from(target_dir);
+    let available = available_disk_space(&target).map_err(|e| e.to_string())?;
+    let block_size = filesystem_block_size(&target).map_err(|e| e.to_string())?;
+    Ok(BatchSpaceReport::new(&sizes, block_size, available))
+}
+
+#[tauri::command]
+pub async fn same_filesystem(
+    _app_handle: AppHandle,
+    path_a: String,
+    path_b: String,
+) -> Result<bool, String> {
+    is_same_filesystem(Path::new(&path_a), Path::new(&path_b)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn find_existing_by_checksum(
+    _app_handle: AppHandle,
+    directory: String,
+    digest: String,
+    algorithm: Option<String>,
+) -> Result<Option<String>, String> {
+    let algorithm = match algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(&name).map_err(|e| e.to_string())?,
+        None => ChecksumAlgorithm::default(),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        find_file_by_checksum(Path::new(&directory), &digest, algorithm)
+            .map(|found| found.map(|path| path.to_string_lossy().to_string()))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn calculate_multiple_checksums(
+    _app_handle: AppHandle,
+    path: String,
+    algorithms: Vec<String>,
+) -> Result<HashMap<String, String>, String> {
+    let algorithms = algorithms
+        .iter()
+        .map(|name| ChecksumAlgorithm::from_name(name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    run_blocking_with_timeout(move || compute_file_checksums(Path::new(&path), &algorithms))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn is_download_complete(
+    target_path: String,
+    expected_size: u64,
+    expected_checksum: Option<String>,
+    algorithm: Option<String>,
+) -> Result<bool, String> {
+    let algorithm = match algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(&name).map_err(|e| e.to_string())?,
+        None => ChecksumAlgorithm::default(),
+    };
+    run_blocking_with_timeout(move || {
+        download_is_complete(
+            Path::new(&target_path),
+            Some(expected_size),
+            expected_checksum.as_deref(),
+            algorithm,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn verify_download_size(path: String, expected_size: u64) -> Result<(), String> {
+    run_blocking_with_timeout(move || check_download_size(Path::new(&path), expected_size))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn verify_zip_integrity(path: String) -> Result<ZipReport, String> {
+    run_blocking_with_timeout(move || inspect_zip(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Copy a large local file, resuming an interrupted copy, and emit `copy-progress`
+#[tauri::command]
+pub async fn copy_file(
+    app_handle: AppHandle,
+    source: String,
+    destination: String,
+    task_id: Option<String>,
+) -> Result<CopyOutcome, String> {
+    let task_id = task_id.unwrap_or_else(|| destination.clone());
+    tokio::task::spawn_blocking(move || {
+        let started = std::time::Instant::now();
+        copy_file_resumable(Path::new(&source), Path::new(&destination), |copied, total| {
+            let elapsed = started.elapsed().as_secs_f64();
+            let speed = if elapsed > 0.0 { copied as f64 / elapsed } else { 0.0 };
+            let progress = DownloadProgress::new(task_id.clone(), copied, total, speed);
+            let _ = app_handle.emit("copy-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn link_or_copy(
+    _app_handle: AppHandle,
+    source: String,
+    destination: String,
+) -> Result<LinkMethod, String> {
+    link_or_copy_file(Path::new(&source), Path::new(&destination)).map_err(|e| e.to_string())
+}
+
+/// Largest file that may cross the bridge as a single base64 string
+pub const MAX_BASE64_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// Raw bytes encoded per step; a multiple of 3 so chunks join without padding
+const BASE64_ENCODE_CHUNK: usize = 3 * 16 * 1024;
+/// Encoded characters decoded per step; a multiple of 4 for the same reason
+const BASE64_DECODE_CHUNK: usize = 4 * 16 * 1024;
+
+/// Base64-encode a file chunk by chunk into a single preallocated string
+pub fn encode_file_to_base64(path: &Path, limit: u64) -> Result<String, DownloadError> {
+    use base64::Engine as _;
+    use std::io::Read;
+
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    if size > limit {
+        return Err(DownloadError::FileTooLarge { size, limit });
+    }
+
+    let mut encoded = String::with_capacity(size.div_ceil(3) as usize * 4);
+    let mut buffer = vec![0u8; BASE64_ENCODE_CHUNK];
+    loop {
+        // Fill the whole buffer so only the final chunk can end in padding
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let read = file.read(&mut buffer[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        if filled == 0 {
+            break;
+        }
+        base64::engine::general_purpose::STANDARD.encode_string(&buffer[..filled], &mut encoded);
+        if filled < buffer.len() {
+            break;
+        }
+    }
+    Ok(encoded)
+}
+
+/// Decode base64 `data` chunk by chunk into a temp file beside `path`, renamed over it
+/// only once all of it decoded, so bad data leaves an existing file untouched
+pub fn decode_base64_to_file(path: &Path, data: &str, limit: u64) -> Result<u64, DownloadError> {
+    use base64::Engine as _;
+    use std::io::Write;
+
+    let data = data.trim();
+    let decoded_size = (data.len() as u64 / 4) * 3;
+    if decoded_size > limit {
+        return Err(DownloadError::FileTooLarge {
+            size: decoded_size,
+            limit,
+        });
+    }
+
+    let temp_path = temp_path_for(path);
+    let write_all = || -> Result<u64, DownloadError> {
+        let mut file = fs::File::create(&temp_path)?;
+        let mut written = 0u64;
+        for chunk in data.as_bytes().chunks(BASE64_DECODE_CHUNK) {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(chunk)
+                .map_err(|e| DownloadError::InvalidBase64(e.to_string()))?;
+            file.write_all(&bytes)?;
+            written += bytes.len() as u64;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(written)
+    };
+
+    write_all().inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+/// File extensions for common `Content-Type` values
+const MIME_EXTENSIONS: &[(&str, &str)] = &[
+    ("application/gzip", "gz"),
+    ("application/json", "json"),
+    ("application/pdf", "pdf"),
+    ("application/x-tar", "tar"),
+    ("application/xml", "xml"),
+    ("application/zip", "zip"),
+    ("audio/mpeg", "mp3"),
+    ("image/gif", "gif"),
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/svg+xml", "svg"),
+    ("image/webp", "webp"),
+    ("text/csv", "csv"),
+    ("text/html", "html"),
+    ("text/markdown", "md"),
+    ("text/plain", "txt"),
+    ("text/xml", "xml"),
+    ("video/mp4", "mp4"),
+];
+
+/// Extension for `mime`, ignoring parameters such as `; charset=utf-8`. Generic types
+/// like `application/octet-stream` have none.
+pub fn suggest_extension_for_mime(mime: &str) -> Option<String> {
+    let essence = mime.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    MIME_EXTENSIONS
+        .iter()
+        .find(|(known, _)| *known == essence)
+        .map(|(_, extension)| extension.to_string())
+}
+
+/// `target` with the extension for `mime` appended, unless it already has an extension
+/// or the type has no known one. When a file already has the extended name, a free
+/// `name (N).ext` next to it is used instead so it isn't overwritten.
+pub fn with_mime_extension(target: &Path, mime: &str) -> PathBuf {
+    if target.extension().is_some() {
+        return target.to_path_buf();
+    }
+    match suggest_extension_for_mime(mime) {
+        Some(extension) => {
+            unique_filename(&target.with_extension(extension), CASE_INSENSITIVE_FILESYSTEM)
+        }
+        None => target.to_path_buf(),
+    }
+}
+
+/// Every name `with_mime_extension` could give `target` without a collision: `target`
+/// itself and, when it has no extension, `target` with each known one
+pub fn mime_extension_candidates(target: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![target.to_path_buf()];
+    if target.extension().is_none() {
+        for (_, extension) in MIME_EXTENSIONS {
+            let candidate = target.with_extension(extension);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// Atomically create an empty `path` to reserve its name. Returns `false`, leaving the
+/// file untouched, when something already exists there.
+pub fn create_placeholder(path: &Path) -> Result<bool, DownloadError> {
+    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// First `max_bytes` of `path`, or the whole file when it is shorter
+pub async fn read_head(path: &Path, max_bytes: u64) -> Result<Vec<u8>, DownloadError> {
+    use tokio::io::AsyncReadExt;
+
+    let file = File::open(path).await?;
+    let mut buffer = Vec::new();
+    file.take(max_bytes).read_to_end(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// Bytes from the start of a file that `detect_text_format` inspects
+const TEXT_FORMAT_SAMPLE_LEN: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Mixed,
+    /// No line breaks in the sample
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextFormat {
+    /// "utf-8", "utf-16le" or "utf-16be", or "unknown" for content that is neither
+    /// valid UTF-8 nor marked by a BOM
+    pub encoding: String,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+/// Classify the line breaks in a sequence of code units. A lone `\r` is not counted.
+fn line_ending_of(units: impl Iterator<Item = u16>) -> LineEnding {
+    let (mut lf, mut crlf) = (0usize, 0usize);
+    let mut previous = 0u16;
+    for unit in units {
+        if unit == u16::from(b'\n') {
+            if previous == u16::from(b'\r') {
+                crlf += 1;
+            } else {
+                lf += 1;
+            }
+        }
+        previous = unit;
+    }
+    match (lf, crlf) {
+        (0, 0) => LineEnding::None,
+        (_, 0) => LineEnding::Lf,
+        (0, _) => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Encoding, BOM and line-ending style of `sample`, the head of a text file
+pub fn text_format_of(sample: &[u8]) -> TextFormat {
+    let utf16 = |body: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        line_ending_of(body.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])))
+    };
+    let (encoding, bom, line_ending) = if let Some(body) = sample.strip_prefix(b"\xEF\xBB\xBF") {
+        ("utf-8", true, line_ending_of(body.iter().map(|&byte| u16::from(byte))))
+    } else if let Some(body) = sample.strip_prefix(b"\xFF\xFE") {
+        ("utf-16le", true, utf16(body, u16::from_le_bytes))
+    } else if let Some(body) = sample.strip_prefix(b"\xFE\xFF") {
+        ("utf-16be", true, utf16(body, u16::from_be_bytes))
+    } else {
+        // The sample may end part-way through a character, which doesn't make it invalid
+        let valid = match std::str::from_utf8(sample) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none(),
+        };
+        let encoding = if valid { "utf-8" } else { "unknown" };
+        (encoding, false, line_ending_of(sample.iter().map(|&byte| u16::from(byte))))
+    };
+    TextFormat {
+        encoding: encoding.to_string(),
+        bom,
+        line_ending,
+    }
+}
+
+/// `text_format_of` the first `TEXT_FORMAT_SAMPLE_LEN` bytes of `path`
+pub async fn detect_text_format_at(path: &Path) -> Result<TextFormat, DownloadError> {
+    Ok(text_format_of(&read_head(path, TEXT_FORMAT_SAMPLE_LEN).await?))
+}
+
+/// Last `max_bytes` of `path`, or the whole file when it is shorter
+pub async fn read_tail(path: &Path, max_bytes: u64) -> Result<Vec<u8>, DownloadError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+    let mut file = File::open(path).await?;
+    let file_size = file.metadata().await?.len();
+    file.seek(SeekFrom::Start(file_size.saturating_sub(max_bytes))).await?;
+
+    // Bounded by max_bytes in case the file grows while we read
+    let mut buffer = Vec::new();
+    file.take(max_bytes).read_to_end(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// Heuristic check for an active writer: samples the size and mtime of `path` twice,
+/// `settle` apart, and reports stable only if neither changed. A writer that pauses for
+/// longer than `settle` is not detected, so pick a window above its expected gaps.
+pub async fn file_is_stable(path: &Path, settle: Duration) -> Result<bool, DownloadError> {
+    let before = tokio::fs::metadata(path).await?;
+    tokio::time::sleep(settle).await;
+    let after = tokio::fs::metadata(path).await?;
+    Ok(before.len() == after.len() && before.modified()? == after.modified()?)
+}
+
+/// Read each `(offset, len)` range of `path` in order through one open handle.
+/// Ranges starting past the end of the file come back empty; ones crossing it are cut short.
+pub async fn read_ranges(path: &Path, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>, DownloadError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+    let mut file = File::open(path).await?;
+    let file_size = file.metadata().await?.len();
+    let mut chunks = Vec::with_capacity(ranges.len());
+    for &(offset, len) in ranges {
+        let mut buffer = Vec::new();
+        if offset < file_size {
+            file.seek(SeekFrom::Start(offset)).await?;
+            (&mut file).take(len as u64).read_to_end(&mut buffer).await?;
+        }
+        chunks.push(buffer);
+    }
+    Ok(chunks)
+}
+
+/// Write all of `data`, yielding to the runtime after every `yield_every_bytes` so a
+/// large write doesn't starve other tasks. `None` or zero writes it in one go.
+pub async fn write_all_yielding<W>(
+    writer: &mut W,
+    data: &[u8],
+    yield_every_bytes: Option<usize>,
+) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let Some(step) = yield_every_bytes.filter(|&step| step > 0) else {
+        return writer.write_all(data).await;
+    };
+    for slice in data.chunks(step) {
+        writer.write_all(slice).await?;
+        tokio::task::yield_now().await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn write_file_chunk(
+    _app_handle: AppHandle,
+    path: String,
+    data: Vec<u8>,
+    append: bool,
+    yield_every_bytes: Option<usize>,
+) -> Result<(), String> {
+    use tokio::fs::OpenOptions;
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    write_all_yielding(&mut file, &data, yield_every_bytes)
+        .await
+        .map_err(|e| format!("Failed to write data: {}", e))?;
+
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to flush file: {}", e))?;
+
+    // Keeps a `begin_file_write` mark from being taken as abandoned
+    update_write_marks(Path::new(&path), |marks| {
+        if marks.frontend > 0 {
+            marks.frontend_activity = Some(Instant::now());
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn read_file_chunk(
+    _app_handle: AppHandle,
+    path: String,
+    offset: u64,
+    length: usize,
+) -> Result<Vec<u8>, String> {
+    read_chunk(Path::new(&path), offset, length)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Mark `path` as being written by a download driven through `write_file_chunk`, so
+/// `read_file_chunk` refuses it until `finish_file_write`. Call that on failure too:
+/// until then only `ABANDONED_WRITE_TIMEOUT` without a chunk releases the file.
+#[tauri::command]
+pub fn begin_file_write(path: String) {
+    update_write_marks(Path::new(&path), |marks| {
+        marks.frontend += 1;
+        marks.frontend_activity = Some(Instant::now());
+    });
+}
+
+#[tauri::command]
+pub fn finish_file_write(path: String) {
+    update_write_marks(Path::new(&path), |marks| {
+        marks.frontend = marks.frontend.saturating_sub(1)
+    });
+}
+
+/// Up to `length` bytes of `path` from `offset`; empty at or past the end of the file,
+/// including for zero-byte files. Fails with `WriteInProgress` while a download is
+/// still writing the file.
+pub async fn read_chunk(path: &Path, offset: u64, length: usize) -> Result<Vec<u8>, DownloadError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+    if is_write_in_progress(path) {
+        return Err(DownloadError::WriteInProgress(path.display().to_string()));
+    }
+
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    let mut buffer = vec![0u8; length];
+    let bytes_read = file.read(&mut buffer).await?;
+
+    buffer.truncate(bytes_read);
+    Ok(buffer)
+}
+
+/// Read at most `max_bytes` from the start of a file, e.g. for previews or type sniffing
+#[tauri::command]
+pub async fn read_file_head(path: String, max_bytes: u64) -> Result<Vec<u8>, String> {
+    with_timeout(command_timeout(), read_head(Path::new(&path), max_bytes))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Encoding, BOM and line endings of a text file, judged from its first 64 KiB
+#[tauri::command]
+pub async fn detect_text_format(path: String) -> Result<TextFormat, String> {
+    with_timeout(command_timeout(), detect_text_format_at(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Read at most `max_bytes` from the end of a file, e.g. for previewing logs
+#[tauri::command]
+pub async fn read_file_tail(path: String, max_bytes: u64) -> Result<Vec<u8>, String> {
+    with_timeout(command_timeout(), read_tail(Path::new(&path), max_bytes))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Whether a file looks finished, i.e. unchanged over `settle_ms`. Heuristic only.
+#[tauri::command]
+pub async fn is_file_stable(path: String, settle_ms: u64) -> Result<bool, String> {
+    file_is_stable(Path::new(&path), Duration::from_millis(settle_ms))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Read several `(offset, len)` ranges of a file in one call, e.g. for sparse verification
+#[tauri::command]
+pub async fn read_file_ranges(path: String, ranges: Vec<(u64, usize)>) -> Result<Vec<Vec<u8>>, String> {
+    with_timeout(command_timeout(), read_ranges(Path::new(&path), &ranges))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn calculate_file_checksum_mmap(
+    path: String,
+    algorithm: Option<String>,
+) -> Result<String, String> {
+    let algorithm = match algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(&name).map_err(|e| e.to_string())?,
+        None => ChecksumAlgorithm::default(),
+    };
+    run_blocking_with_timeout(move || compute_file_checksum_mmap(Path::new(&path), algorithm))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn encode_file_base64(path: String) -> Result<String, String> {
+    run_blocking_with_timeout(move || encode_file_to_base64(Path::new(&path), MAX_BASE64_FILE_SIZE))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn write_base64_to_file(path: String, data: String) -> Result<u64, String> {
+    run_blocking_with_timeout(move || decode_base64_to_file(Path::new(&path), &data, MAX_BASE64_FILE_SIZE))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Per-block SHA-256 digests of `path`, for later incremental verification
+#[tauri::command]
+pub async fn calculate_block_checksums(path: String, block_size: u64) -> Result<Vec<String>, String> {
+    run_blocking_with_timeout(move || block_checksums(Path::new(&path), block_size))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Indices of the blocks of `path` that no longer match `previous`
+#[tauri::command]
+pub async fn compare_block_checksums(
+    path: String,
+    previous: Vec<String>,
+    block_size: u64,
+) -> Result<Vec<usize>, String> {
+    run_blocking_with_timeout(move || changed_blocks(Path::new(&path), &previous, block_size))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// SHA-256 of `path`. With a `task_id`, `cancel(task_id)` stops the hashing early.
+#[tauri::command]
+pub async fn calculate_file_checksum(
+    _app_handle: AppHandle,
+    path: String,
+    task_id: Option<String>,
+) -> Result<String, String> {
+    let guard = task_id.as_deref().map(CancellationGuard::register);
+    run_blocking_with_timeout(move || {
+        let with_context = |context: &str, e: std::io::Error| {
+            DownloadError::Io(std::io::Error::new(e.kind(), format!("{}: {}", context, e)))
+        };
+        let file = fs::File::open(&path).map_err(|e| with_context("Failed to open file", e))?;
+
+        let algorithm = ChecksumAlgorithm::Sha256;
+        let not_cancelled = AtomicBool::new(false);
+        let cancelled = guard.as_ref().map_or(&not_cancelled, |guard| guard.flag());
+        let mut digests = checksums_from_reader(file, &[algorithm], cancelled).map_err(|e| match e {
+            DownloadError::Io(e) => with_context("Failed to read file", e),
+            other => other,
+        })?;
+        Ok(digests.remove(algorithm.name()).unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cancel(task_id: String) -> bool {
+    cancel_task(&task_id)
+}
+
+/// Cancel a download submitted with `queue_download_to_file`, whether it is still
+/// waiting for a slot or already running
+#[tauri::command]
+pub fn cancel_queued_download(queue: State<'_, DownloadQueue>, task_id: String) -> bool {
+    queue.cancel_download(&task_id)
+}
+
+#[tauri::command]
+pub fn get_queued_download_state(
+    queue: State<'_, DownloadQueue>,
+    task_id: String,
+) -> Option<QueuedTaskState> {
+    queue.state(&task_id)
+}
+
+#[tauri::command]
+pub async fn get_file_metadata(
+    _app_handle: AppHandle,
+    path: String,
+    follow_symlinks: Option<bool>,
+) -> Result<FileMetadata, String> {
+    run_blocking_with_timeout(move || {
+        read_file_metadata(Path::new(&path), follow_symlinks.unwrap_or(true))
+            .map_err(|e| DownloadError::Io(std::io::Error::other(e)))
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Read metadata for `path`. When `follow_symlinks` is false a symlink is
+/// described itself (via `symlink_metadata`) rather than its target.
+pub fn read_file_metadata(path: &Path, follow_symlinks: bool) -> Result<FileMetadata, String> {
+    use std::time::SystemTime;
+
+    let link_metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+
+    let metadata = if follow_symlinks && is_symlink {
+        std::fs::metadata(path)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+    } else {
+        link_metadata
+    };
+
+    let modified = metadata.modified()
+        .map_err(|e| format!("Failed to get modification time: {}", e))?;
+
+    let modified_timestamp = modified.duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(|e| format!("Failed to convert time: {}", e))?
+        .as_secs();
+
+    let symlink_target = if is_symlink {
+        std::fs::read_link(path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
+
+    Ok(FileMetadata {
+        size: metadata.len(),
+        modified: modified_timestamp,
+        is_file: metadata.is_file(),
+        is_dir: metadata.is_dir(),
+        is_symlink,
+        symlink_target,
+    })
+}
+
+#[derive(serde::Serialize)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified: u64,
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+}
+
+/// Everything a verification panel shows for one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationInfo {
+    pub size: u64,
+    /// Unix seconds of the last modification
+    pub modified: u64,
+    pub algorithm: String,
+    pub checksum: String,
+}
+
+/// Stat `path` and stream it once through `algorithm`. Symlinks are followed.
+pub fn read_verification_info(
+    path: &Path,
+    algorithm: ChecksumAlgorithm,
+) -> Result<VerificationInfo, DownloadError> {
+    let metadata = read_file_metadata(path, true).map_err(|e| DownloadError::Io(std::io::Error::other(e)))?;
+    if !metadata.is_file {
+        return Err(DownloadError::InvalidPath(format!("Not a file: {}", path.display())));
+    }
+
+    Ok(VerificationInfo {
+        size: metadata.size,
+        modified: metadata.modified,
+        algorithm: algorithm.name().to_string(),
+        checksum: compute_file_checksum(path, algorithm)?,
+    })
+}
+
+/// Size, modification time and digest of a file in one call, defaulting to sha256
+#[tauri::command]
+pub async fn get_file_verification_info(
+    path: String,
+    algorithm: Option<String>,
+) -> Result<VerificationInfo, String> {
+    let algorithm = match algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(&name).map_err(|e| e.to_string())?,
+        None => ChecksumAlgorithm::default(),
+    };
+    run_blocking_with_timeout(move || read_verification_info(Path::new(&path), algorithm))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Maximum number of files deleted concurrently by `delete_files`
+const DELETE_CONCURRENCY: usize = 4;
+
+fn delete_single_file<F>(path: &Path, secure: bool, on_progress: F) -> Result<(), DownloadError>
+where
+    F: FnMut(SecureDeleteProgress),
+{
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        return Err(DownloadError::InvalidPath(format!(
+            "Not a file: {}",
+            path.display()
+        )));
+    }
+
+    if secure && metadata.is_file() {
+        secure_remove_file_with_progress(path, on_progress)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Delete every path with bounded parallelism, reporting success per path in input order.
+/// A failure on one path does not abort the rest of the batch. Secure deletes report
+/// overwrite progress through `on_progress`.
+pub async fn delete_files_batch<F>(
+    paths: Vec<String>,
+    secure: bool,
+    on_progress: F,
+) -> Vec<(String, bool)>
+where
+    F: Fn(SecureDeleteProgress) + Send + Sync + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(DELETE_CONCURRENCY));
+    let on_progress = Arc::new(on_progress);
+    let mut tasks = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let semaphore = Arc::clone(&semaphore);
+        let on_progress = Arc::clone(&on_progress);
+        let target = PathBuf::from(&path);
+        let task = tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            tokio::task::spawn_blocking(move || {
+                delete_single_file(&target, secure, |progress| on_progress(progress)).is_ok()
+            })
+            .await
+            .unwrap_or(false)
+        });
+        tasks.push((path, task));
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (path, task) in tasks {
+        let deleted = task.await.unwrap_or(false);
+        results.push((path, deleted));
+    }
+    results
+}
+
+#[tauri::command]
+pub async fn delete_files(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+    secure: bool,
+) -> Result<Vec<(String, bool)>, String> {
+    let results = delete_files_batch(paths, secure, move |progress| {
+        let _ = app_handle.emit("secure-delete-progress", progress);
+    })
+    .await;
+    Ok(results)
+}
+
+const DUPLICATE_HASH_CONCURRENCY: usize = 4;
+
+/// A file `find_duplicates` couldn't read, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnreadableFile {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of `find_duplicates`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    /// Groups of two or more identical files, each sorted by path
+    pub groups: Vec<Vec<String>>,
+    /// Files left out of the comparison because they couldn't be inspected or read
+    pub unreadable: Vec<UnreadableFile>,
+}
+
+/// Group the files directly inside `directory` by content. Files are bucketed by size
+/// first so only sizes shared by several files get hashed. A file that can't be read
+/// is reported in `unreadable` without stopping the rest.
+pub async fn find_duplicates(directory: &Path) -> Result<DuplicateReport, DownloadError> {
+    find_duplicates_with(directory, |path| indexed_checksum(path, ChecksumAlgorithm::Sha256)).await
+}
+
+async fn find_duplicates_with<H>(directory: &Path, hash: H) -> Result<DuplicateReport, DownloadError>
+where
+    H: Fn(&Path) -> Result<String, DownloadError> + Send + Sync + 'static,
+{
+    let mut unreadable = Vec::new();
+    let mut report_unreadable = |path: &Path, error: &dyn std::fmt::Display| {
+        unreadable.push(UnreadableFile {
+            path: path.to_string_lossy().to_string(),
+            error: error.to_string(),
+        });
+    };
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Skipping unreadable entry in {}: {}", directory.display(), e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_file() => {}
+            Ok(_) => continue,
+            Err(e) => {
+                report_unreadable(&path, &e);
+                continue;
+            }
+        }
+        match entry.metadata() {
+            Ok(metadata) => by_size.entry(metadata.len()).or_default().push(path),
+            Err(e) => report_unreadable(&path, &e),
+        }
+    }
+
+    let semaphore = Arc::new(Semaphore::new(DUPLICATE_HASH_CONCURRENCY));
+    let hash = Arc::new(hash);
+    let mut tasks = Vec::new();
+    for (size, paths) in by_size.into_iter().filter(|(_, paths)| paths.len() > 1) {
+        for path in paths {
+            let semaphore = Arc::clone(&semaphore);
+            let hash = Arc::clone(&hash);
+            let hash_path = path.clone();
+            let task = tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let digest = tokio::task::spawn_blocking(move || hash(&hash_path))
+                    .await
+                    .map_err(|e| DownloadError::Io(std::io::Error::other(e)))??;
+                Ok::<_, DownloadError>((size, digest))
+            });
+            tasks.push((path, task));
+        }
+    }
+
+    let mut by_content: HashMap<(u64, String), Vec<String>> = HashMap::new();
+    for (path, task) in tasks {
+        let outcome = task
+            .await
+            .unwrap_or_else(|e| Err(DownloadError::Io(std::io::Error::other(e))));
+        match outcome {
+            Ok(key) => by_content
+                .entry(key)
+                .or_default()
+                .push(path.to_string_lossy().to_string()),
+            Err(e) => report_unreadable(&path, &e),
+        }
+    }
+
+    let mut groups: Vec<Vec<String>> = by_content
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|mut paths| {
+            paths.sort();
+            paths
+        })
+        .collect();
+    groups.sort();
+    unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(DuplicateReport { groups, unreadable })
+}
+
+#[tauri::command]
+pub async fn cleanup_temp_files(
+    directory: String,
+    max_age_secs: Option<u64>,
+) -> Result<Vec<String>, String> {
+    let max_age = max_age_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TEMP_FILE_MAX_AGE);
+    run_blocking_with_timeout(move || remove_stale_temp_files(Path::new(&directory), max_age))
+        .await
+        .map(|removed| {
+            removed
+                .into_iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Change the temp file prefix and keep it for later launches
+#[tauri::command]
+pub async fn set_temp_prefix(app_handle: AppHandle, prefix: String) -> Result<(), String> {
+    let config_dir = app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+    save_temp_file_prefix(&config_dir, &prefix).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn find_duplicate_files(directory: String) -> Result<DuplicateReport, String> {
+    find_duplicates(Path::new(&directory))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Default cap on the read buffers `checksum_files_batch` holds at once
+pub const DEFAULT_CHECKSUM_MEMORY_BUDGET: u64 = 64 * 1024 * 1024;
+/// Read buffer per file in a checksum batch unless the caller picks one
+pub const DEFAULT_CHECKSUM_BUFFER_SIZE: usize = 1024 * 1024;
+/// Largest read buffer a checksum batch allocates per file, whatever the caller asks for
+pub const MAX_CHECKSUM_BUFFER_SIZE: usize = 16 * 1024 * 1024;
+/// Files hashed at once however large the budget, to spare the disk
+const MAX_CHECKSUM_BATCH_CONCURRENCY: usize = 8;
+/// Permits of `checksum_memory`. A file takes the share of them its buffer is of the
+/// budget, so concurrent batches together stay within it.
+const CHECKSUM_MEMORY_SHARES: u32 = 1024;
+
+static CHECKSUM_MEMORY_BUDGET: AtomicU64 = AtomicU64::new(DEFAULT_CHECKSUM_MEMORY_BUDGET);
+
+pub fn checksum_memory_budget() -> u64 {
+    CHECKSUM_MEMORY_BUDGET.load(Ordering::Relaxed)
+}
+
+pub fn set_checksum_memory_budget(bytes: u64) {
+    CHECKSUM_MEMORY_BUDGET.store(bytes, Ordering::Relaxed);
+}
+
+/// How many files a batch may hash at once so `buffer_size * in_flight` stays within
+/// `budget`. At least one, so a buffer larger than the budget still makes progress.
+pub fn checksum_batch_concurrency(buffer_size: usize, budget: u64) -> usize {
+    let per_file = (buffer_size as u64).max(1);
+    ((budget / per_file).min(MAX_CHECKSUM_BATCH_CONCURRENCY as u64) as usize).max(1)
+}
+
+/// Shares of the checksum memory budget, held by every file being hashed in any batch
+fn checksum_memory() -> Arc<Semaphore> {
+    static MEMORY: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    Arc::clone(MEMORY.get_or_init(|| Arc::new(Semaphore::new(CHECKSUM_MEMORY_SHARES as usize))))
+}
+
+/// Read buffer a batch actually uses: at least one byte and no more than the budget
+/// or `MAX_CHECKSUM_BUFFER_SIZE`
+fn clamp_checksum_buffer(buffer_size: usize, budget: u64) -> usize {
+    let budget = usize::try_from(budget).unwrap_or(usize::MAX);
+    buffer_size.clamp(1, budget.clamp(1, MAX_CHECKSUM_BUFFER_SIZE))
+}
+
+/// Digest of `path` read through a `buffer_size` buffer
+fn compute_file_checksum_buffered(
+    path: &Path,
+    algorithm: ChecksumAlgorithm,
+    buffer_size: usize,
+) -> Result<String, DownloadError> {
+    use std::io::Read;
+
+    let mut file = fs::File::open(path)?;
+    let mut buffer = vec![0u8; buffer_size.max(1)];
+    let mut hasher = algorithm.hasher();
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize_hex())
+}
+
+/// Run `work` on every path on blocking threads. Each file holds its share of the
+/// process-wide `checksum_memory` while it runs, so across all batches no more are in
+/// flight than `checksum_batch_concurrency` allows. Results come back in input order.
+async fn run_within_memory_budget<T, F>(
+    paths: Vec<String>,
+    buffer_size: usize,
+    budget: u64,
+    work: F,
+) -> Vec<(String, Result<T, DownloadError>)>
+where
+    F: Fn(&Path) -> Result<T, DownloadError> + Send + Sync + 'static,
+    T: Send + 'static,
+{
+    let shares = CHECKSUM_MEMORY_SHARES / checksum_batch_concurrency(buffer_size, budget) as u32;
+    let work = Arc::new(work);
+    let mut tasks = Vec::with_capacity(paths.len());
+    for path in paths {
+        let work = Arc::clone(&work);
+        let target = PathBuf::from(&path);
+        let task = tokio::spawn(async move {
+            let _permit = checksum_memory().acquire_many_owned(shares).await;
+            tokio::task::spawn_blocking(move || work(&target))
+                .await
+                .map_err(|e| DownloadError::Io(std::io::Error::other(e)))?
+        });
+        tasks.push((path, task));
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (path, task) in tasks {
+        let result = task
+            .await
+            .unwrap_or_else(|e| Err(DownloadError::Io(std::io::Error::other(e))));
+        results.push((path, result));
+    }
+    results
+}
+
+/// Outcome for one file of `checksum_files_batch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchChecksumResult {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Hash every path, keeping total buffer memory within `budget`. `buffer_size` is
+/// clamped by `clamp_checksum_buffer`. A file that fails is reported without stopping
+/// the rest.
+pub async fn checksum_files_batch(
+    paths: Vec<String>,
+    algorithm: ChecksumAlgorithm,
+    buffer_size: usize,
+    budget: u64,
+) -> Vec<BatchChecksumResult> {
+    let buffer_size = clamp_checksum_buffer(buffer_size, budget);
+    run_within_memory_budget(paths, buffer_size, budget, move |path| {
+        compute_file_checksum_buffered(path, algorithm, buffer_size)
+    })
+    .await
+    .into_iter()
+    .map(|(path, result)| match result {
+        Ok(checksum) => BatchChecksumResult { path, checksum: Some(checksum), error: None },
+        Err(e) => BatchChecksumResult { path, checksum: None, error: Some(e.to_string()) },
+    })
+    .collect()
+}
+
+/// Checksums of many files at once, within the memory budget set by
+/// `set_checksum_memory_limit`
+#[tauri::command]
+pub async fn calculate_checksums_batch(
+    paths: Vec<String>,
+    algorithm: Option<String>,
+    buffer_size: Option<usize>,
+) -> Result<Vec<BatchChecksumResult>, String> {
+    let algorithm = match algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(&name).map_err(|e| e.to_string())?,
+        None => ChecksumAlgorithm::default(),
+    };
+    let buffer_size = buffer_size.unwrap_or(DEFAULT_CHECKSUM_BUFFER_SIZE);
+    if buffer_size == 0 {
+        return Err("Buffer size must be positive".to_string());
+    }
+    Ok(checksum_files_batch(paths, algorithm, buffer_size, checksum_memory_budget()).await)
+}
+
+#[tauri::command]
+pub fn set_checksum_memory_limit(bytes: u64) -> Result<(), String> {
+    if bytes == 0 {
+        return Err("Memory limit must be positive".to_string());
+    }
+    set_checksum_memory_budget(bytes);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: u64,
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<globset::GlobSet, DownloadError> {
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::Glob::new(pattern)
+            .map_err(|e| DownloadError::Path(format!("Invalid pattern '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| DownloadError::Path(e.to_string()))
+}
+
+/// List the entries of `directory` sorted by name. Hidden entries are dropped unless
+/// `show_hidden` is set, then names must match one of `include_patterns` (when any are
+/// given) and none of `exclude_patterns`.
+pub fn list_directory_entries(
+    directory: &Path,
+    show_hidden: bool,
+    include_patterns: &[String],
+    exclude_patterns: &[String],
+) -> Result<Vec<DirectoryEntry>, DownloadError> {
+    let include = build_glob_set(include_patterns)?;
+    let exclude = build_glob_set(exclude_patterns)?;
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if !show_hidden && name.starts_with('.') {
+            continue;
+        }
+        if !include_patterns.is_empty() && !include.is_match(&name) {
+            continue;
+        }
+        if exclude.is_match(&name) {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        entries.push(DirectoryEntry {
+            name,
+            path: entry.path().to_string_lossy().to_string(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified,
+        });
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Identity of a directory for loop detection: device and inode on Unix, where hard
+/// links and bind mounts share them, and the canonical path elsewhere
+#[cfg(unix)]
+type DirectoryKey = (u64, u64);
+#[cfg(not(unix))]
+type DirectoryKey = PathBuf;
+
+fn directory_key(path: &Path, metadata: &fs::Metadata) -> Result<DirectoryKey, DownloadError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = path;
+        Ok((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        Ok(fs::canonicalize(path)?)
+    }
+}
+
+/// Every file below `root` with its metadata, following symlinks. A directory reached
+/// a second time, through a symlink cycle or a second link to it, is skipped rather
+/// than walked again, so the walk always terminates. Dangling links are ignored.
+pub fn walk_files(root: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>, DownloadError> {
+    let root_metadata = fs::metadata(root)?;
+    if !root_metadata.is_dir() {
+        return Err(DownloadError::InvalidPath(format!("Not a directory: {}", root.display())));
+    }
+
+    let mut visited = std::collections::HashSet::new();
+    visited.insert(directory_key(root, &root_metadata)?);
+    let mut pending = vec![root.to_path_buf()];
+    let mut files = Vec::new();
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if !metadata.is_dir() {
+                files.push((path, metadata));
+            } else if visited.insert(directory_key(&path, &metadata)?) {
+                pending.push(path);
+            } else {
+                log::warn!("Skipping already visited directory (symlink loop?): {}", path.display());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Total size in bytes of the files below `directory`
+pub fn directory_size(directory: &Path) -> Result<u64, DownloadError> {
+    Ok(walk_files(directory)?.iter().map(|(_, metadata)| metadata.len()).sum())
+}
+
+/// Every file below `directory`, named by its path relative to it and sorted by name
+pub fn list_files_recursive(directory: &Path) -> Result<Vec<DirectoryEntry>, DownloadError> {
+    let mut entries: Vec<DirectoryEntry> = walk_files(directory)?
+        .into_iter()
+        .map(|(path, metadata)| DirectoryEntry {
+            name: path.strip_prefix(directory).unwrap_or(&path).to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            is_dir: false,
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn get_directory_size(path: String) -> Result<u64, String> {
+    run_blocking_with_timeout(move || directory_size(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_directory_recursive(path: String) -> Result<Vec<DirectoryEntry>, String> {
+    run_blocking_with_timeout(move || list_files_recursive(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_directory(
+    _app_handle: AppHandle,
+    path: String,
+    show_hidden: Option<bool>,
+    include_patterns: Option<Vec<String>>,
+    exclude_patterns: Option<Vec<String>>,
+) -> Result<Vec<DirectoryEntry>, String> {
+    list_directory_entries(
+        Path::new(&path),
+        show_hidden.unwrap_or(false),
+        &include_patterns.unwrap_or_default(),
+        &exclude_patterns.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    // Create a fresh directory per test so parallel tests don't clobber each other
+    fn unique_test_dir(label: &str) -> PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = env::temp_dir().join(format!("s3-upload-tool-{}-{}", label, timestamp));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    struct MockDownloadManager {
+        downloads_dir: PathBuf,
+    }
+
+    impl MockDownloadManager {
+        fn new() -> Result<Self, DownloadError> {
+            let downloads_dir = env::temp_dir().join("s3-upload-tool-downloads-test");
+            if downloads_dir.exists() {
+                fs::remove_dir_all(&downloads_dir)?;
+            }
+            fs::create_dir_all(&downloads_dir)?;
+            Ok(MockDownloadManager { downloads_dir })
+        }
+
+        fn validate_download_path(&self, path: &str) -> Result<PathBuf, DownloadError> {
+            let path_buf = PathBuf::from(path);
+
+            // For testing, we'll be more lenient with path validation
+            if path_buf.is_relative() {
+                return Ok(self.downloads_dir.join(path_buf));
+            }
+
+            Ok(path_buf)
+        }
+
+        fn check_file_exists(&self, path: &PathBuf) -> bool {
+            path.exists()
+        }
+
+        fn get_default_download_path(&self, filename: &str) -> PathBuf {
+            self.downloads_dir.join(filename)
+        }
+
+        fn generate_unique_filename(&self, base_path: &PathBuf) -> PathBuf {
+            if !base_path.exists() {
+                return base_path.clone();
+            }
+
+            let parent = base_path.parent().unwrap_or_else(|| std::path::Path::new("."));
+            let stem = base_path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+            let extension = base_path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+            for i in 1..1000 {
+                let new_filename = if extension.is_empty() {
+                    format!("{} ({})", stem, i)
+                } else {
+                    format!("{} ({}).{}", stem, i, extension)
+                };
+                
+                let new_path = parent.join(new_filename);
+                if !new_path.exists() {
+                    return new_path;
+                }
+            }
+
+            // Fallback with timestamp
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            
+            let new_filename = if extension.is_empty() {
+                format!("{}-{}", stem, timestamp)
+            } else {
+                format!("{}-{}.{}", stem, timestamp, extension)
+            };
+            
+            parent.join(new_filename)
+        }
+    }
+
+    #[test]
+    fn test_validate_download_path() {
+        let manager = MockDownloadManager::new().unwrap();
+        
+        // Test relative path
+        let result = manager.validate_download_path("test.txt");
+        assert!(result.is_ok());
+        
+        // Test absolute path
+        let abs_path = manager.downloads_dir.join("test.txt");
+        let result = manager.validate_download_path(&abs_path.to_string_lossy());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_file_exists() {
+        let manager = MockDownloadManager::new().unwrap();
+        let test_file = manager.downloads_dir.join("test.txt");
+        
+        // File doesn't exist initially
+        assert!(!manager.check_file_exists(&test_file));
+        
+        // Create file
+        fs::write(&test_file, "test content").unwrap();
+        
+        // File exists now
+        assert!(manager.check_file_exists(&test_file));
+    }
+
+    #[test]
+    fn test_default_download_path() {
+        let manager = MockDownloadManager::new().unwrap();
+        let filename = "test.txt";
+        let expected_path = manager.downloads_dir.join(filename);
+        let actual_path = manager.get_default_download_path(filename);
+        
+        assert_eq!(actual_path, expected_path);
+    }
+
+    #[test]
+    fn test_truncate_filename_preserving_extension() {
+        let long_name = format!("{}.tar.gz", "報告書-ü".repeat(60));
+        let truncated = truncate_filename_preserving_extension(&long_name, MAX_FILENAME_BYTES);
+        assert!(truncated.len() <= MAX_FILENAME_BYTES);
+        assert!(truncated.ends_with(".gz"));
+        assert!(truncated.starts_with("報告書-ü"));
+
+        // Names differing only past the cut still get distinct results
+        let other = long_name.replacen("ü.tar", "x.tar", 1);
+        assert_ne!(truncate_filename_preserving_extension(&other, MAX_FILENAME_BYTES), truncated);
+
+        assert_eq!(truncate_filename_preserving_extension("short.txt", MAX_FILENAME_BYTES), "short.txt");
+        assert!(truncate_filename_preserving_extension(&"a".repeat(300), 20).len() <= 20);
+        assert!(truncate_filename_preserving_extension("name.verylongextension", 12).len() <= 12);
+
+        let manager = DownloadManager::from_dir(unique_test_dir("truncate-name"));
+        let path = manager.get_default_download_path(&long_name);
+        assert_eq!(path.file_name().unwrap().to_str().unwrap(), truncated);
+    }
+
+    #[test]
+    fn test_generate_unique_filename() {
+        let manager = MockDownloadManager::new().unwrap();
+        let base_path = manager.downloads_dir.join("test.txt");
+        
+        // First call should return the original path
+        let unique_path = manager.generate_unique_filename(&base_path);
+        assert_eq!(unique_path, base_path);
+        
+        // Create the file
+        fs::write(&base_path, "test content").unwrap();
+        
+        // Second call should return a unique path
+        let unique_path = manager.generate_unique_filename(&base_path);
+        assert_ne!(unique_path, base_path);
+        assert!(unique_path.to_string_lossy().contains("test (1).txt"));
+    }
+
+    #[test]
+    fn test_generate_unique_filename_no_extension() {
+        let manager = MockDownloadManager::new().unwrap();
+        let base_path = manager.downloads_dir.join("test");
+        
+        // Create the file
+        fs::write(&base_path, "test content").unwrap();
+        
+        // Should generate unique name without extension
+        let unique_path = manager.generate_unique_filename(&base_path);
+        assert_ne!(unique_path, base_path);
+        assert!(unique_path.to_string_lossy().contains("test (1)"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_files_batch_reports_per_file_results() {
+        let batch_dir = unique_test_dir("delete-batch");
+
+        let plain = batch_dir.join("plain.txt");
+        let secure = batch_dir.join("secure.bin");
+        let missing = batch_dir.join("missing.txt");
+        let directory = batch_dir.join("nested");
+        fs::write(&plain, "plain content").unwrap();
+        fs::write(&secure, vec![7u8; 4096]).unwrap();
+        fs::create_dir_all(&directory).unwrap();
+
+        let paths: Vec<String> = [&plain, &secure, &missing, &directory]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+
+        let results = delete_files_batch(paths.clone(), true, |_| {}).await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], (paths[0].clone(), true));
+        assert_eq!(results[1], (paths[1].clone(), true));
+        assert_eq!(results[2], (paths[2].clone(), false));
+        assert_eq!(results[3], (paths[3].clone(), false));
+        assert!(!plain.exists());
+        assert!(!secure.exists());
+        assert!(directory.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_available_inodes_on_temp_dir() {
+        let dir = unique_test_dir("free-inodes");
+        assert!(available_inodes(&dir).unwrap() > 0);
+        // Paths that don't exist yet are checked on their nearest existing parent
+        assert!(available_inodes(&dir.join("not/yet/created")).unwrap() > 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filesystem_max_file_size_only_limits_fat32() {
+        let path = Path::new("/mnt/drive/big.iso");
+        let five_gib = 5 * 1024 * 1024 * 1024;
+        let detected = |name: &'static str| move |_: &Path| Ok(Some(name.to_string()));
+
+        assert!(!fits_filesystem_max_file_size_with(path, five_gib, detected("msdos")).unwrap());
+        assert!(!fits_filesystem_max_file_size_with(path, FAT32_MAX_FILE_SIZE + 1, detected("vfat")).unwrap());
+        assert!(fits_filesystem_max_file_size_with(path, FAT32_MAX_FILE_SIZE, detected("msdos")).unwrap());
+        assert!(fits_filesystem_max_file_size_with(path, five_gib, detected("exfat")).unwrap());
+        assert!(fits_filesystem_max_file_size_with(path, five_gib, detected("ext4")).unwrap());
+        assert!(fits_filesystem_max_file_size_with(path, five_gib, |_| Ok(None)).unwrap());
+    }
+
+    #[test]
+    fn test_changed_blocks_reports_only_modified_block() {
+        let dir = unique_test_dir("block-checksums");
+        let path = dir.join("data.bin");
+        let mut contents = vec![7u8; 4096 * 3 + 100];
+        fs::write(&path, &contents).unwrap();
+
+        let before = block_checksums(&path, 4096).unwrap();
+        assert_eq!(before.len(), 4);
+        assert_eq!(before[0], before[1]);
+        assert!(changed_blocks(&path, &before, 4096).unwrap().is_empty());
+
+        contents[4096 * 2 + 10] = 8;
+        fs::write(&path, &contents).unwrap();
+        assert_eq!(changed_blocks(&path, &before, 4096).unwrap(), vec![2]);
+
+        contents.truncate(4096);
+        fs::write(&path, &contents).unwrap();
+        assert_eq!(changed_blocks(&path, &before, 4096).unwrap(), vec![1, 2, 3]);
+
+        assert!(matches!(block_checksums(&path, 0), Err(DownloadError::InvalidBlockSize(0))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_detect_text_format() {
+        let expected = |encoding: &str, bom, line_ending| TextFormat {
+            encoding: encoding.to_string(),
+            bom,
+            line_ending,
+        };
+        assert_eq!(text_format_of(b"one\ntwo\n"), expected("utf-8", false, LineEnding::Lf));
+        assert_eq!(text_format_of(b"one\r\ntwo\r\n"), expected("utf-8", false, LineEnding::Crlf));
+        assert_eq!(text_format_of(b"one\r\ntwo\nthree"), expected("utf-8", false, LineEnding::Mixed));
+        assert_eq!(text_format_of(b"\xEF\xBB\xBFone\r\ntwo\r\n"), expected("utf-8", true, LineEnding::Crlf));
+        assert_eq!(text_format_of(b"\xEF\xBB\xBFone\ntwo\r\n"), expected("utf-8", true, LineEnding::Mixed));
+        assert_eq!(text_format_of(b"single line"), expected("utf-8", false, LineEnding::None));
+        assert_eq!(text_format_of(b"\xFF\xFEa\0\r\0\n\0"), expected("utf-16le", true, LineEnding::Crlf));
+        assert_eq!(text_format_of(b"\xFE\xFF\0a\0\n"), expected("utf-16be", true, LineEnding::Lf));
+        // "é" cut in half at the end of the sample is still UTF-8
+        assert_eq!(text_format_of(b"caf\xC3").encoding, "utf-8");
+        assert_eq!(text_format_of(b"\xC3\x28\n").encoding, "unknown");
+
+        let dir = unique_test_dir("text-format");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "\u{feff}first\r\nsecond\r\n").unwrap();
+        assert_eq!(detect_text_format_at(&path).await.unwrap(), expected("utf-8", true, LineEnding::Crlf));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// SHA-256 and MD5 of zero bytes
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const EMPTY_MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+    #[tokio::test]
+    async fn test_empty_file_handling() {
+        let dir = unique_test_dir("empty-file");
+        let path = dir.join("empty.bin");
+        fs::write(&path, b"").unwrap();
+
+        assert_eq!(compute_file_checksum(&path, ChecksumAlgorithm::Sha256).unwrap(), EMPTY_SHA256);
+        assert_eq!(compute_file_checksum(&path, ChecksumAlgorithm::Md5).unwrap(), EMPTY_MD5);
+        assert_eq!(compute_file_checksum_mmap(&path, ChecksumAlgorithm::Sha256).unwrap(), EMPTY_SHA256);
+        let digests =
+            compute_file_checksums(&path, &[ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5]).unwrap();
+        assert_eq!(digests["sha256"], EMPTY_SHA256);
+        assert_eq!(digests["md5"], EMPTY_MD5);
+
+        assert!(read_chunk(&path, 0, 1024).await.unwrap().is_empty());
+        assert!(read_chunk(&path, 10, 1024).await.unwrap().is_empty());
+        assert!(read_head(&path, 1024).await.unwrap().is_empty());
+        assert!(read_tail(&path, 1024).await.unwrap().is_empty());
+        assert_eq!(read_ranges(&path, &[(0, 16)]).await.unwrap(), vec![Vec::<u8>::new()]);
+        assert_eq!(encode_file_to_base64(&path, MAX_BASE64_FILE_SIZE).unwrap(), "");
+
+        let metadata = read_file_metadata(&path, true).unwrap();
+        assert_eq!(metadata.size, 0);
+        assert!(metadata.is_file);
+        let info = read_verification_info(&path, ChecksumAlgorithm::Sha256).unwrap();
+        assert_eq!((info.size, info.checksum.as_str()), (0, EMPTY_SHA256));
+
+        check_download_size(&path, 0).unwrap();
+        assert!(download_is_complete(&path, Some(0), Some(EMPTY_SHA256), ChecksumAlgorithm::Sha256).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verification_info_matches_stat_and_digest() {
+        use sha2::Digest;
+
+        let dir = unique_test_dir("verification-info");
+        let path = dir.join("verify.bin");
+        let contents = b"contents shown in the verification panel";
+        fs::write(&path, contents).unwrap();
+
+        let info = read_verification_info(&path, ChecksumAlgorithm::Sha256).unwrap();
+        assert_eq!(info.size, fs::metadata(&path).unwrap().len());
+        assert_eq!(info.algorithm, "sha256");
+        assert_eq!(info.checksum, format!("{:x}", sha2::Sha256::digest(contents)));
+
+        let md5_info = read_verification_info(&path, ChecksumAlgorithm::Md5).unwrap();
+        assert_eq!(md5_info.checksum, format!("{:x}", md5::Md5::digest(contents)));
+        assert!(matches!(
+            read_verification_info(&dir, ChecksumAlgorithm::Sha256),
+            Err(DownloadError::InvalidPath(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_metadata_symlink_follow_and_no_follow() {
+        let dir = unique_test_dir("symlink-metadata");
+        let target = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        fs::write(&target, "symlink target content").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let followed = read_file_metadata(&link, true).unwrap();
+        assert!(followed.is_file);
+        assert!(followed.is_symlink);
+        assert_eq!(followed.size, "symlink target content".len() as u64);
+
+        let not_followed = read_file_metadata(&link, false).unwrap();
+        assert!(not_followed.is_symlink);
+        assert!(!not_followed.is_file);
+        assert_eq!(
+            not_followed.symlink_target,
+            Some(target.to_string_lossy().to_string())
+        );
+
+        let regular = read_file_metadata(&target, false).unwrap();
+        assert!(!regular.is_symlink);
+        assert!(regular.symlink_target.is_none());
+    }
+
+    #[test]
+    fn test_batch_space_report_fits_threshold() {
+        let sizes = [1000, 3000, 4096];
+
+        // Each size rounds up to a 4096-byte block: 3 * 4096 = 12288
+        let exact = BatchSpaceReport::new(&sizes, 4096, 12288);
+        assert_eq!(exact.required_total, 12288);
+        assert!(exact.fits);
+        assert_eq!(exact.shortfall, 0);
+
+        let short = BatchSpaceReport::new(&sizes, 4096, 12287);
+        assert!(!short.fits);
+        assert_eq!(short.shortfall, 1);
+
+        let unrounded = BatchSpaceReport::new(&sizes, 0, 8096);
+        assert_eq!(unrounded.required_total, 8096);
+        assert!(unrounded.fits);
+    }
+
+    #[test]
+    fn test_available_disk_space_for_missing_target() {
+        let dir = unique_test_dir("disk-space");
+        let target = dir.join("not-yet-created").join("file.bin");
+
+        assert!(available_disk_space(&target).unwrap() > 0);
+        assert!(filesystem_block_size(&target).unwrap() > 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filesystem_block_sizes_are_plausible_powers_of_two() {
+        let dir = std::env::temp_dir();
+        for block_size in [filesystem_block_size(&dir).unwrap(), preferred_io_block_size(&dir).unwrap()] {
+            assert!(block_size.is_power_of_two(), "{}", block_size);
+            assert!((512..=16 * 1024 * 1024).contains(&block_size), "{}", block_size);
+        }
+    }
+
+    #[test]
+    fn test_same_filesystem_within_directory() {
+        let dir = unique_test_dir("same-fs");
+        let existing = dir.join("existing.txt");
+        fs::write(&existing, "data").unwrap();
+
+        assert!(is_same_filesystem(&existing, &dir.join("not-yet-created.txt")).unwrap());
+    }
+
+    // Two filesystems are only reliably available on Linux, where /proc is always
+    // its own mount; moves across such a boundary take the copy+fsync+rename path.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_same_filesystem_across_mounts() {
+        let dir = unique_test_dir("cross-fs");
+
+        assert!(!is_same_filesystem(&dir, Path::new("/proc/self")).unwrap());
+    }
+
+    #[test]
+    fn test_move_file_same_filesystem() {
+        let dir = unique_test_dir("move-file");
+        let source = dir.join("source.bin");
+        let destination = dir.join("moved.bin");
+        fs::write(&source, b"moved content").unwrap();
+
+        move_file(&source, &destination, false).unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::read(&destination).unwrap(), b"moved content");
+
+        // An existing destination is kept unless overwriting is asked for
+        fs::write(&source, b"newer content").unwrap();
+        assert!(matches!(
+            move_file(&source, &destination, false),
+            Err(DownloadError::FileExists(_))
+        ));
+        assert_eq!(fs::read(&source).unwrap(), b"newer content");
+        assert_eq!(fs::read(&destination).unwrap(), b"moved content");
+
+        move_file(&source, &destination, true).unwrap();
+        assert!(!source.exists());
+        assert_eq!(fs::read(&destination).unwrap(), b"newer content");
+    }
+
+    #[test]
+    fn test_find_existing_by_checksum() {
+        let dir = unique_test_dir("dedup-find");
+        fs::write(dir.join("a.txt"), "alpha").unwrap();
+        fs::write(dir.join("b.txt"), "bravo").unwrap();
+
+        let digest = compute_file_checksum(&dir.join("b.txt"), ChecksumAlgorithm::Sha256).unwrap();
+        let found = find_file_by_checksum(&dir, &digest.to_uppercase(), ChecksumAlgorithm::Sha256)
+            .unwrap();
+        assert_eq!(found, Some(dir.join("b.txt")));
+
+        // Cached entries are invalidated when the file changes
+        fs::write(dir.join("b.txt"), "changed content").unwrap();
+        assert_eq!(
+            find_file_by_checksum(&dir, &digest, ChecksumAlgorithm::Sha256).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_link_or_copy_produces_identical_content() {
+        let dir = unique_test_dir("dedup-link");
+        let source = dir.join("source.bin");
+        let destination = dir.join("linked.bin");
+        fs::write(&source, b"deduplicated payload").unwrap();
+
+        let method = link_or_copy_file(&source, &destination).unwrap();
+
+        assert_eq!(method, LinkMethod::Hardlink);
+        assert_eq!(fs::read(&destination).unwrap(), fs::read(&source).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_multiple_checksums_single_pass() {
+        use sha2::Digest;
+
+        let dir = unique_test_dir("multi-checksum");
+        let path = dir.join("payload.bin");
+        let content: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &content).unwrap();
+
+        let digests = compute_file_checksums(
+            &path,
+            &[ChecksumAlgorithm::Md5, ChecksumAlgorithm::Sha256],
+        )
+        .unwrap();
+
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests["md5"], format!("{:x}", md5::Md5::digest(&content)));
+        assert_eq!(digests["sha256"], format!("{:x}", sha2::Sha256::digest(&content)));
+    }
+
+    struct MockDirectories {
+        download_dir: Option<PathBuf>,
+        home_dir: Option<PathBuf>,
+    }
+
+    impl DirectoryProvider for MockDirectories {
+        fn download_dir(&self) -> Result<PathBuf, String> {
+            self.download_dir.clone().ok_or_else(|| "no XDG download dir".to_string())
+        }
+
+        fn home_dir(&self) -> Result<PathBuf, String> {
+            self.home_dir.clone().ok_or_else(|| "no home dir".to_string())
+        }
+    }
+
+    #[test]
+    fn test_downloads_dir_fallback_chain() {
+        let downloads = PathBuf::from("/mock/Downloads");
+        let home = PathBuf::from("/mock/home");
+
+        let resolved = DownloadManager::with_directories(&MockDirectories {
+            download_dir: Some(downloads.clone()),
+            home_dir: Some(home.clone()),
+        });
+        assert_eq!(resolved.downloads_dir(), downloads.as_path());
+
+        let home_fallback = DownloadManager::with_directories(&MockDirectories {
+            download_dir: None,
+            home_dir: Some(home.clone()),
+        });
+        assert_eq!(home_fallback.downloads_dir(), home.as_path());
+
+        let temp_fallback = DownloadManager::with_directories(&MockDirectories {
+            download_dir: None,
+            home_dir: None,
+        });
+        assert_eq!(temp_fallback.downloads_dir(), env::temp_dir().as_path());
+    }
+
+    #[test]
+    fn test_format_file_size_binary() {
+        assert_eq!(format_file_size(0, SizeUnits::Binary), "0 B");
+        assert_eq!(format_file_size(1023, SizeUnits::Binary), "1023 B");
+        assert_eq!(format_file_size(1024, SizeUnits::Binary), "1.0 KiB");
+        assert_eq!(format_file_size(1536 * 1024 * 1024, SizeUnits::Binary), "1.5 GiB");
+        assert_eq!(format_file_size(1024 * 1024 - 1, SizeUnits::Binary), "1.0 MiB");
+        assert_eq!(format_file_size(u64::MAX, SizeUnits::Binary), "16.0 EiB");
+    }
+
+    #[test]
+    fn test_format_file_size_decimal() {
+        assert_eq!(format_file_size(0, SizeUnits::Decimal), "0 B");
+        assert_eq!(format_file_size(1023, SizeUnits::Decimal), "1.0 KB");
+        assert_eq!(format_file_size(1024, SizeUnits::Decimal), "1.0 KB");
+        assert_eq!(format_file_size(1_500_000_000, SizeUnits::Decimal), "1.5 GB");
+        assert_eq!(format_file_size(999_949, SizeUnits::Decimal), "999.9 KB");
+        assert_eq!(format_file_size(999_950, SizeUnits::Decimal), "1.0 MB");
+        assert_eq!(format_file_size(u64::MAX, SizeUnits::Decimal), "18.4 EB");
+    }
+
+    fn entry_names(entries: &[DirectoryEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_list_directory_patterns() {
+        let dir = unique_test_dir("list-patterns");
+        for name in ["a.zip", "b.zip", "notes.txt", "photo.jpg", ".hidden.zip"] {
+            fs::write(dir.join(name), b"data").unwrap();
+        }
+
+        let all = list_directory_entries(&dir, false, &[], &[]).unwrap();
+        assert_eq!(entry_names(&all), vec!["a.zip", "b.zip", "notes.txt", "photo.jpg"]);
+
+        let zips = list_directory_entries(&dir, false, &["*.zip".to_string()], &[]).unwrap();
+        assert_eq!(entry_names(&zips), vec!["a.zip", "b.zip"]);
+
+        let filtered = list_directory_entries(
+            &dir,
+            true,
+            &["*.zip".to_string(), "*.txt".to_string()],
+            &["b.*".to_string()],
+        )
+        .unwrap();
+        assert_eq!(entry_names(&filtered), vec![".hidden.zip", "a.zip", "notes.txt"]);
+
+        assert!(list_directory_entries(&dir, false, &["[".to_string()], &[]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recursive_walk_stops_at_symlink_loop() {
+        let dir = unique_test_dir("symlink-loop");
+        fs::write(dir.join("top.txt"), b"abc").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub").join("inner.bin"), b"12345").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop")).unwrap();
+
+        assert_eq!(directory_size(&dir).unwrap(), 8);
+        let listed = list_files_recursive(&dir).unwrap();
+        assert_eq!(entry_names(&listed), vec!["sub/inner.bin", "top.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checksum_batch_respects_memory_budget() {
+        use std::sync::atomic::AtomicUsize;
+
+        assert_eq!(checksum_batch_concurrency(4 * 1024 * 1024, 1024 * 1024), 1);
+        assert_eq!(checksum_batch_concurrency(1024 * 1024, 3 * 1024 * 1024), 3);
+        assert_eq!(checksum_batch_concurrency(1024, u64::MAX), MAX_CHECKSUM_BATCH_CONCURRENCY);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (current, highest) = (Arc::clone(&in_flight), Arc::clone(&peak));
+        let paths: Vec<String> = (0..6).map(|i| format!("file-{}", i)).collect();
+        let results = run_within_memory_budget(paths.clone(), 8 * 1024 * 1024, 1024 * 1024, move |_| {
+            let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+            highest.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            current.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .await;
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        let returned: Vec<String> = results.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(returned, paths);
+
+        // Two batches at once share one budget rather than getting one each
+        in_flight.store(0, Ordering::SeqCst);
+        peak.store(0, Ordering::SeqCst);
+        let batch = |label: &str| {
+            let (current, highest) = (Arc::clone(&in_flight), Arc::clone(&peak));
+            let paths = (0..4).map(|i| format!("{}-{}", label, i)).collect();
+            run_within_memory_budget(paths, 1024 * 1024, 2 * 1024 * 1024, move |_| {
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                highest.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                current.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+        tokio::join!(batch("first"), batch("second"));
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        assert_eq!(clamp_checksum_buffer(0, 1024), 1);
+        assert_eq!(clamp_checksum_buffer(4096, 1024), 1024);
+        assert_eq!(clamp_checksum_buffer(usize::MAX, u64::MAX), MAX_CHECKSUM_BUFFER_SIZE);
+
+        let dir = unique_test_dir("checksum-batch");
+        fs::write(dir.join("a.txt"), b"").unwrap();
+        let existing = dir.join("a.txt").to_string_lossy().to_string();
+        let missing = dir.join("missing.txt").to_string_lossy().to_string();
+        let results =
+            checksum_files_batch(vec![existing, missing], ChecksumAlgorithm::Sha256, 1024, 1024).await;
+        assert_eq!(results[0].checksum.as_deref(), Some(EMPTY_SHA256));
+        assert!(results[1].checksum.is_none() && results[1].error.is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_groups_identical_content() {
+        let dir = unique_test_dir("duplicates");
+        fs::write(dir.join("a.bin"), b"same content").unwrap();
+        fs::write(dir.join("b.bin"), b"same content").unwrap();
+        fs::write(dir.join("c.bin"), b"same content").unwrap();
+        // Same size as the group above but different bytes
+        fs::write(dir.join("d.bin"), b"diff content").unwrap();
+        fs::write(dir.join("e.txt"), b"hello").unwrap();
+        fs::write(dir.join("f.txt"), b"hello").unwrap();
+        fs::write(dir.join("unique.txt"), b"only one of these").unwrap();
+        fs::create_dir(dir.join("nested")).unwrap();
+
+        let report = find_duplicates(&dir).await.unwrap();
+
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        assert_eq!(
+            report.groups,
+            vec![
+                vec![path("a.bin"), path("b.bin"), path("c.bin")],
+                vec![path("e.txt"), path("f.txt")],
+            ]
+        );
+        assert!(report.unreadable.is_empty());
+
+        // A file that can't be read is reported and the rest still compared
+        let unreadable = dir.join("b.bin");
+        let report = find_duplicates_with(&dir, move |candidate| {
+            if candidate == unreadable {
+                return Err(DownloadError::PermissionDenied(candidate.display().to_string()));
+            }
+            compute_file_checksum(candidate, ChecksumAlgorithm::Sha256)
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            report.groups,
+            vec![vec![path("a.bin"), path("c.bin")], vec![path("e.txt"), path("f.txt")]]
+        );
+        assert_eq!(report.unreadable.len(), 1);
+        assert_eq!(report.unreadable[0].path, path("b.bin"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checksum_index_evicts_least_recently_used() {
+        let mut index = ChecksumIndex::new();
+        let started = Instant::now();
+        let entry = |age_ms: u64| IndexedDigest {
+            size: 1,
+            modified: std::time::UNIX_EPOCH,
+            digest: String::new(),
+            last_used: started + Duration::from_millis(age_ms),
+        };
+        let key = |name: &str| (PathBuf::from(name), ChecksumAlgorithm::Sha256);
+
+        remember_digest(&mut index, key("a"), entry(2), 2);
+        remember_digest(&mut index, key("b"), entry(1), 2);
+        remember_digest(&mut index, key("a"), entry(3), 2);
+        assert_eq!(index.len(), 2);
+
+        remember_digest(&mut index, key("c"), entry(4), 2);
+        assert_eq!(index.len(), 2);
+        assert!(index.contains_key(&key("a")) && index.contains_key(&key("c")));
+    }
+
+    #[test]
+    fn test_suggest_extension_for_mime() {
+        assert_eq!(suggest_extension_for_mime("application/pdf").as_deref(), Some("pdf"));
+        assert_eq!(suggest_extension_for_mime("image/JPEG").as_deref(), Some("jpg"));
+        assert_eq!(
+            suggest_extension_for_mime("text/plain; charset=utf-8").as_deref(),
+            Some("txt")
+        );
+        assert_eq!(suggest_extension_for_mime("application/octet-stream"), None);
+
+        let dir = Path::new("/downloads");
+        assert_eq!(
+            with_mime_extension(&dir.join("report"), "application/pdf"),
+            dir.join("report.pdf")
+        );
+        assert_eq!(
+            with_mime_extension(&dir.join("report.txt"), "application/pdf"),
+            dir.join("report.txt")
+        );
+        assert_eq!(
+            with_mime_extension(&dir.join("blob"), "application/octet-stream"),
+            dir.join("blob")
+        );
+
+        let dir = unique_test_dir("mime-extension-collision");
+        fs::write(dir.join("report.pdf"), b"existing").unwrap();
+        assert_eq!(
+            with_mime_extension(&dir.join("report"), "application/pdf"),
+            dir.join("report (1).pdf")
+        );
+        assert!(mime_extension_candidates(&dir.join("report")).contains(&dir.join("report.pdf")));
+        assert_eq!(mime_extension_candidates(&dir.join("report.txt")), vec![dir.join("report.txt")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_placeholder_race_has_one_winner() {
+        let dir = unique_test_dir("placeholder-race");
+        let path = dir.join("report.pdf");
+        let barrier = std::sync::Barrier::new(8);
+
+        let results: Vec<bool> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        create_placeholder(&path).unwrap()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+
+        assert_eq!(results.iter().filter(|created| **created).count(), 1);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+
+        fs::write(&path, b"downloaded").unwrap();
+        assert!(!create_placeholder(&path).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), b"downloaded");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_file_fresh_resumed_and_restarted() {
+        let dir = unique_test_dir("copy-resume");
+        let source = dir.join("source.bin");
+        let contents: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&source, &contents).unwrap();
+
+        let fresh = dir.join("fresh.bin");
+        let mut last_progress = (0, 0);
+        let outcome = copy_file_resumable(&source, &fresh, |copied, total| last_progress = (copied, total)).unwrap();
+        assert_eq!(outcome.start, CopyStart::Fresh);
+        assert_eq!(outcome.bytes_copied, contents.len() as u64);
+        assert_eq!(last_progress, (contents.len() as u64, contents.len() as u64));
+        assert_eq!(fs::read(&fresh).unwrap(), contents);
+
+        let resumed = dir.join("resumed.bin");
+        fs::write(&resumed, &contents[..2_500_000]).unwrap();
+        let outcome = copy_file_resumable(&source, &resumed, |_, _| {}).unwrap();
+        assert_eq!(outcome.start, CopyStart::Resumed);
+        assert_eq!(outcome.resumed_from, 2_500_000);
+        assert_eq!(outcome.bytes_copied, contents.len() as u64 - 2_500_000);
+        assert_eq!(fs::read(&resumed).unwrap(), contents);
+
+        let mismatched = dir.join("mismatched.bin");
+        fs::write(&mismatched, vec![0xAAu8; 1_000_000]).unwrap();
+        let outcome = copy_file_resumable(&source, &mismatched, |_, _| {}).unwrap();
+        assert_eq!(outcome.start, CopyStart::Restarted);
+        assert_eq!(outcome.resumed_from, 0);
+        assert_eq!(fs::read(&mismatched).unwrap(), contents);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_head() {
+        let dir = unique_test_dir("read-head");
+        let path = dir.join("preview.txt");
+        fs::write(&path, b"0123456789").unwrap();
+
+        assert_eq!(read_head(&path, 4).await.unwrap(), b"0123");
+        assert_eq!(read_head(&path, 10).await.unwrap(), b"0123456789");
+        assert_eq!(read_head(&path, 64).await.unwrap(), b"0123456789");
+        assert!(read_head(&dir.join("missing.txt"), 4).await.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_tail() {
+        let dir = unique_test_dir("read-tail");
+        let path = dir.join("download.log");
+        fs::write(&path, b"line one\nline two\n").unwrap();
+
+        assert_eq!(read_tail(&path, 9).await.unwrap(), b"line two\n");
+        assert_eq!(read_tail(&path, 1024).await.unwrap(), b"line one\nline two\n");
+        assert!(read_tail(&path, 0).await.unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_stability() {
+        let dir = unique_test_dir("file-stable");
+        let path = dir.join("growing.bin");
+        fs::write(&path, b"start").unwrap();
+
+        let writer_path = path.clone();
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            let mut file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&writer_path)
+                .await
+                .unwrap();
+            for _ in 0..20 {
+                file.write_all(b"more").await.unwrap();
+                file.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        assert!(!file_is_stable(&path, Duration::from_millis(100)).await.unwrap());
+        writer.await.unwrap();
+
+        assert!(file_is_stable(&path, Duration::from_millis(50)).await.unwrap());
+        assert!(file_is_stable(&dir.join("missing.bin"), Duration::ZERO).await.is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_ranges() {
+        let dir = unique_test_dir("read-ranges");
+        let path = dir.join("data.bin");
+        let contents: Vec<u8> = (0..=255).collect();
+        fs::write(&path, &contents).unwrap();
+
+        let chunks = read_ranges(&path, &[(200, 8), (0, 4), (100, 16)]).await.unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                contents[200..208].to_vec(),
+                contents[0..4].to_vec(),
+                contents[100..116].to_vec(),
+            ]
+        );
+
+        let clamped = read_ranges(&path, &[(250, 100), (256, 4), (1000, 4)]).await.unwrap();
+        assert_eq!(clamped, vec![contents[250..].to_vec(), Vec::new(), Vec::new()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_write_all_yielding_keeps_content() {
+        let dir = unique_test_dir("write-yielding");
+        let path = dir.join("data.bin");
+        let contents: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        for yield_every in [None, Some(0), Some(7), Some(4096), Some(1 << 20)] {
+            let mut file = File::create(&path).await.unwrap();
+            write_all_yielding(&mut file, &contents, yield_every).await.unwrap();
+            file.flush().await.unwrap();
+            drop(file);
+            assert_eq!(fs::read(&path).unwrap(), contents, "yield_every={:?}", yield_every);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_estimate_eta() {
+        assert_eq!(estimate_eta(250, 1000, 50.0), Some(15));
+        // Partial seconds round up so the ETA never reads 0 before completion
+        assert_eq!(estimate_eta(999, 1000, 50.0), Some(1));
+        assert_eq!(estimate_eta(250, 1000, 0.0), None);
+        assert_eq!(estimate_eta(250, 0, 50.0), None);
+        assert_eq!(estimate_eta(1000, 1000, 0.0), Some(0));
+
+        let progress = DownloadProgress::new("task".to_string(), 500, 1000, 100.0);
+        assert_eq!(progress.progress, 50.0);
+        assert_eq!(progress.eta_secs, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunk_refuses_file_being_written() {
+        let dir = unique_test_dir("write-in-progress");
+        let path = dir.join("download.bin");
+        fs::write(&path, b"partial").unwrap();
+
+        let guard = WriteInProgressGuard::register(&path);
+        let nested = WriteInProgressGuard::register(&path);
+        drop(nested);
+        assert!(matches!(
+            read_chunk(&path, 0, 16).await,
+            Err(DownloadError::WriteInProgress(_))
+        ));
+
+        drop(guard);
+        assert_eq!(read_chunk(&path, 0, 16).await.unwrap(), b"partial");
+        assert!(!is_write_in_progress(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_marks_match_equivalent_paths_and_expire_when_abandoned() {
+        let dir = unique_test_dir("write-registry-key");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let path = dir.join("download.bin");
+        let roundabout = dir.join("sub").join("..").join("download.bin");
+
+        let guard = WriteInProgressGuard::register(&roundabout);
+        assert!(is_write_in_progress(&path));
+        drop(guard);
+        assert!(!is_write_in_progress(&path));
+
+        begin_file_write(roundabout.to_string_lossy().to_string());
+        assert!(is_write_in_progress(&path));
+        finish_file_write(path.to_string_lossy().to_string());
+        assert!(!is_write_in_progress(&roundabout));
+
+        let mut marks = WriteMarks {
+            frontend: 1,
+            frontend_activity: Instant::now().checked_sub(ABANDONED_WRITE_TIMEOUT),
+            ..Default::default()
+        };
+        marks.expire_abandoned();
+        assert!(marks.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_guard_released_after_parent_is_created() {
+        let dir = unique_test_dir("write-guard-new-parent");
+        // Through a symlink the canonical key differs once the parent exists
+        let real = dir.join("real");
+        fs::create_dir_all(&real).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&real, dir.join("link")).unwrap();
+        #[cfg(not(unix))]
+        fs::create_dir_all(dir.join("link")).unwrap();
+        let path = dir.join("link").join("not-yet").join("download.bin");
+
+        let guard = WriteInProgressGuard::register(&path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        drop(guard);
+
+        let registered = writes_in_progress().lock().unwrap().keys().any(|key| key.starts_with(&dir));
+        assert!(!registered);
+        assert!(!is_write_in_progress(&path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_predict_duration() {
+        // 10 MiB at 1 MiB/s, and 2.4s rounds to the nearest second
+        assert_eq!(predict_duration(10 * 1024 * 1024, 1024.0 * 1024.0), Some(10));
+        assert_eq!(predict_duration(2400, 1000.0), Some(2));
+        assert_eq!(predict_duration(1000, 0.0), None);
+        assert_eq!(predict_duration(1000, f64::NAN), None);
+        // A fast link still reports at least a second for a non-empty file
+        assert_eq!(predict_duration(1, 1e12), Some(1));
+        assert_eq!(predict_duration(0, 1e12), Some(0));
+    }
+
+    #[test]
+    fn test_aggregate_progress_across_parts() {
+        let part = |downloaded_bytes, total_bytes, speed| PartProgress { downloaded_bytes, total_bytes, speed };
+        let parts = vec![part(400, Some(1000), 100.0), part(1000, Some(1000), 0.0), part(100, Some(500), 50.0)];
+
+        let aggregate = aggregate_progress("task".to_string(), &parts);
+        assert_eq!(aggregate.task_id, "task");
+        assert_eq!(aggregate.downloaded_bytes, 1500);
+        assert_eq!(aggregate.total_bytes, 2500);
+        assert_eq!(aggregate.progress, 60.0);
+        assert_eq!(aggregate.speed, 150.0);
+        assert_eq!(aggregate.eta_secs, Some(7));
+
+        let mut unknown = parts.clone();
+        unknown[2].total_bytes = None;
+        let aggregate = aggregate_progress("task".to_string(), &unknown);
+        assert_eq!(aggregate.downloaded_bytes, 1500);
+        assert_eq!(aggregate.total_bytes, 0);
+        assert_eq!(aggregate.progress, 0.0);
+        assert_eq!(aggregate.eta_secs, None);
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_expires_on_slow_operation() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, DownloadError>(42)
+        };
+        match with_timeout(Duration::from_millis(20), slow).await {
+            Err(DownloadError::Io(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::TimedOut);
+                assert_eq!(e.to_string(), "operation timed out");
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+
+        let fast = async { Ok::<_, DownloadError>(42) };
+        assert_eq!(with_timeout(Duration::from_secs(1), fast).await.unwrap(), 42);
+    }
+
+    #[test]
+    fn test_check_download_size() {
+        let dir = unique_test_dir("download-size");
+        let target = dir.join("object.bin");
+        fs::write(&target, b"hello world").unwrap();
+
+        assert!(check_download_size(&target, 11).is_ok());
+        assert!(matches!(
+            check_download_size(&target, 20),
+            Err(DownloadError::SizeMismatch {
+                expected: 20,
+                actual: 11
+            })
+        ));
+    }
+
+    #[test]
+    fn test_download_is_complete_checks_size_before_hashing() {
+        let dir = unique_test_dir("download-complete");
+        let target = dir.join("object.bin");
+        fs::write(&target, b"hello world").unwrap();
+        let digest = compute_file_checksum(&target, ChecksumAlgorithm::Sha256).unwrap();
+
+        let hashed = std::cell::Cell::new(false);
+        let complete = download_is_complete_with(&target, Some(5), Some(&digest), |path| {
+            hashed.set(true);
+            compute_file_checksum(path, ChecksumAlgorithm::Sha256)
+        })
+        .unwrap();
+        assert!(!complete);
+        assert!(!hashed.get());
+
+        assert!(download_is_complete(&target, Some(11), Some(&digest), ChecksumAlgorithm::Sha256).unwrap());
+        assert!(!download_is_complete(
+            &target,
+            Some(11),
+            Some(&"0".repeat(64)),
+            ChecksumAlgorithm::Sha256
+        )
+        .unwrap());
+        assert!(!download_is_complete(&dir.join("missing"), Some(11), None, ChecksumAlgorithm::Sha256).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_base64_file_round_trip() {
+        use base64::Engine as _;
+
+        let _prefix = test_temp_prefix::hold();
+        let dir = unique_test_dir("base64");
+        // Spans several encode chunks and ends on a length that needs padding
+        let contents: Vec<u8> = (0..(BASE64_ENCODE_CHUNK * 2 + 7)).map(|i| (i % 256) as u8).collect();
+        let source = dir.join("source.bin");
+        fs::write(&source, &contents).unwrap();
+
+        let encoded = encode_file_to_base64(&source, MAX_BASE64_FILE_SIZE).unwrap();
+        assert_eq!(encoded, base64::engine::general_purpose::STANDARD.encode(&contents));
+
+        let target = dir.join("target.bin");
+        let written = decode_base64_to_file(&target, &encoded, MAX_BASE64_FILE_SIZE).unwrap();
+        assert_eq!(written, contents.len() as u64);
+        assert_eq!(fs::read(&target).unwrap(), contents);
+
+        assert!(matches!(
+            encode_file_to_base64(&source, 16),
+            Err(DownloadError::FileTooLarge { limit: 16, .. })
+        ));
+        assert!(matches!(
+            decode_base64_to_file(&dir.join("bad.bin"), "not*base64", MAX_BASE64_FILE_SIZE),
+            Err(DownloadError::InvalidBase64(_))
+        ));
+        assert!(!dir.join("bad.bin").exists());
+
+        // Data that turns out to be bad past the first chunk keeps the existing file
+        let mut corrupt = encoded.clone();
+        corrupt.push_str("****");
+        assert!(decode_base64_to_file(&target, &corrupt, MAX_BASE64_FILE_SIZE).is_err());
+        assert_eq!(fs::read(&target).unwrap(), contents);
+        assert!(!temp_path_for(&target).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_validation_reasons_are_distinct() {
+        let errors = vec![
+            DownloadError::Io(std::io::Error::other("disk")),
+            DownloadError::Http("disk".to_string()),
+            DownloadError::Path("disk".to_string()),
+            DownloadError::FileExists("disk".to_string()),
+            DownloadError::InvalidPath("disk".to_string()),
+            DownloadError::PermissionDenied("disk".to_string()),
+            DownloadError::InsufficientSpace,
+            DownloadError::Cancelled,
+            DownloadError::InvalidResumeData,
+            DownloadError::ChecksumMismatch {
+                expected: "disk".to_string(),
+                actual: "disk".to_string(),
+            },
+            DownloadError::FileTooLarge { size: 1, limit: 0 },
+            DownloadError::InvalidBase64("disk".to_string()),
+        ];
+
+        let reasons: std::collections::HashSet<String> = errors
+            .into_iter()
+            .map(|e| {
+                let validation = PathValidation::from(Err(e));
+                assert!(!validation.valid);
+                validation.reason.unwrap()
+            })
+            .collect();
+        assert_eq!(reasons.len(), 12);
+
+        let manager = DownloadManager::from_dir(unique_test_dir("validate-reasons"));
+        let relative = PathValidation::from(manager.validate_download_path("relative/file.txt"));
+        let missing_parent = PathValidation::from(
+            manager.validate_download_path(&env::temp_dir().join("no-such-dir-xyz/file.txt").to_string_lossy()),
+        );
+        assert_eq!(relative.reason.as_deref(), Some("Invalid path: Path must be absolute"));
+        assert!(missing_parent.reason.unwrap().contains("Parent directory does not exist"));
+        assert_eq!(
+            PathValidation::from(Ok(PathBuf::from("/tmp/file.txt"))),
+            PathValidation { valid: true, reason: None }
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_collision_detection() {
+        let dir = unique_test_dir("case-collision");
+        fs::write(dir.join("File.txt"), b"data").unwrap();
+
+        assert!(collides_case_insensitive(&dir.join("file.txt")).unwrap());
+        assert!(collides_case_insensitive(&dir.join("FILE.TXT")).unwrap());
+        assert!(!collides_case_insensitive(&dir.join("other.txt")).unwrap());
+
+        let manager = DownloadManager::from_dir(dir.clone());
+        let unique = manager.generate_unique_filename_with(&dir.join("file.txt"), true);
+        assert_eq!(unique, dir.join("file (1).txt"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn test_generate_unique_filename_avoids_case_variants() {
+        let dir = unique_test_dir("case-collision-default");
+        fs::write(dir.join("File.txt"), b"data").unwrap();
+
+        let manager = DownloadManager::from_dir(dir.clone());
+        let unique = manager.generate_unique_filename(&dir.join("file.txt"));
+        assert_eq!(unique, dir.join("file (1).txt"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mount_status_of_local_directory() {
+        let dir = unique_test_dir("mount-status");
+
+        let status = check_mount_status(&dir.join("download.bin"));
+        assert!(status.mounted);
+        assert!(status.writable);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let missing = check_mount_status(&dir.join("unplugged").join("download.bin"));
+        assert!(!missing.mounted);
+        assert!(!missing.writable);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mount_status_reports_filesystem_type_on_linux() {
+        let status = check_mount_status(Path::new("/proc/self"));
+        assert_eq!(status.filesystem_type.as_deref(), Some("proc"));
+        assert_eq!(unescape_mount_field("/mnt/usb\\040drive"), "/mnt/usb drive");
+    }
+
+    #[test]
+    fn test_cancelling_checksum_stops_promptly() {
+        let dir = unique_test_dir("checksum-cancel");
+        let path = dir.join("large.bin");
+        let file = fs::File::create(&path).unwrap();
+        file.set_len(512 * 1024 * 1024).unwrap();
+        drop(file);
+
+        let guard = CancellationGuard::register("checksum-cancel-test");
+        let worker_path = path.clone();
+        let started = std::time::Instant::now();
+        let result = std::thread::scope(|scope| {
+            let worker = scope.spawn(|| {
+                compute_file_checksums_cancellable(&worker_path, &[ChecksumAlgorithm::Sha256], guard.flag())
+            });
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(cancel_task("checksum-cancel-test"));
+            worker.join().unwrap()
+        });
+
+        assert!(matches!(result, Err(DownloadError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        drop(guard);
+        assert!(!cancel_task("checksum-cancel-test"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_queued_download_never_runs() {
+        let queue = DownloadQueue::new(1);
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let first = queue.enqueue("queue-cancel-first", |_guard| async move {
+            let _ = release_rx.await;
+            Ok(())
+        });
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let second_ran = Arc::clone(&ran);
+        let second = queue.enqueue("queue-cancel-second", move |_guard| async move {
+            second_ran.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(queue.state("queue-cancel-first"), Some(QueuedTaskState::Running));
+        assert_eq!(queue.state("queue-cancel-second"), Some(QueuedTaskState::Queued));
+
+        assert!(queue.cancel_download("queue-cancel-second"));
+        assert!(matches!(second.await.unwrap(), Err(DownloadError::Cancelled)));
+        assert_eq!(queue.state("queue-cancel-second"), None);
+
+        release_tx.send(()).unwrap();
+        first.await.unwrap().unwrap();
+        assert_eq!(queue.state("queue-cancel-first"), None);
+        assert!(!ran.load(Ordering::SeqCst));
+        assert!(!queue.cancel_download("queue-cancel-second"));
+    }
+
+    #[test]
+    fn test_zip_integrity_detects_truncation() {
+        use std::io::Write;
+
+        let dir = unique_test_dir("zip-integrity");
+        let path = dir.join("archive.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(&[b'a'; 4096]).unwrap();
+        writer.start_file("b.txt", options).unwrap();
+        writer.write_all(b"hello zip").unwrap();
+        writer.finish().unwrap();
+
+        let report = inspect_zip(&path).unwrap();
+        assert_eq!(
+            report,
+            ZipReport {
+                valid: true,
+                entry_count: 2,
+                uncompressed_size: 4096 + 9,
+            }
+        );
+
+        let bytes = fs::read(&path).unwrap();
+        let truncated = dir.join("truncated.zip");
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(!inspect_zip(&truncated).unwrap().valid);
+
+        assert!(inspect_zip(&dir.join("missing.zip")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_checksum_matches_streaming() {
+        let dir = unique_test_dir("mmap-checksum");
+        let path = dir.join("large.bin");
+        let contents: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i: u32| (i % 253) as u8).collect();
+        fs::write(&path, &contents).unwrap();
+
+        for algorithm in [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5] {
+            assert_eq!(
+                compute_file_checksum_mmap(&path, algorithm).unwrap(),
+                compute_file_checksum(&path, algorithm).unwrap()
+            );
+        }
+
+        let empty = dir.join("empty.bin");
+        fs::write(&empty, b"").unwrap();
+        assert_eq!(
+            compute_file_checksum_mmap(&empty, ChecksumAlgorithm::Sha256).unwrap(),
+            compute_file_checksum(&empty, ChecksumAlgorithm::Sha256).unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_removes_only_stale_temp_files() {
+        use fs2::FileExt;
+
+        let _prefix = test_temp_prefix::hold();
+        let dir = unique_test_dir("temp-cleanup");
+        let two_hours_ago = std::time::SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        let make = |name: &str, stale: bool| {
+            let path = dir.join(name);
+            let file = fs::File::create(&path).unwrap();
+            if stale {
+                file.set_modified(two_hours_ago).unwrap();
+            }
+            path
+        };
+
+        let stale = make(&temp_path_for(&dir.join("config.encrypted")).file_name().unwrap().to_string_lossy(), true);
+        let fresh = make(&temp_path_for(&dir.join("fresh.bin")).file_name().unwrap().to_string_lossy(), false);
+        let foreign = make("someone-else.tmp", true);
+        let locked = make(&temp_path_for(&dir.join("locked.bin")).file_name().unwrap().to_string_lossy(), true);
+        let lock = fs::File::open(&locked).unwrap();
+        lock.lock_exclusive().unwrap();
+
+        let removed = remove_stale_temp_files(&dir, DEFAULT_TEMP_FILE_MAX_AGE).unwrap();
+
+        assert_eq!(removed, vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(foreign.exists());
+        assert!(locked.exists());
+
+        fs2::FileExt::unlock(&lock).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_custom_temp_prefix_is_used_and_cleaned_up() {
+        let _prefix = test_temp_prefix::hold();
+        assert_eq!(default_temp_file_prefix(), ".s3-upload-tool-");
+        for invalid in ["", ".", "..", "a/b", "a\\b", "report", ".a", "..hidden", ".a/b-"] {
+            assert!(set_temp_file_prefix(invalid).is_err(), "{:?}", invalid);
+        }
+
+        let dir = unique_test_dir("temp-prefix");
+        let prefix = ".my-app-";
+        let temp_path = temp_path_with_prefix(&dir.join("config.encrypted"), prefix);
+        assert_eq!(temp_path.file_name().unwrap(), ".my-app-config.encrypted.tmp");
+
+        let two_hours_ago = std::time::SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        fs::File::create(&temp_path).unwrap().set_modified(two_hours_ago).unwrap();
+        let default_named = temp_path_with_prefix(&dir.join("other.bin"), &default_temp_file_prefix());
+        fs::File::create(&default_named).unwrap().set_modified(two_hours_ago).unwrap();
+
+        let only_custom = [prefix.to_string()];
+        let removed =
+            remove_stale_temp_files_with_prefixes(&dir, DEFAULT_TEMP_FILE_MAX_AGE, &only_custom).unwrap();
+        assert_eq!(removed, vec![temp_path.clone()]);
+        assert!(default_named.exists());
+
+        // With a custom prefix set, orphans from before the change are still cleaned up
+        fs::File::create(&temp_path).unwrap().set_modified(two_hours_ago).unwrap();
+        set_temp_file_prefix(prefix).unwrap();
+        let mut removed = remove_stale_temp_files(&dir, DEFAULT_TEMP_FILE_MAX_AGE).unwrap();
+        removed.sort();
+        let mut expected = vec![temp_path.clone(), default_named.clone()];
+        expected.sort();
+        assert_eq!(removed, expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_temp_prefix_survives_restart() {
+        let _prefix = test_temp_prefix::hold();
+        let dir = unique_test_dir("temp-prefix-saved");
+
+        restore_temp_file_prefix(&dir);
+        assert_eq!(temp_file_prefix(), default_temp_file_prefix());
+
+        save_temp_file_prefix(&dir, ".saved-prefix-").unwrap();
+        assert!(save_temp_file_prefix(&dir, "report").is_err());
+        set_temp_file_prefix(&default_temp_file_prefix()).unwrap();
+
+        restore_temp_file_prefix(&dir);
+        assert_eq!(temp_file_prefix(), ".saved-prefix-");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1866 @@
+use crate::download::{
+    check_download_size, download_is_complete, estimate_eta, mime_extension_candidates, move_file,
+    with_mime_extension, temp_path_for, CancellationGuard, ChecksumAlgorithm, ChecksumHasher, DownloadError,
+    DownloadProgress, DownloadQueue, WriteInProgressGuard,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+
+/// Distinguishes the queue task ids of concurrent manifests
+static MANIFEST_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Default time allowed to establish a connection
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+/// Default time allowed between reads before a stalled transfer is abandoned
+const DEFAULT_READ_TIMEOUT_MS: u64 = 30_000;
+
+/// Headers the HTTP client manages itself and callers may not override
+const RESERVED_HEADERS: &[&str] = &["host", "content-length"];
+
+/// One object to fetch as part of a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub url: String,
+    pub target_path: String,
+    pub expected_checksum: Option<String>,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ManifestOptions {
+    /// Stop starting new entries after the first failure
+    pub fail_fast: bool,
+    /// Algorithm of every `expected_checksum`, defaults to sha256
+    pub checksum_algorithm: Option<String>,
+    /// Extra request headers some providers require, e.g. `x-amz-request-payer`
+    pub headers: HashMap<String, String>,
+    pub connect_timeout_ms: u64,
+    /// Longest gap between received chunks; the partial file is kept when it expires
+    pub read_timeout_ms: u64,
+    /// Append an extension matching the response's `Content-Type` to targets without one
+    pub fix_extensions: bool,
+    /// Write a `.done` marker next to each verified download for external tools
+    pub write_done_markers: bool,
+}
+
+impl Default for ManifestOptions {
+    fn default() -> Self {
+        ManifestOptions {
+            fail_fast: false,
+            checksum_algorithm: None,
+            headers: HashMap::new(),
+            connect_timeout_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+            read_timeout_ms: DEFAULT_READ_TIMEOUT_MS,
+            fix_extensions: false,
+            write_done_markers: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestEntryStatus {
+    /// The target already existed with the expected size and checksum
+    AlreadyPresent,
+    Downloaded,
+    Failed,
+    /// Not attempted because an earlier entry failed with `fail_fast` set
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntryResult {
+    pub url: String,
+    pub target_path: String,
+    pub status: ManifestEntryStatus,
+    pub error: Option<String>,
+}
+
+/// Progress for one entry together with the overall manifest position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestProgress {
+    pub entry_index: usize,
+    pub entry_downloaded_bytes: u64,
+    pub entry_total_bytes: Option<u64>,
+    pub finished_entries: usize,
+    pub total_entries: usize,
+    /// Seconds left on this entry, known once its size is and bytes are flowing
+    pub eta_secs: Option<u64>,
+}
+
+fn http_error(error: reqwest::Error) -> DownloadError {
+    if error.is_timeout() {
+        return DownloadError::Http(format!("Timed out: {}", error));
+    }
+    DownloadError::Http(error.to_string())
+}
+
+/// HTTP client with the connect and read timeouts from `options`
+pub fn build_http_client(options: &ManifestOptions) -> Result<reqwest::Client, DownloadError> {
+    http_client(options.connect_timeout_ms, options.read_timeout_ms)
+}
+
+fn http_client(connect_timeout_ms: u64, read_timeout_ms: u64) -> Result<reqwest::Client, DownloadError> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(connect_timeout_ms))
+        .read_timeout(Duration::from_millis(read_timeout_ms))
+        .build()
+        .map_err(http_error)
+}
+
+/// Turn caller-supplied headers into a `HeaderMap`, rejecting reserved or malformed ones
+pub fn build_request_headers(headers: &HashMap<String, String>) -> Result<HeaderMap, DownloadError> {
+    let mut header_map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        if RESERVED_HEADERS.contains(&name.trim().to_ascii_lowercase().as_str()) {
+            return Err(DownloadError::InvalidHeader(format!("{} can't be overridden", name)));
+        }
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| DownloadError::InvalidHeader(format!("invalid header name {:?}", name)))?;
+        let header_value = HeaderValue::from_str(value)
+            .map_err(|_| DownloadError::InvalidHeader(format!("invalid value for {}", name)))?;
+        header_map.insert(header_name, header_value);
+    }
+    Ok(header_map)
+}
+
+/// Path of the in-progress file written next to `target` until it is verified
+pub fn partial_path_for(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Path of the `.resume.json` sidecar describing the partial download of `target`
+pub fn resume_path_for(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".resume.json");
+    PathBuf::from(name)
+}
+
+/// Path of the `.done` marker written beside `target` once it has been verified
+pub fn done_marker_path_for(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".done");
+    PathBuf::from(name)
+}
+
+/// Contents of a `.done` marker: what the finished download was verified as
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DoneMarker {
+    pub checksum: String,
+    pub algorithm: String,
+    pub size: u64,
+}
+
+/// Write the `.done` marker for `target` through a temp file and rename, so a
+/// reader never sees a half-written marker
+pub fn write_done_marker(target: &Path, marker: &DoneMarker) -> Result<(), DownloadError> {
+    let marker_path = done_marker_path_for(target);
+    let temp_path = temp_path_for(&marker_path);
+    std::fs::write(
+        &temp_path,
+        serde_json::to_string_pretty(marker).map_err(std::io::Error::from)?,
+    )?;
+    std::fs::rename(&temp_path, &marker_path)?;
+    Ok(())
+}
+
+/// Whether `target` has a readable `.done` marker whose size still matches the file
+pub fn is_marked_done(target: &Path) -> Result<bool, DownloadError> {
+    let contents = match std::fs::read_to_string(done_marker_path_for(target)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let Ok(marker) = serde_json::from_str::<DoneMarker>(&contents) else {
+        return Ok(false);
+    };
+    Ok(std::fs::metadata(target).is_ok_and(|metadata| metadata.len() == marker.size))
+}
+
+/// Contents of a `.resume.json` sidecar, kept while a download is in progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeState {
+    pub url: String,
+    pub target_path: String,
+    pub partial_path: String,
+    pub expected_checksum: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl ResumeState {
+    fn for_entry(entry: &ManifestEntry) -> Self {
+        ResumeState {
+            url: entry.url.clone(),
+            target_path: entry.target_path.clone(),
+            partial_path: partial_path_for(Path::new(&entry.target_path))
+                .to_string_lossy()
+                .to_string(),
+            expected_checksum: entry.expected_checksum.clone(),
+            size: entry.size,
+        }
+    }
+}
+
+/// A resume sidecar `relocate_partial_downloads` left in place, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelocationSkip {
+    pub sidecar_path: String,
+    pub reason: String,
+}
+
+/// Outcome of `relocate_partial_downloads`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelocationReport {
+    /// New target path of every partial download that was moved
+    pub moved: Vec<String>,
+    pub skipped: Vec<RelocationSkip>,
+}
+
+/// Move the partial downloads described by resume sidecars in `old_dir` into
+/// `new_dir`, rewriting each sidecar to point at the new location. Sidecars whose
+/// partial file is gone are left alone. One that can't be read or moved, or whose
+/// target, partial or sidecar name is already taken in `new_dir`, is reported as
+/// skipped without stopping the rest.
+pub fn relocate_partial_downloads(old_dir: &Path, new_dir: &Path) -> Result<RelocationReport, DownloadError> {
+    std::fs::create_dir_all(new_dir)?;
+    let mut report = RelocationReport::default();
+
+    for dir_entry in std::fs::read_dir(old_dir)? {
+        let sidecar_path = match dir_entry {
+            Ok(dir_entry) => dir_entry.path(),
+            Err(e) => {
+                log::warn!("Skipping unreadable entry in {}: {}", old_dir.display(), e);
+                continue;
+            }
+        };
+        let is_sidecar = sidecar_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(".resume.json"));
+        if !is_sidecar {
+            continue;
+        }
+
+        match relocate_partial_download(&sidecar_path, new_dir) {
+            Ok(Some(new_target)) => report.moved.push(new_target.to_string_lossy().to_string()),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("Not relocating {}: {}", sidecar_path.display(), e);
+                report.skipped.push(RelocationSkip {
+                    sidecar_path: sidecar_path.to_string_lossy().to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Move the partial download described by `sidecar_path` into `new_dir`. Returns the
+/// new target, or `None` when the partial file no longer exists.
+fn relocate_partial_download(sidecar_path: &Path, new_dir: &Path) -> Result<Option<PathBuf>, DownloadError> {
+    let contents = std::fs::read_to_string(sidecar_path)?;
+    let state: ResumeState = serde_json::from_str(&contents).map_err(|_| DownloadError::InvalidResumeData)?;
+
+    let old_partial = PathBuf::from(&state.partial_path);
+    let Some(file_name) = Path::new(&state.target_path).file_name() else {
+        return Err(DownloadError::InvalidResumeData);
+    };
+    if !old_partial.exists() {
+        return Ok(None);
+    }
+
+    let new_target = new_dir.join(file_name);
+    let new_partial = partial_path_for(&new_target);
+    let new_sidecar = resume_path_for(&new_target);
+    if let Some(taken) = [&new_target, &new_partial, &new_sidecar].into_iter().find(|path| path.exists()) {
+        return Err(DownloadError::FileExists(taken.display().to_string()));
+    }
+    move_file(&old_partial, &new_partial, false)?;
+
+    let updated = ResumeState {
+        target_path: new_target.to_string_lossy().to_string(),
+        partial_path: new_partial.to_string_lossy().to_string(),
+        ..state
+    };
+    std::fs::write(
+        &new_sidecar,
+        serde_json::to_string_pretty(&updated).map_err(std::io::Error::from)?,
+    )?;
+    std::fs::remove_file(sidecar_path)?;
+    Ok(Some(new_target))
+}
+
+/// Size of the partial download of `entry` worth resuming: its sidecar has to describe
+/// this same entry, so a partial left by a different object is started over
+async fn resumable_partial_len(entry: &ManifestEntry, partial_path: &Path, resume_path: &Path) -> u64 {
+    let matches_entry = tokio::fs::read_to_string(resume_path)
+        .await
+        .ok()
+        .and_then(|contents| serde_json::from_str::<ResumeState>(&contents).ok())
+        .is_some_and(|state| state == ResumeState::for_entry(entry));
+    if !matches_entry {
+        return 0;
+    }
+    tokio::fs::metadata(partial_path).await.map(|m| m.len()).unwrap_or(0)
+}
+
+/// Stream `url` into a `.part` file beside `target`, hashing on the fly, and
+/// rename it into place only once the checksum (if any) matches. A partial file
+/// left by an earlier attempt at the same entry is continued with a `Range`
+/// request. Returns where the file ended up, which differs from `target` only with
+/// `fix_extension`. `read_file_chunk` refuses `target` until this returns.
+async fn download_entry<F>(
+    client: &reqwest::Client,
+    entry: &ManifestEntry,
+    headers: &HeaderMap,
+    algorithm: ChecksumAlgorithm,
+    fix_extension: bool,
+    write_marker: bool,
+    on_chunk: F,
+) -> Result<(ManifestEntryStatus, PathBuf), DownloadError>
+where
+    F: Fn(u64),
+{
+    let target = PathBuf::from(&entry.target_path);
+    // An earlier run with `fix_extension` may have stored the file under another name
+    let candidates = if fix_extension {
+        mime_extension_candidates(&target)
+    } else {
+        vec![target.clone()]
+    };
+    let size = entry.size;
+    let checksum = entry.expected_checksum.clone();
+    let present = tokio::task::spawn_blocking(move || {
+        candidates.into_iter().find(|candidate| {
+            download_is_complete(candidate, size, checksum.as_deref(), algorithm).unwrap_or(false)
+        })
+    })
+    .await;
+    if let Ok(Some(present)) = present {
+        return Ok((ManifestEntryStatus::AlreadyPresent, present));
+    }
+
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let _writing = WriteInProgressGuard::register(&target);
+
+    let partial_path = partial_path_for(&target);
+    let resume_path = resume_path_for(&target);
+    let existing = resumable_partial_len(entry, &partial_path, &resume_path).await;
+    let mut request_headers = headers.clone();
+    if existing > 0 {
+        request_headers.insert(reqwest::header::RANGE, range_from(existing)?);
+    }
+
+    let response = client
+        .get(&entry.url)
+        .headers(request_headers)
+        .send()
+        .await
+        .map_err(http_error)?;
+    if range_not_satisfiable(&response, existing) {
+        let checksum = entry.expected_checksum.as_deref();
+        let (size, actual) =
+            verify_complete_partial(&response, &partial_path, &resume_path, entry.size, checksum, algorithm)
+                .await?;
+        tokio::fs::rename(&partial_path, &target).await?;
+        let _ = tokio::fs::remove_file(&resume_path).await;
+        if write_marker {
+            let marker = DoneMarker {
+                checksum: actual,
+                algorithm: algorithm.name().to_string(),
+                size,
+            };
+            write_done_marker(&target, &marker)?;
+        }
+        on_chunk(size);
+        return Ok((ManifestEntryStatus::Downloaded, target));
+    }
+    let mut response = response.error_for_status().map_err(http_error)?;
+
+    let resumed_from = resumed_from(&response, existing);
+    let expected_size = response
+        .content_length()
+        .map(|length| length + resumed_from)
+        .or(entry.size);
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let mut hasher = algorithm.hasher();
+    let mut file = open_partial(&partial_path, resumed_from, &mut hasher).await?;
+    let resume_state = serde_json::to_string_pretty(&ResumeState::for_entry(entry))
+        .map_err(std::io::Error::from)?;
+    tokio::fs::write(&resume_path, resume_state).await?;
+    let mut downloaded = resumed_from;
+
+    while let Some(chunk) = response.chunk().await.map_err(http_error)? {
+        file.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        on_chunk(downloaded);
+    }
+    file.flush().await?;
+    drop(file);
+
+    if let Some(expected_size) = expected_size {
+        if let Err(e) = check_download_size(&partial_path, expected_size) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(e);
+        }
+    }
+
+    let actual = hasher.finalize_hex();
+    if let Some(expected) = &entry.expected_checksum {
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(DownloadError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    let target = match content_type.filter(|_| fix_extension) {
+        Some(content_type) => with_mime_extension(&target, &content_type),
+        None => target,
+    };
+    tokio::fs::rename(&partial_path, &target).await?;
+    let _ = tokio::fs::remove_file(&resume_path).await;
+
+    // Only reached once the size and checksum checks above have passed
+    if write_marker {
+        let marker = DoneMarker {
+            checksum: actual,
+            algorithm: algorithm.name().to_string(),
+            size: downloaded,
+        };
+        write_done_marker(&target, &marker)?;
+    }
+    Ok((ManifestEntryStatus::Downloaded, target))
+}
+
+/// Options for streaming a single download to disk with `stream_to_file`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamOptions {
+    pub expected_checksum: Option<String>,
+    /// Algorithm of `expected_checksum`, defaults to sha256
+    pub checksum_algorithm: Option<String>,
+    /// Continue an existing `.part` file with a `Range` request instead of starting over
+    pub resume: bool,
+    /// Cap on the transfer rate, unlimited when unset
+    pub max_bytes_per_sec: Option<u64>,
+    /// Refuse objects larger than this, judged by `Content-Length` up front and by the
+    /// bytes received when the server doesn't send one
+    pub max_size_bytes: Option<u64>,
+    pub headers: HashMap<String, String>,
+    pub connect_timeout_ms: u64,
+    pub read_timeout_ms: u64,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            expected_checksum: None,
+            checksum_algorithm: None,
+            resume: false,
+            max_bytes_per_sec: None,
+            max_size_bytes: None,
+            headers: HashMap::new(),
+            connect_timeout_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+            read_timeout_ms: DEFAULT_READ_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Leading bytes of a partial download compared with the remote object before resuming
+const PREFIX_CHECK_LEN: u64 = 1024;
+
+/// Whether the partial download at `partial_path` starts with the same bytes as the
+/// object at `url`, fetched with a small `Range` request. A missing or empty partial
+/// trivially matches.
+pub async fn partial_matches_remote(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    partial_path: &Path,
+) -> Result<bool, DownloadError> {
+    use tokio::io::AsyncReadExt;
+
+    let local_len = match tokio::fs::metadata(partial_path).await {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e.into()),
+    };
+    let length = local_len.min(PREFIX_CHECK_LEN);
+    if length == 0 {
+        return Ok(true);
+    }
+    let mut local = Vec::with_capacity(length as usize);
+    tokio::fs::File::open(partial_path)
+        .await?
+        .take(length)
+        .read_to_end(&mut local)
+        .await?;
+
+    let mut request_headers = headers.clone();
+    let range = HeaderValue::from_str(&format!("bytes=0-{}", length - 1))
+        .map_err(|_| DownloadError::InvalidHeader("invalid range".to_string()))?;
+    request_headers.insert(reqwest::header::RANGE, range);
+    let mut response = client
+        .get(url)
+        .headers(request_headers)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(http_error)?;
+
+    // A server that ignores Range sends the whole object; only its start is needed
+    let mut remote = Vec::with_capacity(length as usize);
+    while (remote.len() as u64) < length {
+        match response.chunk().await.map_err(http_error)? {
+            Some(chunk) => remote.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    remote.truncate(length as usize);
+    Ok(remote == local)
+}
+
+/// Check the partial download of `target` against `url` and delete it, along with its
+/// resume sidecar, if the remote object has changed. Returns whether the partial was kept.
+pub async fn discard_partial_if_changed(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    target: &Path,
+) -> Result<bool, DownloadError> {
+    let partial_path = partial_path_for(target);
+    if partial_matches_remote(client, url, headers, &partial_path).await? {
+        return Ok(true);
+    }
+    log::warn!(
+        "Partial download of {} no longer matches the remote object, restarting",
+        target.display()
+    );
+    tokio::fs::remove_file(&partial_path).await?;
+    let _ = tokio::fs::remove_file(resume_path_for(target)).await;
+    Ok(false)
+}
+
+/// Feed the current contents of `path` to `hasher`, so a resumed download is
+/// verified over the whole file and not just the bytes fetched this time
+async fn hash_existing(path: &Path, hasher: &mut ChecksumHasher) -> Result<(), DownloadError> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// `Range` header asking for everything after the `existing` bytes of a partial file
+fn range_from(existing: u64) -> Result<HeaderValue, DownloadError> {
+    HeaderValue::from_str(&format!("bytes={}-", existing))
+        .map_err(|_| DownloadError::InvalidHeader("invalid range".to_string()))
+}
+
+/// Offset `response` continues from. A server that ignores Range answers 200 with the
+/// whole body, which has to replace the partial file rather than extend it.
+fn resumed_from(response: &reqwest::Response, existing: u64) -> u64 {
+    match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => existing,
+        _ => 0,
+    }
+}
+
+/// Whether `response` refuses a resume from `existing` bytes with 416 Range Not
+/// Satisfiable, which servers send when the partial already holds the whole object
+fn range_not_satisfiable(response: &reqwest::Response, existing: u64) -> bool {
+    existing > 0 && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+}
+
+/// Object size from the `Content-Range: bytes */<size>` of a 416 response
+fn unsatisfied_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes */")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Check a partial file the server wouldn't extend (see `range_not_satisfiable`) before
+/// it is renamed into place. Its size has to match the total from `Content-Range`, or
+/// `expected_size` when the server leaves that out, and its digest `expected_checksum`
+/// when given; with neither a size nor a checksum to go by it isn't trusted. A partial
+/// that fails is removed with its resume sidecar so the next attempt starts over.
+/// Returns the size and digest of the complete partial.
+async fn verify_complete_partial(
+    response: &reqwest::Response,
+    partial_path: &Path,
+    resume_path: &Path,
+    expected_size: Option<u64>,
+    expected_checksum: Option<&str>,
+    algorithm: ChecksumAlgorithm,
+) -> Result<(u64, String), DownloadError> {
+    let expected_size = unsatisfied_range_total(response).or(expected_size);
+    let size = tokio::fs::metadata(partial_path).await?.len();
+    let mut hasher = algorithm.hasher();
+    hash_existing(partial_path, &mut hasher).await?;
+    let actual = hasher.finalize_hex();
+
+    let failure = match (expected_size, expected_checksum) {
+        (Some(expected), _) if expected != size => Some(DownloadError::SizeMismatch {
+            expected,
+            actual: size,
+        }),
+        (_, Some(expected)) if !actual.eq_ignore_ascii_case(expected) => Some(DownloadError::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: actual.clone(),
+        }),
+        (None, None) => Some(DownloadError::Http(format!(
+            "{} for a partial download that can't be verified",
+            response.status()
+        ))),
+        _ => None,
+    };
+    if let Some(e) = failure {
+        let _ = tokio::fs::remove_file(partial_path).await;
+        let _ = tokio::fs::remove_file(resume_path).await;
+        return Err(e);
+    }
+    Ok((size, actual))
+}
+
+/// Open the partial file for writing the response body: appended to after hashing
+/// what it already holds when resuming, truncated otherwise
+async fn open_partial(
+    partial_path: &Path,
+    resumed_from: u64,
+    hasher: &mut ChecksumHasher,
+) -> Result<tokio::fs::File, DownloadError> {
+    if resumed_from > 0 {
+        hash_existing(partial_path, hasher).await?;
+        Ok(tokio::fs::OpenOptions::new().append(true).open(partial_path).await?)
+    } else {
+        Ok(tokio::fs::File::create(partial_path).await?)
+    }
+}
+
+/// Stream `url` into a `.part` file beside `target` without going through the
+/// frontend, hashing on the fly. The next chunk is only pulled once the previous
+/// one is written, so a slow disk slows the transfer instead of filling memory.
+///
+/// `cancel_task(task_id)` stops the transfer and keeps the partial file and its
+/// resume sidecar for a later call with `resume`, which first checks the partial
+/// still matches the remote object and restarts if not. The file is renamed into place
+/// once its size and checksum (if given) match. Going over `max_size_bytes` fails with
+/// `FileTooLarge` and removes the partial file. `read_file_chunk` refuses `target` until
+/// this returns. Returns the final size.
+pub async fn stream_to_file<F>(
+    client: &reqwest::Client,
+    url: &str,
+    target: &Path,
+    task_id: &str,
+    options: &StreamOptions,
+    on_progress: F,
+) -> Result<u64, DownloadError>
+where
+    F: Fn(DownloadProgress),
+{
+    let guard = CancellationGuard::register(task_id);
+    let algorithm = match &options.checksum_algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(name)?,
+        None => ChecksumAlgorithm::default(),
+    };
+    let mut headers = build_request_headers(&options.headers)?;
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // Registered once the parent exists, so readers resolve the path to the same key
+    let _writing = WriteInProgressGuard::register(target);
+
+    let partial_path = partial_path_for(target);
+    let resume_path = resume_path_for(target);
+    let existing = if options.resume && discard_partial_if_changed(client, url, &headers, target).await? {
+        tokio::fs::metadata(&partial_path).await.map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+    if existing > 0 {
+        headers.insert(reqwest::header::RANGE, range_from(existing)?);
+    }
+
+    let response = tokio::select! {
+        biased;
+        _ = guard.cancelled() => return Err(DownloadError::Cancelled),
+        response = client.get(url).headers(headers).send() => response.map_err(http_error)?,
+    };
+    if range_not_satisfiable(&response, existing) {
+        let recorded_size = tokio::fs::read_to_string(&resume_path)
+            .await
+            .ok()
+            .and_then(|contents| serde_json::from_str::<ResumeState>(&contents).ok())
+            .and_then(|state| state.size);
+        let checksum = options.expected_checksum.as_deref();
+        let (size, _) =
+            verify_complete_partial(&response, &partial_path, &resume_path, recorded_size, checksum, algorithm)
+                .await?;
+        tokio::fs::rename(&partial_path, target).await?;
+        let _ = tokio::fs::remove_file(&resume_path).await;
+        on_progress(DownloadProgress::new(task_id.to_string(), size, size, 0.0));
+        return Ok(size);
+    }
+    let mut response = response.error_for_status().map_err(http_error)?;
+
+    let resumed_from = resumed_from(&response, existing);
+    let total_bytes = response.content_length().map(|length| length + resumed_from);
+    if let (Some(limit), Some(size)) = (options.max_size_bytes, total_bytes) {
+        if size > limit {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(DownloadError::FileTooLarge { size, limit });
+        }
+    }
+
+    let mut hasher = algorithm.hasher();
+    let mut file = open_partial(&partial_path, resumed_from, &mut hasher).await?;
+    let resume_state = ResumeState {
+        url: url.to_string(),
+        target_path: target.to_string_lossy().to_string(),
+        partial_path: partial_path.to_string_lossy().to_string(),
+        expected_checksum: options.expected_checksum.clone(),
+        size: total_bytes,
+    };
+    tokio::fs::write(
+        &resume_path,
+        serde_json::to_string_pretty(&resume_state).map_err(std::io::Error::from)?,
+    )
+    .await?;
+
+    let started = Instant::now();
+    let mut downloaded = resumed_from;
+    let cancelled = loop {
+        let chunk = tokio::select! {
+            biased;
+            _ = guard.cancelled() => break true,
+            chunk = response.chunk() => chunk.map_err(http_error)?,
+        };
+        let Some(chunk) = chunk else {
+            break false;
+        };
+        let received = downloaded + chunk.len() as u64;
+        if let Some(limit) = options.max_size_bytes.filter(|&limit| received > limit) {
+            drop(file);
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(DownloadError::FileTooLarge { size: received, limit });
+        }
+        file.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        downloaded = received;
+
+        let transferred = downloaded - resumed_from;
+        let elapsed = started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            transferred as f64 / elapsed
+        } else {
+            0.0
+        };
+        on_progress(DownloadProgress::new(
+            task_id.to_string(),
+            downloaded,
+            total_bytes.unwrap_or(0),
+            speed,
+        ));
+
+        // Hold back until the average rate since the start is under the cap
+        if let Some(rate) = options.max_bytes_per_sec.filter(|&rate| rate > 0) {
+            let due = Duration::from_secs_f64(transferred as f64 / rate as f64);
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                tokio::select! {
+                    biased;
+                    _ = guard.cancelled() => break true,
+                    _ = tokio::time::sleep(wait) => {}
+                }
+            }
+        }
+    };
+    file.flush().await?;
+    drop(file);
+    if cancelled {
+        return Err(DownloadError::Cancelled);
+    }
+
+    if let Some(expected_size) = total_bytes {
+        if let Err(e) = check_download_size(&partial_path, expected_size) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(e);
+        }
+    }
+
+    if let Some(expected) = &options.expected_checksum {
+        let actual = hasher.finalize_hex();
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            let _ = tokio::fs::remove_file(&resume_path).await;
+            return Err(DownloadError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    tokio::fs::rename(&partial_path, target).await?;
+    let _ = tokio::fs::remove_file(&resume_path).await;
+    Ok(downloaded)
+}
+
+/// Download every manifest entry through `queue`, so manifests share its concurrency
+/// limit with other queued downloads. Entries that already match are skipped, and a
+/// failed entry is recorded without stopping the rest unless `fail_fast` is set.
+/// Results are returned in manifest order.
+pub async fn process_manifest<F>(
+    client: reqwest::Client,
+    queue: &DownloadQueue,
+    manifest: Vec<ManifestEntry>,
+    options: ManifestOptions,
+    on_progress: F,
+) -> Result<Vec<ManifestEntryResult>, DownloadError>
+where
+    F: Fn(ManifestProgress) + Send + Sync + 'static,
+{
+    let algorithm = match &options.checksum_algorithm {
+        Some(name) => ChecksumAlgorithm::from_name(name)?,
+        None => ChecksumAlgorithm::default(),
+    };
+    let headers = Arc::new(build_request_headers(&options.headers)?);
+    let total_entries = manifest.len();
+    let sequence = MANIFEST_SEQUENCE.fetch_add(1, Ordering::SeqCst);
+    let stop = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicUsize::new(0));
+    let on_progress = Arc::new(on_progress);
+    let mut tasks = Vec::with_capacity(total_entries);
+
+    for (entry_index, entry) in manifest.into_iter().enumerate() {
+        let client = client.clone();
+        let headers = Arc::clone(&headers);
+        let stop = Arc::clone(&stop);
+        let finished = Arc::clone(&finished);
+        let on_progress = Arc::clone(&on_progress);
+        let fail_fast = options.fail_fast;
+        let fix_extensions = options.fix_extensions;
+        let write_done_markers = options.write_done_markers;
+
+        let task_id = format!("manifest-{}-{}", sequence, entry_index);
+        let queued_entry = (entry.url.clone(), entry.target_path.clone());
+        let handle = queue.enqueue(&task_id, move |_guard| async move {
+            if stop.load(Ordering::SeqCst) {
+                return Ok((entry, ManifestEntryStatus::Skipped, None));
+            }
+
+            let started = Instant::now();
+            let report = |entry_downloaded_bytes: u64, finished_entries: usize| {
+                let elapsed = started.elapsed().as_secs_f64();
+                let speed = if elapsed > 0.0 {
+                    entry_downloaded_bytes as f64 / elapsed
+                } else {
+                    0.0
+                };
+                on_progress(ManifestProgress {
+                    entry_index,
+                    entry_downloaded_bytes,
+                    entry_total_bytes: entry.size,
+                    finished_entries,
+                    total_entries,
+                    eta_secs: estimate_eta(entry_downloaded_bytes, entry.size.unwrap_or(0), speed),
+                });
+            };
+
+            let outcome = download_entry(
+                &client,
+                &entry,
+                &headers,
+                algorithm,
+                fix_extensions,
+                write_done_markers,
+                |downloaded| report(downloaded, finished.load(Ordering::SeqCst)),
+            )
+            .await;
+            let finished_entries = finished.fetch_add(1, Ordering::SeqCst) + 1;
+
+            match outcome {
+                Ok((status, final_target)) => {
+                    let size = std::fs::metadata(&final_target).map(|m| m.len()).unwrap_or(0);
+                    report(size, finished_entries);
+                    let entry = ManifestEntry {
+                        target_path: final_target.to_string_lossy().to_string(),
+                        ..entry
+                    };
+                    Ok((entry, status, None))
+                }
+                Err(e) => {
+                    if fail_fast {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                    report(0, finished_entries);
+                    Ok((entry, ManifestEntryStatus::Failed, Some(e.to_string())))
+                }
+            }
+        });
+        tasks.push((queued_entry, handle));
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for ((url, target_path), task) in tasks {
+        let outcome = task
+            .await
+            .map_err(|e| DownloadError::Http(format!("Download task failed: {}", e)))?;
+        let result = match outcome {
+            Ok((entry, status, error)) => ManifestEntryResult {
+                url: entry.url,
+                target_path: entry.target_path,
+                status,
+                error,
+            },
+            // Only reachable if the queued task was cancelled before it ran
+            Err(e) => ManifestEntryResult {
+                url,
+                target_path,
+                status: ManifestEntryStatus::Skipped,
+                error: Some(e.to_string()),
+            },
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+// Tauri commands
+#[tauri::command]
+pub async fn download_manifest(
+    app_handle: AppHandle,
+    queue: State<'_, DownloadQueue>,
+    manifest: Vec<ManifestEntry>,
+    options: Option<ManifestOptions>,
+) -> Result<Vec<ManifestEntryResult>, String> {
+    let options = options.unwrap_or_default();
+    let client = build_http_client(&options).map_err(|e| e.to_string())?;
+    process_manifest(client, &queue, manifest, options, move |progress| {
+        let _ = app_handle.emit("manifest-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Download `url` to `target_path` in the backend, emitting `download-progress` events.
+/// Avoids shipping every chunk over the bridge as `write_file_chunk` would.
+#[tauri::command]
+pub async fn download_to_file(
+    app_handle: AppHandle,
+    url: String,
+    target_path: String,
+    task_id: String,
+    options: Option<StreamOptions>,
+) -> Result<u64, String> {
+    let options = options.unwrap_or_default();
+    let client = http_client(options.connect_timeout_ms, options.read_timeout_ms)
+        .map_err(|e| e.to_string())?;
+    stream_to_file(&client, &url, Path::new(&target_path), &task_id, &options, |progress| {
+        let _ = app_handle.emit("download-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// `download_to_file` through the app's download queue, waiting for a free slot first.
+/// `cancel_queued_download` stops it whether it is still waiting or already running.
+#[tauri::command]
+pub async fn queue_download_to_file(
+    app_handle: AppHandle,
+    queue: State<'_, DownloadQueue>,
+    url: String,
+    target_path: String,
+    task_id: String,
+    options: Option<StreamOptions>,
+) -> Result<u64, String> {
+    let options = options.unwrap_or_default();
+    let client = http_client(options.connect_timeout_ms, options.read_timeout_ms)
+        .map_err(|e| e.to_string())?;
+    let queued_id = task_id.clone();
+    let handle = queue.enqueue(&queued_id, move |_guard| async move {
+        stream_to_file(&client, &url, Path::new(&target_path), &task_id, &options, |progress| {
+            let _ = app_handle.emit("download-progress", progress);
+        })
+        .await
+    });
+    handle
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Before resuming, check the partial download of `target_path` still matches the
+/// remote object, discarding it if not. Returns whether the partial was kept.
+#[tauri::command]
+pub async fn verify_partial_download(
+    url: String,
+    target_path: String,
+    headers: Option<HashMap<String, String>>,
+) -> Result<bool, String> {
+    let headers = build_request_headers(&headers.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let client = http_client(DEFAULT_CONNECT_TIMEOUT_MS, DEFAULT_READ_TIMEOUT_MS)
+        .map_err(|e| e.to_string())?;
+    discard_partial_if_changed(&client, &url, &headers, Path::new(&target_path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Whether an external tool can treat `path` as a finished, verified download
+#[tauri::command]
+pub async fn is_download_marked_done(path: String) -> Result<bool, String> {
+    tokio::task::spawn_blocking(move || is_marked_done(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn relocate_downloads(old_dir: String, new_dir: String) -> Result<RelocationReport, String> {
+    tokio::task::spawn_blocking(move || relocate_partial_downloads(Path::new(&old_dir), Path::new(&new_dir)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    // Create a fresh directory per test so parallel tests don't clobber each other
+    fn unique_test_dir(label: &str) -> PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = env::temp_dir().join(format!("s3-upload-tool-{}-{}", label, timestamp));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Minimal HTTP/1.1 server serving fixed bodies by path and recording request heads
+    struct MockServer {
+        base_url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        fn start(routes: HashMap<String, Vec<u8>>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let base_url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&requests);
+
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { break };
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut head = String::new();
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                            break;
+                        }
+                        head.push_str(&line);
+                    }
+                    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+                    // Honour `Range: bytes=N-` so resumed downloads can be exercised
+                    let range_start = head
+                        .lines()
+                        .find_map(|line| line.to_ascii_lowercase().strip_prefix("range: bytes=").map(str::to_string))
+                        .and_then(|range| range.trim().trim_end_matches('-').parse::<usize>().ok());
+                    recorded.lock().unwrap().push(head);
+
+                    let response = match routes.get(&path) {
+                        Some(body) if range_start.is_some_and(|start| start >= body.len()) => format!(
+                            "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes(),
+                        Some(body) if range_start.is_some_and(|start| start < body.len()) => {
+                            let start = range_start.unwrap_or(0);
+                            let mut response = format!(
+                                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                                body.len() - start,
+                                start,
+                                body.len() - 1,
+                                body.len()
+                            )
+                            .into_bytes();
+                            response.extend_from_slice(&body[start..]);
+                            response
+                        }
+                        Some(body) => {
+                            let mut response = format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                                body.len()
+                            )
+                            .into_bytes();
+                            response.extend_from_slice(body);
+                            response
+                        }
+                        None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec(),
+                    };
+                    let _ = stream.write_all(&response);
+                }
+            });
+
+            MockServer { base_url, requests }
+        }
+
+        fn url(&self, path: &str) -> String {
+            format!("{}{}", self.base_url, path)
+        }
+
+        fn request_count(&self, path: &str) -> usize {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|head| head.split_whitespace().nth(1) == Some(path))
+                .count()
+        }
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        use sha2::Digest;
+        format!("{:x}", sha2::Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_manifest_skips_present_and_records_mismatch() {
+        let dir = unique_test_dir("manifest");
+        let present = b"already downloaded".to_vec();
+        let fresh = b"fresh object body".to_vec();
+        let corrupt = b"unexpected body".to_vec();
+
+        let server = MockServer::start(HashMap::from([
+            ("/present".to_string(), present.clone()),
+            ("/fresh".to_string(), fresh.clone()),
+            ("/corrupt".to_string(), corrupt.clone()),
+        ]));
+        fs::write(dir.join("present.txt"), &present).unwrap();
+
+        let entry = |name: &str, target: &str, checksum: String, size: usize| ManifestEntry {
+            url: server.url(name),
+            target_path: dir.join(target).to_string_lossy().to_string(),
+            expected_checksum: Some(checksum),
+            size: Some(size as u64),
+        };
+        let manifest = vec![
+            entry("/present", "present.txt", sha256_hex(&present), present.len()),
+            entry("/fresh", "fresh.txt", sha256_hex(&fresh), fresh.len()),
+            entry("/corrupt", "corrupt.txt", sha256_hex(b"what we expected"), corrupt.len()),
+        ];
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let results = process_manifest(
+            reqwest::Client::new(),
+            &DownloadQueue::default(),
+            manifest,
+            ManifestOptions::default(),
+            move |progress| recorded.lock().unwrap().push(progress),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results[0].status, ManifestEntryStatus::AlreadyPresent);
+        assert_eq!(results[1].status, ManifestEntryStatus::Downloaded);
+        assert_eq!(results[2].status, ManifestEntryStatus::Failed);
+        assert!(results[2].error.as_deref().unwrap().contains("Checksum mismatch"));
+
+        assert_eq!(server.request_count("/present"), 0);
+        assert_eq!(fs::read(dir.join("fresh.txt")).unwrap(), fresh);
+        assert!(!dir.join("corrupt.txt").exists());
+        assert!(!partial_path_for(&dir.join("corrupt.txt")).exists());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.iter().map(|e| e.finished_entries).max(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_resumes_partial_left_for_the_same_entry() {
+        let dir = unique_test_dir("manifest-resume");
+        let body = b"0123456789abcdefghijklmnopqrstuvwxyz".to_vec();
+        let server = MockServer::start(HashMap::from([("/object".to_string(), body.clone())]));
+        let target = dir.join("object.bin");
+        let entry = ManifestEntry {
+            url: server.url("/object"),
+            target_path: target.to_string_lossy().to_string(),
+            expected_checksum: Some(sha256_hex(&body)),
+            size: Some(body.len() as u64),
+        };
+        fs::write(partial_path_for(&target), &body[..10]).unwrap();
+        fs::write(
+            resume_path_for(&target),
+            serde_json::to_string(&ResumeState::for_entry(&entry)).unwrap(),
+        )
+        .unwrap();
+
+        let queue = DownloadQueue::default();
+        let options = ManifestOptions::default();
+        let results = process_manifest(reqwest::Client::new(), &queue, vec![entry], options, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].status, ManifestEntryStatus::Downloaded);
+        assert_eq!(fs::read(&target).unwrap(), body);
+        let heads = server.requests.lock().unwrap().clone();
+        assert!(heads[0].to_ascii_lowercase().contains("range: bytes=10-"));
+        assert!(!partial_path_for(&target).exists());
+        assert!(!resume_path_for(&target).exists());
+    }
+
+    #[tokio::test]
+    async fn test_fixed_extension_neither_overwrites_nor_downloads_twice() {
+        let dir = unique_test_dir("manifest-fix-extension");
+        let body = b"%PDF-1.4 report body".to_vec();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/report", listener.local_addr().unwrap());
+        let served = body.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    served.len()
+                );
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&served);
+            }
+        });
+
+        let existing = dir.join("report.pdf");
+        fs::write(&existing, b"someone else's report").unwrap();
+        let entry = |name: &str| ManifestEntry {
+            url: url.clone(),
+            target_path: dir.join(name).to_string_lossy().to_string(),
+            expected_checksum: Some(sha256_hex(&body)),
+            size: Some(body.len() as u64),
+        };
+        let options = ManifestOptions {
+            fix_extensions: true,
+            ..Default::default()
+        };
+        let queue = DownloadQueue::default();
+
+        let manifest = vec![entry("report")];
+        let first = process_manifest(reqwest::Client::new(), &queue, manifest, options.clone(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(first[0].status, ManifestEntryStatus::Downloaded);
+        let stored = dir.join("report (1).pdf");
+        assert_eq!(first[0].target_path, stored.to_string_lossy());
+        assert_eq!(fs::read(&stored).unwrap(), body);
+        assert_eq!(fs::read(&existing).unwrap(), b"someone else's report");
+
+        // Once stored under the mime extension, a rerun finds it there
+        fs::rename(&existing, dir.join("elsewhere.pdf")).unwrap();
+        fs::rename(&stored, &existing).unwrap();
+        let second = process_manifest(reqwest::Client::new(), &queue, vec![entry("report")], options, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(second[0].status, ManifestEntryStatus::AlreadyPresent);
+        assert_eq!(second[0].target_path, existing.to_string_lossy());
+    }
+
+    #[tokio::test]
+    async fn test_done_marker_written_only_for_verified_downloads() {
+        let dir = unique_test_dir("manifest-done-marker");
+        let body = b"verified object body".to_vec();
+        let server = MockServer::start(HashMap::from([
+            ("/good".to_string(), body.clone()),
+            ("/bad".to_string(), b"tampered body".to_vec()),
+        ]));
+        let entry = |name: &str, target: &str, checksum: String| ManifestEntry {
+            url: server.url(name),
+            target_path: dir.join(target).to_string_lossy().to_string(),
+            expected_checksum: Some(checksum),
+            size: None,
+        };
+        let manifest = vec![
+            entry("/good", "good.bin", sha256_hex(&body)),
+            entry("/bad", "bad.bin", sha256_hex(b"original body")),
+        ];
+        let options = ManifestOptions {
+            write_done_markers: true,
+            ..Default::default()
+        };
+
+        let queue = DownloadQueue::default();
+        let results = process_manifest(reqwest::Client::new(), &queue, manifest, options, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, ManifestEntryStatus::Downloaded);
+        assert_eq!(results[1].status, ManifestEntryStatus::Failed);
+
+        let good = dir.join("good.bin");
+        let marker: DoneMarker =
+            serde_json::from_str(&fs::read_to_string(done_marker_path_for(&good)).unwrap()).unwrap();
+        assert_eq!(
+            marker,
+            DoneMarker {
+                checksum: sha256_hex(&body),
+                algorithm: "sha256".to_string(),
+                size: body.len() as u64,
+            }
+        );
+        assert!(is_marked_done(&good).unwrap());
+
+        let bad = dir.join("bad.bin");
+        assert!(!done_marker_path_for(&bad).exists());
+        assert!(!is_marked_done(&bad).unwrap());
+
+        // A file that changed size since it was marked no longer counts as done
+        fs::write(&good, b"overwritten").unwrap();
+        assert!(!is_marked_done(&good).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_sends_custom_headers_and_rejects_reserved() {
+        let dir = unique_test_dir("manifest-headers");
+        let server = MockServer::start(HashMap::from([("/paid".to_string(), b"body".to_vec())]));
+        let manifest = vec![ManifestEntry {
+            url: server.url("/paid"),
+            target_path: dir.join("paid.bin").to_string_lossy().to_string(),
+            expected_checksum: None,
+            size: None,
+        }];
+
+        let options = ManifestOptions {
+            headers: HashMap::from([("x-amz-request-payer".to_string(), "requester".to_string())]),
+            ..Default::default()
+        };
+        let queue = DownloadQueue::default();
+        let results = process_manifest(reqwest::Client::new(), &queue, manifest.clone(), options, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(results[0].status, ManifestEntryStatus::Downloaded);
+        let heads = server.requests.lock().unwrap().clone();
+        assert!(heads[0].to_ascii_lowercase().contains("x-amz-request-payer: requester"));
+
+        for reserved in ["Host", "content-length"] {
+            let options = ManifestOptions {
+                headers: HashMap::from([(reserved.to_string(), "1".to_string())]),
+                ..Default::default()
+            };
+            let result = process_manifest(reqwest::Client::new(), &queue, manifest.clone(), options, |_| {}).await;
+            assert!(matches!(result, Err(DownloadError::InvalidHeader(_))));
+        }
+        assert_eq!(server.request_count("/paid"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout_keeps_partial_file() {
+        let dir = unique_test_dir("manifest-read-timeout");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stall", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nfirst bits");
+                let _ = stream.flush();
+                // Stall well past the read timeout without closing the connection
+                std::thread::sleep(std::time::Duration::from_secs(3));
+            }
+        });
+
+        let target = dir.join("stalled.bin");
+        let entry = ManifestEntry {
+            url,
+            target_path: target.to_string_lossy().to_string(),
+            expected_checksum: None,
+            size: Some(100),
+        };
+        let options = ManifestOptions {
+            read_timeout_ms: 300,
+            ..Default::default()
+        };
+        let client = build_http_client(&options).unwrap();
+
+        let started = Instant::now();
+        let error = download_entry(
+            &client,
+            &entry,
+            &HeaderMap::new(),
+            ChecksumAlgorithm::default(),
+            false,
+            false,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert!(matches!(&error, DownloadError::Http(message) if message.starts_with("Timed out")));
+        assert!(error.is_retryable());
+        assert!(partial_path_for(&target).exists());
+        assert!(resume_path_for(&target).exists());
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_read_timeout_resumes_with_range() {
+        let dir = unique_test_dir("manifest-timeout-retry");
+        let body: Vec<u8> = (0..100u8).collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stall", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let served = body.clone();
+        std::thread::spawn(move || {
+            let mut stalled = Vec::new();
+            for attempt in 0..2 {
+                let Ok((mut stream, _)) = listener.accept() else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                    head.push_str(&line);
+                    line.clear();
+                }
+                recorded.lock().unwrap().push(head);
+                if attempt == 0 {
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n");
+                    let _ = stream.write_all(&served[..10]);
+                    let _ = stream.flush();
+                    // Keep the connection open without sending the rest
+                    stalled.push(stream);
+                } else {
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 206 Partial Content\r\nContent-Length: 90\r\nContent-Range: bytes 10-99/100\r\nConnection: close\r\n\r\n",
+                    );
+                    let _ = stream.write_all(&served[10..]);
+                }
+            }
+        });
+
+        let target = dir.join("stalled.bin");
+        let entry = ManifestEntry {
+            url,
+            target_path: target.to_string_lossy().to_string(),
+            expected_checksum: Some(sha256_hex(&body)),
+            size: Some(100),
+        };
+        let options = ManifestOptions {
+            read_timeout_ms: 300,
+            ..Default::default()
+        };
+        let client = build_http_client(&options).unwrap();
+        let algorithm = ChecksumAlgorithm::default();
+
+        let error = download_entry(&client, &entry, &HeaderMap::new(), algorithm, false, false, |_| {})
+            .await
+            .unwrap_err();
+        assert!(error.is_retryable());
+        assert_eq!(fs::read(partial_path_for(&target)).unwrap(), &body[..10]);
+
+        let (status, _) = download_entry(&client, &entry, &HeaderMap::new(), algorithm, false, false, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(status, ManifestEntryStatus::Downloaded);
+        assert_eq!(fs::read(&target).unwrap(), body);
+        let heads = requests.lock().unwrap().clone();
+        assert!(!heads[0].to_ascii_lowercase().contains("range:"));
+        assert!(heads[1].to_ascii_lowercase().contains("range: bytes=10-"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_to_file_full_download() {
+        let dir = unique_test_dir("stream-full");
+        let body: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+        let server = MockServer::start(HashMap::from([("/large".to_string(), body.clone())]));
+        let target = dir.join("large.bin");
+        let options = StreamOptions {
+            expected_checksum: Some(sha256_hex(&body)),
+            ..Default::default()
+        };
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let size = stream_to_file(
+            &reqwest::Client::new(),
+            &server.url("/large"),
+            &target,
+            "stream-full-test",
+            &options,
+            move |progress| recorded.lock().unwrap().push(progress),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(fs::read(&target).unwrap(), body);
+        assert!(!partial_path_for(&target).exists());
+        assert!(!resume_path_for(&target).exists());
+        let events = events.lock().unwrap();
+        let last = events.last().unwrap();
+        assert_eq!(last.downloaded_bytes, body.len() as u64);
+        assert_eq!(last.total_bytes, body.len() as u64);
+        assert_eq!(last.task_id, "stream-full-test");
+    }
+
+    #[tokio::test]
+    async fn test_stream_to_file_cancel_keeps_partial() {
+        let dir = unique_test_dir("stream-cancel");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stall", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                    line.clear();
+                }
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nfirst bits");
+                let _ = stream.flush();
+                std::thread::sleep(std::time::Duration::from_secs(3));
+            }
+        });
+
+        let target = dir.join("cancelled.bin");
+        let received = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&received);
+        let worker_target = target.clone();
+        let download = tokio::spawn(async move {
+            stream_to_file(
+                &reqwest::Client::new(),
+                &url,
+                &worker_target,
+                "stream-cancel-test",
+                &StreamOptions::default(),
+                move |_| flag.store(true, Ordering::SeqCst),
+            )
+            .await
+        });
+
+        let started = Instant::now();
+        while !received.load(Ordering::SeqCst) {
+            assert!(started.elapsed() < std::time::Duration::from_secs(2));
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(crate::download::cancel_task("stream-cancel-test"));
+
+        let result = download.await.unwrap();
+        assert!(matches!(result, Err(DownloadError::Cancelled)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(fs::read(partial_path_for(&target)).unwrap(), b"first bits");
+        assert!(resume_path_for(&target).exists());
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn test_stream_to_file_enforces_max_size() {
+        let dir = unique_test_dir("stream-max-size");
+        let body = vec![5u8; 10_000];
+        let server = MockServer::start(HashMap::from([("/object".to_string(), body.clone())]));
+        let client = reqwest::Client::new();
+
+        let allowed = dir.join("allowed.bin");
+        let options = StreamOptions {
+            max_size_bytes: Some(body.len() as u64),
+            ..Default::default()
+        };
+        let size = stream_to_file(&client, &server.url("/object"), &allowed, "max-size-ok", &options, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(fs::read(&allowed).unwrap(), body);
+
+        // Resuming doesn't sneak past the cap: the existing bytes count towards it
+        let refused = dir.join("refused.bin");
+        fs::write(partial_path_for(&refused), &body[..4_000]).unwrap();
+        let options = StreamOptions {
+            resume: true,
+            max_size_bytes: Some(8_000),
+            ..Default::default()
+        };
+        match stream_to_file(&client, &server.url("/object"), &refused, "max-size-over", &options, |_| {}).await {
+            Err(DownloadError::FileTooLarge { size, limit }) => {
+                assert_eq!(size, body.len() as u64);
+                assert_eq!(limit, 8_000);
+            }
+            other => panic!("expected FileTooLarge, got {:?}", other),
+        }
+        assert!(!refused.exists());
+        assert!(!partial_path_for(&refused).exists());
+        assert!(!resume_path_for(&refused).exists());
+    }
+
+    #[tokio::test]
+    async fn test_stream_to_file_resumes_partial() {
+        let dir = unique_test_dir("stream-resume");
+        let body = b"0123456789 the rest of the object".to_vec();
+        let server = MockServer::start(HashMap::from([("/resume".to_string(), body.clone())]));
+        let target = dir.join("resumed.bin");
+        fs::write(partial_path_for(&target), &body[..10]).unwrap();
+
+        let options = StreamOptions {
+            expected_checksum: Some(sha256_hex(&body)),
+            resume: true,
+            ..Default::default()
+        };
+        let size = stream_to_file(
+            &reqwest::Client::new(),
+            &server.url("/resume"),
+            &target,
+            "stream-resume-test",
+            &options,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(fs::read(&target).unwrap(), body);
+        let heads = server.requests.lock().unwrap().clone();
+        assert!(heads.iter().any(|head| head.to_ascii_lowercase().contains("range: bytes=10-")));
+
+        // A partial that already holds the whole object gets a 416 and is finalised
+        let complete = dir.join("complete.bin");
+        fs::write(partial_path_for(&complete), &body).unwrap();
+        let size = stream_to_file(
+            &reqwest::Client::new(),
+            &server.url("/resume"),
+            &complete,
+            "stream-resume-complete-test",
+            &options,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(fs::read(&complete).unwrap(), body);
+        assert!(!partial_path_for(&complete).exists());
+    }
+
+    #[tokio::test]
+    async fn test_manifest_finalises_complete_partial_refused_with_416() {
+        let dir = unique_test_dir("manifest-416");
+        let body = b"an object the partial already holds".to_vec();
+        let server = MockServer::start(HashMap::from([("/object".to_string(), body.clone())]));
+        let client = reqwest::Client::new();
+
+        let complete = dir.join("complete.bin");
+        let entry = ManifestEntry {
+            url: server.url("/object"),
+            target_path: complete.to_string_lossy().to_string(),
+            expected_checksum: Some(sha256_hex(&body)),
+            size: None,
+        };
+        fs::write(partial_path_for(&complete), &body).unwrap();
+        fs::write(resume_path_for(&complete), serde_json::to_string(&ResumeState::for_entry(&entry)).unwrap())
+            .unwrap();
+        let (status, path) = download_entry(
+            &client,
+            &entry,
+            &HeaderMap::new(),
+            ChecksumAlgorithm::Sha256,
+            false,
+            false,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, ManifestEntryStatus::Downloaded);
+        assert_eq!(fs::read(&path).unwrap(), body);
+        assert!(!resume_path_for(&complete).exists());
+
+        // A partial with the right length but the wrong bytes is discarded
+        let corrupt = dir.join("corrupt.bin");
+        let entry = ManifestEntry {
+            target_path: corrupt.to_string_lossy().to_string(),
+            ..entry
+        };
+        fs::write(partial_path_for(&corrupt), vec![b'x'; body.len()]).unwrap();
+        fs::write(resume_path_for(&corrupt), serde_json::to_string(&ResumeState::for_entry(&entry)).unwrap())
+            .unwrap();
+        let result = download_entry(
+            &client,
+            &entry,
+            &HeaderMap::new(),
+            ChecksumAlgorithm::Sha256,
+            false,
+            false,
+            |_| {},
+        )
+        .await;
+        assert!(matches!(result, Err(DownloadError::ChecksumMismatch { .. })));
+        assert!(!corrupt.exists());
+        assert!(!partial_path_for(&corrupt).exists());
+        assert!(!resume_path_for(&corrupt).exists());
+    }
+
+    #[tokio::test]
+    async fn test_partial_prefix_check_keeps_or_discards() {
+        let dir = unique_test_dir("stream-prefix");
+        let body = b"0123456789 the current object".to_vec();
+        let server = MockServer::start(HashMap::from([("/object".to_string(), body.clone())]));
+        let client = reqwest::Client::new();
+        let url = server.url("/object");
+
+        let matching = dir.join("matching.bin");
+        fs::write(partial_path_for(&matching), &body[..10]).unwrap();
+        assert!(discard_partial_if_changed(&client, &url, &HeaderMap::new(), &matching).await.unwrap());
+        assert!(partial_path_for(&matching).exists());
+
+        let changed = dir.join("changed.bin");
+        fs::write(partial_path_for(&changed), b"9876543210").unwrap();
+        fs::write(resume_path_for(&changed), "{}").unwrap();
+        assert!(!discard_partial_if_changed(&client, &url, &HeaderMap::new(), &changed).await.unwrap());
+        assert!(!partial_path_for(&changed).exists());
+        assert!(!resume_path_for(&changed).exists());
+
+        // Resuming onto a changed object restarts from scratch instead of splicing
+        fs::write(partial_path_for(&changed), b"9876543210").unwrap();
+        let options = StreamOptions {
+            expected_checksum: Some(sha256_hex(&body)),
+            resume: true,
+            ..Default::default()
+        };
+        stream_to_file(&client, &url, &changed, "stream-prefix-test", &options, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&changed).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_fail_fast_skips_remaining() {
+        let dir = unique_test_dir("manifest-fail-fast");
+        let server = MockServer::start(HashMap::new());
+
+        let manifest = (0..3)
+            .map(|i| ManifestEntry {
+                url: server.url(&format!("/missing-{}", i)),
+                target_path: dir.join(format!("missing-{}.bin", i)).to_string_lossy().to_string(),
+                expected_checksum: None,
+                size: None,
+            })
+            .collect();
+        let options = ManifestOptions {
+            fail_fast: true,
+            ..Default::default()
+        };
+
+        let queue = DownloadQueue::new(1);
+        let results = process_manifest(reqwest::Client::new(), &queue, manifest, options, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].status, ManifestEntryStatus::Failed);
+        assert_eq!(results[1].status, ManifestEntryStatus::Skipped);
+        assert_eq!(results[2].status, ManifestEntryStatus::Skipped);
+    }
+
+    #[test]
+    fn test_relocate_rewrites_sidecars_and_moves_partials() {
+        let old_dir = unique_test_dir("relocate-old");
+        let new_dir = unique_test_dir("relocate-new");
+
+        for name in ["a.bin", "b.bin", "gone.bin", "taken.bin", "broken.bin"] {
+            let entry = ManifestEntry {
+                url: format!("https://example.com/{}", name),
+                target_path: old_dir.join(name).to_string_lossy().to_string(),
+                expected_checksum: None,
+                size: Some(10),
+            };
+            let state = ResumeState::for_entry(&entry);
+            fs::write(resume_path_for(&old_dir.join(name)), serde_json::to_string(&state).unwrap())
+                .unwrap();
+            if name != "gone.bin" {
+                fs::write(&state.partial_path, b"part").unwrap();
+            }
+        }
+
+        fs::write(resume_path_for(&old_dir.join("broken.bin")), "{ not json").unwrap();
+        fs::write(new_dir.join("taken.bin"), b"already here").unwrap();
+
+        let mut report = relocate_partial_downloads(&old_dir, &new_dir).unwrap();
+        report.moved.sort();
+        report.skipped.sort_by(|a, b| a.sidecar_path.cmp(&b.sidecar_path));
+        let moved: Vec<String> = ["a.bin", "b.bin"]
+            .iter()
+            .map(|name| new_dir.join(name).to_string_lossy().to_string())
+            .collect();
+        assert_eq!(report.moved, moved);
+        let skipped: Vec<&str> = report.skipped.iter().map(|skip| skip.sidecar_path.as_str()).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                resume_path_for(&old_dir.join("broken.bin")).to_str().unwrap(),
+                resume_path_for(&old_dir.join("taken.bin")).to_str().unwrap(),
+            ]
+        );
+        assert_eq!(fs::read(new_dir.join("taken.bin")).unwrap(), b"already here");
+        assert_eq!(fs::read(partial_path_for(&old_dir.join("taken.bin"))).unwrap(), b"part");
+        assert!(resume_path_for(&old_dir.join("taken.bin")).exists());
+
+        for name in ["a.bin", "b.bin"] {
+            let new_target = new_dir.join(name);
+            let sidecar = fs::read_to_string(resume_path_for(&new_target)).unwrap();
+            let state: ResumeState = serde_json::from_str(&sidecar).unwrap();
+            assert_eq!(state.target_path, new_target.to_string_lossy());
+            assert_eq!(state.url, format!("https://example.com/{}", name));
+            assert_eq!(fs::read(&state.partial_path).unwrap(), b"part");
+            assert!(!partial_path_for(&old_dir.join(name)).exists());
+            assert!(!resume_path_for(&old_dir.join(name)).exists());
+        }
+        assert!(resume_path_for(&old_dir.join("gone.bin")).exists());
+        assert!(!resume_path_for(&new_dir.join("gone.bin")).exists());
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// One completed download as recorded in the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    /// Unix seconds, filled in on append when missing
+    pub timestamp: Option<u64>,
+    pub source_url: String,
+    pub target_path: String,
+    pub size: u64,
+    pub checksum: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Strip presigned-URL credentials (`X-Amz-*` query parameters) from `url`
+pub fn redact_url(url: &str) -> String {
+    let (without_fragment, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    let (base, query) = match without_fragment.split_once('?') {
+        Some((base, query)) => (base, query),
+        None => return url.to_string(),
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or("");
+            !param.is_empty() && !name.to_ascii_lowercase().starts_with("x-amz-")
+        })
+        .collect();
+
+    let mut redacted = base.to_string();
+    if !kept.is_empty() {
+        redacted.push('?');
+        redacted.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        redacted.push('#');
+        redacted.push_str(fragment);
+    }
+    redacted
+}
+
+const CSV_HEADER: &str = "timestamp,filename,source_url,size,checksum,duration_ms";
+
+/// Quote `value` for CSV when it holds a comma, quote or line break, doubling any quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render `records` as CSV with a header row. Timestamps are RFC 3339 in UTC and URLs
+/// are redacted again in case a record predates redaction on append.
+pub fn records_to_csv(records: &[DownloadRecord]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+    for record in records {
+        let timestamp = record
+            .timestamp
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default();
+        let filename = Path::new(&record.target_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| record.target_path.clone());
+        let fields = [
+            timestamp,
+            filename,
+            redact_url(&record.source_url),
+            record.size.to_string(),
+            record.checksum.clone().unwrap_or_default(),
+            record.duration_ms.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Append-only JSON-lines log of completed downloads
+pub struct DownloadLedger {
+    path: PathBuf,
+}
+
+impl DownloadLedger {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, LedgerError> {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| LedgerError::Io(std::io::Error::other(e)))?;
+        fs::create_dir_all(&data_dir)?;
+
+        Ok(Self::from_path(data_dir.join("download-ledger.jsonl")))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        DownloadLedger { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a download, redacting the source URL before it touches disk
+    pub fn append(&self, mut record: DownloadRecord) -> Result<DownloadRecord, LedgerError> {
+        record.source_url = redact_url(&record.source_url);
+        if record.timestamp.is_none() {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            record.timestamp = Some(now);
+        }
+
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // One write per record so concurrent appends don't interleave
+        file.write_all(line.as_bytes())?;
+        Ok(record)
+    }
+
+    /// Most recent records first, at most `limit` of them
+    pub fn history(&self, limit: usize) -> Result<Vec<DownloadRecord>, LedgerError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        let records = contents
+            .lines()
+            .rev()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<DownloadRecord>(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    log::warn!("Skipping malformed ledger entry: {}", e);
+                    None
+                }
+            })
+            .take(limit)
+            .collect();
+        Ok(records)
+    }
+
+    /// Write the whole history, oldest first, to `destination` as CSV. Returns the
+    /// number of records written.
+    pub fn export_csv(&self, destination: &Path) -> Result<usize, LedgerError> {
+        let mut records = self.history(usize::MAX)?;
+        records.reverse();
+        fs::write(destination, records_to_csv(&records))?;
+        Ok(records.len())
+    }
+}
+
+#[tauri::command]
+pub async fn append_download_record(
+    app_handle: AppHandle,
+    record: DownloadRecord,
+) -> Result<DownloadRecord, String> {
+    let ledger = DownloadLedger::new(&app_handle).map_err(|e| e.to_string())?;
+    ledger.append(record).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_download_history(
+    app_handle: AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<DownloadRecord>, String> {
+    let ledger = DownloadLedger::new(&app_handle).map_err(|e| e.to_string())?;
+    ledger
+        .history(limit.unwrap_or(usize::MAX))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_download_history_csv(app_handle: AppHandle, path: String) -> Result<(), String> {
+    let ledger = DownloadLedger::new(&app_handle).map_err(|e| e.to_string())?;
+    ledger
+        .export_csv(Path::new(&path))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn unique_ledger_path(label: &str) -> PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        env::temp_dir().join(format!("s3-upload-tool-{}-{}.jsonl", label, timestamp))
+    }
+
+    fn record(url: &str, target: &str) -> DownloadRecord {
+        DownloadRecord {
+            timestamp: None,
+            source_url: url.to_string(),
+            target_path: target.to_string(),
+            size: 42,
+            checksum: Some("abc123".to_string()),
+            duration_ms: 1500,
+        }
+    }
+
+    #[test]
+    fn test_presigned_url_is_stored_redacted() {
+        let ledger = DownloadLedger::from_path(unique_ledger_path("ledger-redact"));
+        let url = "https://bucket.s3.amazonaws.com/report.pdf?versionId=7&X-Amz-Algorithm=AWS4-HMAC-SHA256\
+                   &X-Amz-Credential=AKIAEXAMPLE%2F20240101&X-Amz-Signature=deadbeef&x-amz-security-token=tok";
+
+        ledger.append(record(url, "/downloads/report.pdf")).unwrap();
+
+        let stored = fs::read_to_string(ledger.path()).unwrap();
+        assert!(!stored.contains("AKIAEXAMPLE"));
+        assert!(!stored.contains("deadbeef"));
+        assert!(!stored.contains("tok"));
+
+        let history = ledger.history(10).unwrap();
+        assert_eq!(
+            history[0].source_url,
+            "https://bucket.s3.amazonaws.com/report.pdf?versionId=7"
+        );
+        assert!(history[0].timestamp.is_some());
+
+        fs::remove_file(ledger.path()).unwrap();
+    }
+
+    #[test]
+    fn test_csv_export_escapes_fields() {
+        let ledger = DownloadLedger::from_path(unique_ledger_path("ledger-csv"));
+        let mut first = record("https://example.com/a.csv?X-Amz-Signature=secret", "/downloads/q1, \"final\".csv");
+        first.timestamp = Some(0);
+        ledger.append(first).unwrap();
+        let mut second = record("https://example.com/b", "/downloads/notes\nv2.txt");
+        second.timestamp = Some(86_400);
+        second.checksum = None;
+        ledger.append(second).unwrap();
+
+        let destination = unique_ledger_path("ledger-csv-export");
+        assert_eq!(ledger.export_csv(&destination).unwrap(), 2);
+
+        let csv = fs::read_to_string(&destination).unwrap();
+        assert_eq!(
+            csv,
+            "timestamp,filename,source_url,size,checksum,duration_ms\r\n\
+             1970-01-01T00:00:00+00:00,\"q1, \"\"final\"\".csv\",https://example.com/a.csv,42,abc123,1500\r\n\
+             1970-01-02T00:00:00+00:00,\"notes\nv2.txt\",https://example.com/b,42,,1500\r\n"
+        );
+        assert!(!csv.contains("secret"));
+
+        fs::remove_file(ledger.path()).unwrap();
+        fs::remove_file(&destination).unwrap();
+    }
+
+    #[test]
+    fn test_history_is_newest_first() {
+        let ledger = DownloadLedger::from_path(unique_ledger_path("ledger-history"));
+        assert!(ledger.history(10).unwrap().is_empty());
+
+        for name in ["first", "second", "third"] {
+            ledger
+                .append(record(&format!("https://example.com/{}", name), name))
+                .unwrap();
+        }
+
+        let targets: Vec<String> = ledger
+            .history(2)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.target_path)
+            .collect();
+        assert_eq!(targets, vec!["third", "second"]);
+
+        fs::remove_file(ledger.path()).unwrap();
+    }
+}
@@ -1,35 +1,156 @@
+pub mod audit;
+pub mod build_info;
+pub mod clipboard;
 pub mod config;
+pub mod config_watch;
 pub mod download;
+pub mod http_download;
+pub mod ledger;
+pub mod regions;
+pub mod upload;
+
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_dialog::init())
+    .manage(download::DownloadQueue::default())
     .invoke_handler(tauri::generate_handler![
       config::save_config,
+      config::check_password_policy,
       config::load_config,
+      config::migrate_config,
+      config::post_migration_verify,
       config::config_exists,
       config::delete_config,
+      config::harden_config_permissions,
       config::export_config,
+      config::export_config_as,
+      config::export_config_redacted,
+      config::clear_config_field,
       config::import_config,
+      config::import_config_checked,
+      config::validate_config,
+      regions::list_aws_regions,
+      regions::is_valid_region,
+      build_info::get_build_info,
+      config::backup_config_to,
+      config::restore_config_from,
+      config::verify_all_backups,
+      config::save_vault,
+      config::load_vault,
+      config::list_vault_profiles,
+      config::rename_vault_profile,
+      config::get_profile_labels,
+      config::upgrade_all_profiles,
+      config::set_password_hint,
+      config::get_password_hint,
+      config_watch::watch_config_changes,
+      config::migrate_config_to_vault,
+      config::get_active_config_crypto_info,
+      config::config_fingerprint,
+      config::config_file_hash,
+      config::get_crypto_capabilities,
+      config::estimate_encryption_time,
+      config::check_rng_health,
+      config::warmup_crypto,
+      config::save_preferences,
+      config::load_preferences,
       config::select_export_path,
       config::select_import_path,
       download::select_download_path,
       download::select_download_directory,
       download::validate_download_path,
+      download::validate_download_path_detailed,
       download::check_file_exists,
       download::get_file_size,
+      download::get_formatted_file_size,
+      download::get_download_eta,
+      download::predict_download_duration,
+      download::set_io_command_timeout,
       download::get_default_download_path,
+      download::truncate_filename,
+      download::aggregate_download_progress,
+      download::get_downloads_directory,
       download::generate_unique_filename,
+      download::create_exclusive_file,
+      download::suggest_extension_for_mime_type,
+      download::path_collides_case_insensitive,
+      download::check_mount_writable,
       download::create_directory,
       download::check_disk_space,
+      download::get_filesystem_block_size,
+      download::check_free_inodes,
+      download::check_filesystem_max_file_size,
+      download::estimate_batch_space,
+      download::same_filesystem,
+      download::find_existing_by_checksum,
+      download::is_download_complete,
+      download::verify_download_size,
+      download::verify_zip_integrity,
+      download::copy_file,
+      download::link_or_copy,
+      download::calculate_multiple_checksums,
       download::write_file_chunk,
       download::read_file_chunk,
+      download::begin_file_write,
+      download::finish_file_write,
+      download::read_file_head,
+      download::detect_text_format,
+      download::read_file_tail,
+      download::read_file_ranges,
+      download::is_file_stable,
+      download::encode_file_base64,
+      download::write_base64_to_file,
       download::calculate_file_checksum,
+      download::calculate_block_checksums,
+      download::compare_block_checksums,
+      download::cancel,
+      download::cancel_queued_download,
+      download::get_queued_download_state,
+      download::calculate_file_checksum_mmap,
       download::get_file_metadata,
+      download::get_file_verification_info,
+      download::delete_files,
+      download::list_directory,
+      download::list_directory_recursive,
+      download::get_directory_size,
+      download::cleanup_temp_files,
+      download::set_temp_prefix,
+      download::find_duplicate_files,
+      download::calculate_checksums_batch,
+      download::set_checksum_memory_limit,
+      clipboard::clear_clipboard_after,
+      clipboard::on_app_suspend,
+      clipboard::on_app_resume,
+      http_download::download_manifest,
+      http_download::download_to_file,
+      http_download::queue_download_to_file,
+      http_download::verify_partial_download,
+      http_download::is_download_marked_done,
+      http_download::relocate_downloads,
+      ledger::append_download_record,
+      ledger::get_download_history,
+      ledger::export_download_history_csv,
+      upload::recommend_part_size,
+      upload::prepare_upload_parts,
+      upload::verify_s3_etag,
+      upload::read_upload_part,
+      upload::split_file,
+      upload::join_files,
     ])
     .setup(|app| {
+      if let Ok(config_dir) = app.path().app_config_dir() {
+        download::restore_temp_file_prefix(&config_dir);
+      }
+      // Take the cold-start cost of key derivation before the first save or load
+      tauri::async_runtime::spawn_blocking(|| {
+        if let Err(e) = config::warm_up_crypto() {
+          log::warn!("Crypto warm-up failed: {}", e);
+        }
+      });
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
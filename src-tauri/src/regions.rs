@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Known AWS regions as (code, display name). Add new regions here.
+pub const AWS_REGIONS: &[(&str, &str)] = &[
+    ("af-south-1", "Africa (Cape Town)"),
+    ("ap-east-1", "Asia Pacific (Hong Kong)"),
+    ("ap-northeast-1", "Asia Pacific (Tokyo)"),
+    ("ap-northeast-2", "Asia Pacific (Seoul)"),
+    ("ap-northeast-3", "Asia Pacific (Osaka)"),
+    ("ap-south-1", "Asia Pacific (Mumbai)"),
+    ("ap-south-2", "Asia Pacific (Hyderabad)"),
+    ("ap-southeast-1", "Asia Pacific (Singapore)"),
+    ("ap-southeast-2", "Asia Pacific (Sydney)"),
+    ("ap-southeast-3", "Asia Pacific (Jakarta)"),
+    ("ap-southeast-4", "Asia Pacific (Melbourne)"),
+    ("ca-central-1", "Canada (Central)"),
+    ("ca-west-1", "Canada West (Calgary)"),
+    ("eu-central-1", "Europe (Frankfurt)"),
+    ("eu-central-2", "Europe (Zurich)"),
+    ("eu-north-1", "Europe (Stockholm)"),
+    ("eu-south-1", "Europe (Milan)"),
+    ("eu-south-2", "Europe (Spain)"),
+    ("eu-west-1", "Europe (Ireland)"),
+    ("eu-west-2", "Europe (London)"),
+    ("eu-west-3", "Europe (Paris)"),
+    ("il-central-1", "Israel (Tel Aviv)"),
+    ("me-central-1", "Middle East (UAE)"),
+    ("me-south-1", "Middle East (Bahrain)"),
+    ("sa-east-1", "South America (São Paulo)"),
+    ("us-east-1", "US East (N. Virginia)"),
+    ("us-east-2", "US East (Ohio)"),
+    ("us-gov-east-1", "AWS GovCloud (US-East)"),
+    ("us-gov-west-1", "AWS GovCloud (US-West)"),
+    ("us-west-1", "US West (N. California)"),
+    ("us-west-2", "US West (Oregon)"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionInfo {
+    pub code: String,
+    pub name: String,
+}
+
+pub fn aws_regions() -> Vec<RegionInfo> {
+    AWS_REGIONS
+        .iter()
+        .map(|(code, name)| RegionInfo {
+            code: code.to_string(),
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+/// Whether `code` is one of the known AWS regions
+pub fn is_known_region(code: &str) -> bool {
+    AWS_REGIONS.iter().any(|(known, _)| *known == code)
+}
+
+#[tauri::command]
+pub fn list_aws_regions() -> Vec<RegionInfo> {
+    aws_regions()
+}
+
+#[tauri::command]
+pub fn is_valid_region(code: String) -> bool {
+    is_known_region(&code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_regions_listed_and_validated() {
+        let regions = aws_regions();
+        for code in ["us-east-1", "eu-west-1", "ap-southeast-2"] {
+            assert!(regions.iter().any(|region| region.code == code));
+            assert!(is_known_region(code));
+        }
+        assert_eq!(
+            regions.iter().find(|region| region.code == "us-east-1").map(|region| region.name.as_str()),
+            Some("US East (N. Virginia)")
+        );
+
+        assert!(!is_known_region("us-middle-7"));
+        assert!(!is_known_region("US-EAST-1"));
+        assert!(!is_known_region(""));
+    }
+}
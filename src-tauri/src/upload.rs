@@ -0,0 +1,572 @@
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Size of each read while hashing parts
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+const MIB: u64 = 1024 * 1024;
+/// S3's smallest allowed part, except for the last one
+pub const MIN_PART_SIZE: u64 = 5 * MIB;
+/// S3's limit on parts per multipart upload
+pub const MAX_PARTS: u64 = 10_000;
+/// S3's largest object
+pub const MAX_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * MIB;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid part size: {0}")]
+    InvalidPartSize(u64),
+    #[error("Invalid part number: {0}")]
+    InvalidPartNumber(u32),
+    #[error("Part {0} starts past the end of the file")]
+    PartOutOfRange(u32),
+    #[error("Invalid parts: {0}")]
+    InvalidParts(String),
+    #[error("File of {0} bytes exceeds the 5 TiB S3 object limit")]
+    FileTooLarge(u64),
+    #[error("Invalid ETag: {0}")]
+    InvalidEtag(String),
+}
+
+/// One part of a multipart upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadPart {
+    /// 1-based, as S3 numbers parts
+    pub part_number: u32,
+    pub offset: u64,
+    pub length: u64,
+    /// Hex-encoded MD5 of the part's bytes
+    pub md5: String,
+}
+
+/// Smallest part size, a whole number of MiB and at least 5 MiB, that uploads
+/// `file_size` bytes in at most 10,000 parts
+pub fn minimum_part_size(file_size: u64) -> Result<u64, UploadError> {
+    if file_size > MAX_OBJECT_SIZE {
+        return Err(UploadError::FileTooLarge(file_size));
+    }
+    let needed = file_size.div_ceil(MAX_PARTS).div_ceil(MIB) * MIB;
+    Ok(needed.max(MIN_PART_SIZE))
+}
+
+/// Split the file at `path` into `part_size` parts, hashing each one while reading the
+/// file once. The final part holds whatever remains and may be shorter.
+pub fn compute_upload_parts(path: &Path, part_size: u64) -> Result<Vec<UploadPart>, UploadError> {
+    if part_size == 0 {
+        return Err(UploadError::InvalidPartSize(part_size));
+    }
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    let mut parts = Vec::new();
+    let mut offset = 0u64;
+
+    loop {
+        let mut hasher = Md5::new();
+        let mut length = 0u64;
+
+        while length < part_size {
+            let wanted = (part_size - length).min(buffer.len() as u64) as usize;
+            let read = file.read(&mut buffer[..wanted])?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            length += read as u64;
+        }
+
+        // Stop at EOF, but an empty file still gets a single empty part
+        if length == 0 && !parts.is_empty() {
+            break;
+        }
+
+        parts.push(UploadPart {
+            part_number: parts.len() as u32 + 1,
+            offset,
+            length,
+            md5: format!("{:x}", hasher.finalize()),
+        });
+        offset += length;
+
+        if length < part_size {
+            break;
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Hex MD5 of the whole file, which is the ETag S3 gives a plainly uploaded object
+fn file_md5_hex(path: &Path) -> Result<String, UploadError> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    let mut hasher = Md5::new();
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// ETag S3 gives an object uploaded in `part_size` parts: the MD5 of the concatenated
+/// binary part MD5s, then `-` and the number of parts
+pub fn multipart_etag(path: &Path, part_size: u64) -> Result<String, UploadError> {
+    let parts = compute_upload_parts(path, part_size)?;
+    let mut combined = Md5::new();
+    for part in &parts {
+        for pair in part.md5.as_bytes().chunks(2) {
+            let byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .unwrap_or_default();
+            combined.update([byte]);
+        }
+    }
+    Ok(format!("{:x}-{}", combined.finalize(), parts.len()))
+}
+
+/// Part sizes worth trying for a multipart ETag of `part_count` parts when the uploader's
+/// choice is unknown: common client defaults, the recommended size, and the smallest
+/// whole-MiB size giving that count
+fn candidate_part_sizes(file_size: u64, part_count: u64) -> Vec<u64> {
+    let mut sizes = vec![
+        MIN_PART_SIZE,
+        8 * MIB,
+        16 * MIB,
+        file_size.div_ceil(part_count).div_ceil(MIB) * MIB,
+    ];
+    if let Ok(recommended) = minimum_part_size(file_size) {
+        sizes.push(recommended);
+    }
+    sizes.retain(|&size| size > 0 && file_size.div_ceil(size).max(1) == part_count);
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// Whether `path` holds the object with S3 ETag `etag`, quoted or not. A plain ETag is
+/// the file's MD5; a multipart one (`<hex>-<parts>`) is recomputed with `part_size`, or
+/// with a few likely part sizes when that isn't known. Objects encrypted with SSE-KMS
+/// or SSE-C have ETags unrelated to their content and never match.
+pub fn file_matches_etag(path: &Path, etag: &str, part_size: Option<u64>) -> Result<bool, UploadError> {
+    let etag = etag.trim().trim_matches('"').to_ascii_lowercase();
+    let Some((_, count)) = etag.split_once('-') else {
+        return Ok(file_md5_hex(path)? == etag);
+    };
+    let part_count: u64 = count
+        .parse()
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| UploadError::InvalidEtag(etag.clone()))?;
+
+    let sizes = match part_size {
+        Some(part_size) => vec![part_size],
+        None => candidate_part_sizes(std::fs::metadata(path)?.len(), part_count),
+    };
+    for size in sizes {
+        if multipart_etag(path, size)? == etag {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Read part `part_number` (1-based) of `part_size` bytes, matching the parts from
+/// `compute_upload_parts`. The final part may come back shorter.
+pub async fn read_part(path: &Path, part_number: u32, part_size: u64) -> Result<Vec<u8>, UploadError> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+    if part_number == 0 {
+        return Err(UploadError::InvalidPartNumber(part_number));
+    }
+    if part_size == 0 {
+        return Err(UploadError::InvalidPartSize(part_size));
+    }
+
+    let offset = (part_number as u64 - 1)
+        .checked_mul(part_size)
+        .ok_or(UploadError::PartOutOfRange(part_number))?;
+    let mut file = tokio::fs::File::open(path).await?;
+    let file_size = file.metadata().await?.len();
+    // An empty file still has its single empty first part
+    if offset >= file_size && !(offset == 0 && file_size == 0) {
+        return Err(UploadError::PartOutOfRange(part_number));
+    }
+
+    file.seek(SeekFrom::Start(offset)).await?;
+    let length = part_size.min(file_size - offset);
+    let mut buffer = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// Suffix of the `n`th piece written by `split_file_into`, e.g. `.part0001`
+fn part_suffix(part_number: u32) -> String {
+    format!(".part{:04}", part_number)
+}
+
+/// Split `path` into `part_size` pieces named `<name>.part0001`, `<name>.part0002`, ...
+/// inside `output_dir`, returning the piece paths in order
+pub fn split_file_into(path: &Path, part_size: u64, output_dir: &Path) -> Result<Vec<PathBuf>, UploadError> {
+    if part_size == 0 {
+        return Err(UploadError::InvalidPartSize(part_size));
+    }
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| UploadError::InvalidParts(format!("Not a file: {}", path.display())))?
+        .to_string_lossy()
+        .to_string();
+
+    let mut source = File::open(path)?;
+    let file_size = source.metadata()?.len();
+    if file_size == 0 {
+        return Err(UploadError::InvalidParts(format!("{} is empty", path.display())));
+    }
+    let part_count = u32::try_from(file_size.div_ceil(part_size)).map_err(|_| {
+        UploadError::InvalidParts(format!(
+            "{} bytes in {} byte pieces needs more than {} pieces",
+            file_size,
+            part_size,
+            u32::MAX
+        ))
+    })?;
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut pieces = Vec::with_capacity(part_count as usize);
+    for part_number in 1..=part_count {
+        let piece_path = output_dir.join(format!("{}{}", file_name, part_suffix(part_number)));
+        let mut piece = File::create(&piece_path)?;
+        std::io::copy(&mut (&mut source).take(part_size), &mut piece)?;
+        piece.sync_all()?;
+        pieces.push(piece_path);
+    }
+    Ok(pieces)
+}
+
+/// Part number encoded in a `.partNNNN` suffix, along with the path before it
+fn parse_piece_path(piece: &Path) -> Option<(String, u32)> {
+    let path = piece.to_string_lossy();
+    let (base, number) = path.rsplit_once(".part")?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((base.to_string(), number.parse().ok()?))
+}
+
+/// Concatenate pieces made by `split_file_into` back into `output_path`. The pieces
+/// must share a base name, be numbered 1..=n in order and each hold some data.
+pub fn join_file_parts(parts: &[PathBuf], output_path: &Path) -> Result<(), UploadError> {
+    if parts.is_empty() {
+        return Err(UploadError::InvalidParts("No parts given".to_string()));
+    }
+
+    let mut base_name = None;
+    for (index, part) in parts.iter().enumerate() {
+        let (base, number) = parse_piece_path(part).ok_or_else(|| {
+            UploadError::InvalidParts(format!("{} is not a .partNNNN file", part.display()))
+        })?;
+        if *base_name.get_or_insert_with(|| base.clone()) != base {
+            return Err(UploadError::InvalidParts(format!(
+                "{} belongs to a different file",
+                part.display()
+            )));
+        }
+        if number != index as u32 + 1 {
+            return Err(UploadError::InvalidParts(format!(
+                "Expected part {} but found {}",
+                index + 1,
+                part.display()
+            )));
+        }
+        if std::fs::metadata(part)?.len() == 0 {
+            return Err(UploadError::InvalidParts(format!("{} is empty", part.display())));
+        }
+    }
+
+    let temp_path = crate::download::temp_path_for(output_path);
+
+    let mut output = File::create(&temp_path)?;
+    for part in parts {
+        std::io::copy(&mut File::open(part)?, &mut output)?;
+    }
+    output.flush()?;
+    output.sync_all()?;
+    drop(output);
+
+    std::fs::rename(&temp_path, output_path)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn recommend_part_size(file_size: u64) -> Result<u64, String> {
+    minimum_part_size(file_size).map_err(|e| e.to_string())
+}
+
+/// Split `path` into upload parts, using the recommended part size when none is given
+#[tauri::command]
+pub async fn prepare_upload_parts(path: String, part_size: Option<u64>) -> Result<Vec<UploadPart>, String> {
+    tokio::task::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let part_size = match part_size {
+            Some(part_size) => part_size,
+            None => minimum_part_size(std::fs::metadata(path).map_err(|e| e.to_string())?.len())
+                .map_err(|e| e.to_string())?,
+        };
+        compute_upload_parts(path, part_size).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Compare a local file with an S3 object's ETag, single-part or multipart
+#[tauri::command]
+pub async fn verify_s3_etag(path: String, etag: String, part_size: Option<u64>) -> Result<bool, String> {
+    tokio::task::spawn_blocking(move || {
+        file_matches_etag(Path::new(&path), &etag, part_size).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn read_upload_part(path: String, part_number: u32, part_size: u64) -> Result<Vec<u8>, String> {
+    read_part(Path::new(&path), part_number, part_size)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn split_file(path: String, part_size: u64, output_dir: String) -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(move || {
+        split_file_into(Path::new(&path), part_size, Path::new(&output_dir))
+            .map(|pieces| {
+                pieces
+                    .into_iter()
+                    .map(|piece| piece.to_string_lossy().to_string())
+                    .collect()
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn join_files(parts: Vec<String>, output_path: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let parts: Vec<PathBuf> = parts.into_iter().map(PathBuf::from).collect();
+        join_file_parts(&parts, Path::new(&output_path)).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn unique_test_file(label: &str, contents: &[u8]) -> PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = env::temp_dir().join(format!("s3-upload-tool-{}-{}", label, timestamp));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn md5_hex(data: &[u8]) -> String {
+        format!("{:x}", Md5::digest(data))
+    }
+
+    #[test]
+    fn test_minimum_part_size_boundaries() {
+        assert_eq!(minimum_part_size(0).unwrap(), MIN_PART_SIZE);
+        assert_eq!(minimum_part_size(MIN_PART_SIZE + 1).unwrap(), MIN_PART_SIZE);
+        assert_eq!(minimum_part_size(MIN_PART_SIZE * MAX_PARTS).unwrap(), MIN_PART_SIZE);
+
+        // One byte more no longer fits in 10,000 parts of 5 MiB
+        let size = MIN_PART_SIZE * MAX_PARTS + 1;
+        let part_size = minimum_part_size(size).unwrap();
+        assert_eq!(part_size, 6 * MIB);
+        assert!(size.div_ceil(part_size) <= MAX_PARTS);
+
+        let part_size = minimum_part_size(MAX_OBJECT_SIZE).unwrap();
+        assert_eq!(part_size % MIB, 0);
+        assert!(MAX_OBJECT_SIZE.div_ceil(part_size) <= MAX_PARTS);
+        assert!(MAX_OBJECT_SIZE.div_ceil(part_size - MIB) > MAX_PARTS);
+
+        assert!(matches!(
+            minimum_part_size(MAX_OBJECT_SIZE + 1),
+            Err(UploadError::FileTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_upload_parts_boundaries_and_md5() {
+        // 2.5 parts, with a part size that isn't a multiple of the read buffer
+        let part_size = HASH_BUFFER_SIZE as u64 + 100;
+        let contents: Vec<u8> = (0..(part_size * 5 / 2)).map(|i| (i % 251) as u8).collect();
+        let path = unique_test_file("upload-parts", &contents);
+
+        let parts = compute_upload_parts(&path, part_size).unwrap();
+
+        assert_eq!(parts.len(), 3);
+        let mut expected_offset = 0;
+        for (index, part) in parts.iter().enumerate() {
+            assert_eq!(part.part_number, index as u32 + 1);
+            assert_eq!(part.offset, expected_offset);
+            let start = part.offset as usize;
+            let end = start + part.length as usize;
+            assert_eq!(part.md5, md5_hex(&contents[start..end]));
+            expected_offset += part.length;
+        }
+        assert_eq!(parts[0].length, part_size);
+        assert_eq!(parts[1].length, part_size);
+        assert_eq!(parts[2].length, part_size / 2);
+        assert_eq!(expected_offset, contents.len() as u64);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_upload_parts_exact_multiple_and_empty() {
+        let path = unique_test_file("upload-parts-exact", &[7u8; 20]);
+        let parts = compute_upload_parts(&path, 10).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].offset, 10);
+        assert_eq!(parts[1].length, 10);
+        fs::remove_file(&path).unwrap();
+
+        let path = unique_test_file("upload-parts-empty", b"");
+        let parts = compute_upload_parts(&path, 10).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].length, 0);
+        assert_eq!(parts[0].md5, md5_hex(b""));
+        assert!(matches!(
+            compute_upload_parts(&path, 0),
+            Err(UploadError::InvalidPartSize(0))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_upload_parts() {
+        let contents: Vec<u8> = (0..25u8).collect();
+        let path = unique_test_file("read-upload-part", &contents);
+
+        assert_eq!(read_part(&path, 1, 10).await.unwrap(), &contents[0..10]);
+        assert_eq!(read_part(&path, 2, 10).await.unwrap(), &contents[10..20]);
+        assert_eq!(read_part(&path, 3, 10).await.unwrap(), &contents[20..25]);
+
+        assert!(matches!(
+            read_part(&path, 4, 10).await,
+            Err(UploadError::PartOutOfRange(4))
+        ));
+        assert!(matches!(
+            read_part(&path, 0, 10).await,
+            Err(UploadError::InvalidPartNumber(0))
+        ));
+
+        // Parts line up with the ones prepare_upload_parts describes
+        for part in compute_upload_parts(&path, 10).unwrap() {
+            let data = read_part(&path, part.part_number, 10).await.unwrap();
+            assert_eq!(data.len() as u64, part.length);
+            assert_eq!(md5_hex(&data), part.md5);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_matches_simple_and_multipart_etags() {
+        let contents: Vec<u8> = (0..2_500u32).map(|i| (i % 251) as u8).collect();
+        let path = unique_test_file("etag.bin", &contents);
+
+        let simple = format!("\"{}\"", md5_hex(&contents).to_uppercase());
+        assert!(file_matches_etag(&path, &simple, None).unwrap());
+        assert!(!file_matches_etag(&path, &md5_hex(b"other"), None).unwrap());
+
+        let mut part_digests = Vec::new();
+        for chunk in contents.chunks(1_000) {
+            part_digests.extend_from_slice(&Md5::digest(chunk));
+        }
+        let multipart = format!("\"{}-3\"", md5_hex(&part_digests));
+        assert!(file_matches_etag(&path, &multipart, Some(1_000)).unwrap());
+        assert!(!file_matches_etag(&path, &multipart, Some(2_000)).unwrap());
+
+        // A one-part multipart upload is found without knowing the part size
+        let one_part = format!("{}-1", md5_hex(&Md5::digest(&contents)));
+        assert!(file_matches_etag(&path, &one_part, None).unwrap());
+
+        assert!(matches!(
+            file_matches_etag(&path, "abc-x", None),
+            Err(UploadError::InvalidEtag(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_split_then_join_round_trip() {
+        let contents: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let source = unique_test_file("split-source.bin", &contents);
+        let output_dir = unique_test_file("split-output", b"");
+        fs::remove_file(&output_dir).unwrap();
+
+        let pieces = split_file_into(&source, 3_000, &output_dir).unwrap();
+
+        assert_eq!(pieces.len(), 4);
+        let source_name = source.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            pieces[0].file_name().unwrap().to_string_lossy(),
+            format!("{}.part0001", source_name)
+        );
+        assert_eq!(fs::metadata(&pieces[3]).unwrap().len(), 1_000);
+
+        let joined = output_dir.join("joined.bin");
+        join_file_parts(&pieces, &joined).unwrap();
+        assert_eq!(fs::read(&joined).unwrap(), contents);
+
+        // Out-of-order or missing pieces are refused
+        let shuffled = vec![pieces[1].clone(), pieces[0].clone()];
+        assert!(matches!(
+            join_file_parts(&shuffled, &joined),
+            Err(UploadError::InvalidParts(_))
+        ));
+        assert!(matches!(
+            join_file_parts(&pieces[..1].iter().chain(&pieces[2..]).cloned().collect::<Vec<_>>(), &joined),
+            Err(UploadError::InvalidParts(_))
+        ));
+
+        fs::remove_dir_all(&output_dir).unwrap();
+        fs::remove_file(&source).unwrap();
+    }
+
+    #[test]
+    fn test_split_refuses_more_pieces_than_part_numbers() {
+        // Sparse, so no data is actually written
+        let source = unique_test_file("split-huge.bin", b"");
+        File::options().write(true).open(&source).unwrap().set_len(u32::MAX as u64 + 1).unwrap();
+        let output_dir = unique_test_file("split-huge-output", b"");
+        fs::remove_file(&output_dir).unwrap();
+
+        assert!(matches!(
+            split_file_into(&source, 1, &output_dir),
+            Err(UploadError::InvalidParts(_))
+        ));
+        assert!(!output_dir.exists());
+
+        fs::remove_file(&source).unwrap();
+    }
+}